@@ -1,8 +1,11 @@
 extern crate gl_generator;
 
+mod ltc_fit;
+
 use gl_generator::{Registry, Api, Profile, Fallbacks, GlobalGenerator};
 use std::env;
 use std::fs::File;
+use std::io::Write;
 use std::path::Path;
  
  
@@ -27,9 +30,34 @@ fn register_gl_api(file: &mut File) {
         .unwrap();
 }
  
+/// Fit the area light lookup tables and write them out as Rust source code.
+fn write_ltc_tables(file: &mut File) {
+    let tables = ltc_fit::fit_ltc_tables();
+    let size = ltc_fit::LTC_TABLE_SIZE;
+
+    writeln!(file, "pub const LTC_TABLE_SIZE: usize = {};", size).unwrap();
+    writeln!(file, "pub static LTC_INVERSE_MATRICES: [[f32; 4]; {}] = [", size * size).unwrap();
+    for m in tables.inverse_matrices.iter() {
+        writeln!(file, "    [{:?}, {:?}, {:?}, {:?}],", m[0], m[1], m[2], m[3]).unwrap();
+    }
+    writeln!(file, "];").unwrap();
+    writeln!(file, "pub static LTC_MAGNITUDES: [f32; {}] = [", size * size).unwrap();
+    for magnitude in tables.magnitudes.iter() {
+        writeln!(file, "    {:?},", magnitude).unwrap();
+    }
+    writeln!(file, "];").unwrap();
+}
+ 
 fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=ltc_fit.rs");
+
     let dest = env::var("OUT_DIR").unwrap();
     let mut file = File::create(&Path::new(&dest).join("gl_bindings.rs")).unwrap();
 
     register_gl_api(&mut file);
+
+    let mut file = File::create(&Path::new(&dest).join("ltc_tables.rs")).unwrap();
+
+    write_ltc_tables(&mut file);
 }
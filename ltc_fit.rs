@@ -0,0 +1,412 @@
+//! Fit the lookup tables for shading area lights with linearly transformed cosines (LTC)
+//! to the Blinn-Phong lobe used by the mesh shader. The fitting procedure follows
+//! `Heitz, Dupuy, Hill, Neubelt, Real-Time Polygonal-Light Shading with Linearly
+//! Transformed Cosines, 2016`, and its reference implementation. The tables are indexed by
+//! the roughness of the lobe and by `sqrt(1 - cos(theta))`, where `theta` is the angle
+//! between the view direction and the surface normal.
+use std::f64::consts::PI;
+
+
+/// The number of rows and columns in each table.
+pub const LTC_TABLE_SIZE: usize = 32;
+/// The number of samples along each axis used to estimate the fitting error.
+const NUM_SAMPLES: usize = 16;
+/// The smallest lobe width the tables represent. Narrower lobes are numerically degenerate.
+const MIN_ALPHA: f64 = 0.0001;
+
+
+type Vec3 = [f64; 3];
+/// A 3x3 matrix stored in column major order.
+type Mat3 = [[f64; 3]; 3];
+
+#[inline]
+fn add(v: Vec3, w: Vec3) -> Vec3 {
+    [v[0] + w[0], v[1] + w[1], v[2] + w[2]]
+}
+
+#[inline]
+fn scale(s: f64, v: Vec3) -> Vec3 {
+    [s * v[0], s * v[1], s * v[2]]
+}
+
+#[inline]
+fn dot(v: Vec3, w: Vec3) -> f64 {
+    v[0] * w[0] + v[1] * w[1] + v[2] * w[2]
+}
+
+#[inline]
+fn length(v: Vec3) -> f64 {
+    dot(v, v).sqrt()
+}
+
+#[inline]
+fn normalize(v: Vec3) -> Vec3 {
+    scale(1.0 / length(v), v)
+}
+
+#[inline]
+fn mul(m: &Mat3, v: Vec3) -> Vec3 {
+    [
+        m[0][0] * v[0] + m[1][0] * v[1] + m[2][0] * v[2],
+        m[0][1] * v[0] + m[1][1] * v[1] + m[2][1] * v[2],
+        m[0][2] * v[0] + m[1][2] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+fn mul_mat(a: &Mat3, b: &Mat3) -> Mat3 {
+    [mul(a, b[0]), mul(a, b[1]), mul(a, b[2])]
+}
+
+fn determinant(m: &Mat3) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[2][1] * m[1][2])
+        - m[1][0] * (m[0][1] * m[2][2] - m[2][1] * m[0][2])
+        + m[2][0] * (m[0][1] * m[1][2] - m[1][1] * m[0][2])
+}
+
+fn inverse(m: &Mat3) -> Mat3 {
+    let det_inv = 1.0 / determinant(m);
+    let mut result = [[0.0; 3]; 3];
+    for c in 0..3 {
+        for r in 0..3 {
+            // The inverse is the transposed matrix of cofactors divided by the determinant.
+            let (c0, c1) = ((r + 1) % 3, (r + 2) % 3);
+            let (r0, r1) = ((c + 1) % 3, (c + 2) % 3);
+            result[c][r] = (m[c0][r0] * m[c1][r1] - m[c1][r0] * m[c0][r1]) * det_inv;
+        }
+    }
+
+    result
+}
+
+/// The specular exponent of the Blinn-Phong lobe with the same width as a
+/// Beckmann lobe of width `alpha`, c.f. `Walter et al., Microfacet Models for
+/// Refraction through Rough Surfaces, 2007`.
+#[inline]
+fn specular_exponent(alpha: f64) -> f64 {
+    2.0 / (alpha * alpha) - 2.0
+}
+
+/// The Blinn-Phong lobe we fit the tables to. The half vector is distributed as
+/// `(n + 1) / (2 * pi) * cos(theta_h)^n`, and the lobe is chosen so that the BRDF times the
+/// cosine term equals the density of the reflected directions above the horizon.
+/// Returns the value of the BRDF times the cosine term, and the sampling density of `l`.
+fn brdf_eval(v: Vec3, l: Vec3, alpha: f64) -> (f64, f64) {
+    if v[2] <= 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let n = specular_exponent(alpha);
+    let h = normalize(add(v, l));
+    let density_h = (n + 1.0) / (2.0 * PI) * h[2].max(0.0).powf(n);
+    let density_l = density_h / (4.0 * dot(v, h).abs().max(1e-8));
+    let value = if l[2] <= 0.0 { 0.0 } else { density_l };
+
+    (value, density_l)
+}
+
+/// Importance sample the Blinn-Phong lobe.
+fn brdf_sample(v: Vec3, alpha: f64, u1: f64, u2: f64) -> Vec3 {
+    let n = specular_exponent(alpha);
+    let phi = 2.0 * PI * u1;
+    let cos_theta = u2.powf(1.0 / (n + 1.0));
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let h = [sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta];
+
+    add(scale(-1.0, v), scale(2.0 * dot(v, h), h))
+}
+
+/// A clamped cosine distribution transformed by the matrix `m`.
+#[derive(Copy, Clone)]
+struct Ltc {
+    m11: f64,
+    m22: f64,
+    m13: f64,
+    magnitude: f64,
+    x: Vec3,
+    y: Vec3,
+    z: Vec3,
+    m: Mat3,
+    inv_m: Mat3,
+    det_m: f64,
+}
+
+impl Ltc {
+    fn new() -> Ltc {
+        let mut ltc = Ltc {
+            m11: 1.0,
+            m22: 1.0,
+            m13: 0.0,
+            magnitude: 1.0,
+            x: [1.0, 0.0, 0.0],
+            y: [0.0, 1.0, 0.0],
+            z: [0.0, 0.0, 1.0],
+            m: [[0.0; 3]; 3],
+            inv_m: [[0.0; 3]; 3],
+            det_m: 1.0,
+        };
+        ltc.update();
+
+        ltc
+    }
+
+    fn update(&mut self) {
+        let basis = [self.x, self.y, self.z];
+        let shape = [[self.m11, 0.0, 0.0], [0.0, self.m22, 0.0], [self.m13, 0.0, 1.0]];
+        self.m = mul_mat(&basis, &shape);
+        self.inv_m = inverse(&self.m);
+        self.det_m = determinant(&self.m).abs();
+    }
+
+    fn eval(&self, l: Vec3) -> f64 {
+        let l_original = normalize(mul(&self.inv_m, l));
+        let l_transformed = mul(&self.m, l_original);
+        let len = length(l_transformed);
+        let jacobian = self.det_m / (len * len * len);
+        let d = l_original[2].max(0.0) / PI;
+
+        self.magnitude * d / jacobian
+    }
+
+    fn sample(&self, u1: f64, u2: f64) -> Vec3 {
+        let theta = u1.sqrt().acos();
+        let phi = 2.0 * PI * u2;
+        let l = [theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()];
+
+        normalize(mul(&self.m, l))
+    }
+}
+
+/// Estimate the integral of the lobe and its average direction.
+fn average_terms(v: Vec3, alpha: f64) -> (f64, Vec3) {
+    let mut norm = 0.0;
+    let mut average_dir = [0.0; 3];
+    for j in 0..NUM_SAMPLES {
+        for i in 0..NUM_SAMPLES {
+            let u1 = (i as f64 + 0.5) / NUM_SAMPLES as f64;
+            let u2 = (j as f64 + 0.5) / NUM_SAMPLES as f64;
+            let l = brdf_sample(v, alpha, u1, u2);
+            let (value, density) = brdf_eval(v, l, alpha);
+            if density > 0.0 {
+                let weight = value / density;
+                norm += weight;
+                average_dir = add(average_dir, scale(weight, l));
+            }
+        }
+    }
+    norm /= (NUM_SAMPLES * NUM_SAMPLES) as f64;
+    // The lobe is symmetric about the plane of incidence.
+    average_dir[1] = 0.0;
+
+    (norm, normalize(average_dir))
+}
+
+/// Estimate the error between the lobe and its LTC approximation using multiple
+/// importance sampling of both distributions.
+fn compute_error(ltc: &Ltc, v: Vec3, alpha: f64) -> f64 {
+    let mut error = 0.0;
+    for j in 0..NUM_SAMPLES {
+        for i in 0..NUM_SAMPLES {
+            let u1 = (i as f64 + 0.5) / NUM_SAMPLES as f64;
+            let u2 = (j as f64 + 0.5) / NUM_SAMPLES as f64;
+            for l in [ltc.sample(u1, u2), brdf_sample(v, alpha, u1, u2)].iter() {
+                let (value_brdf, density_brdf) = brdf_eval(v, *l, alpha);
+                let value_ltc = ltc.eval(*l);
+                let density_ltc = value_ltc / ltc.magnitude;
+                let difference = (value_brdf - value_ltc).abs();
+                let denominator = density_ltc + density_brdf;
+                if denominator > 0.0 {
+                    error += difference * difference * difference / denominator;
+                }
+            }
+        }
+    }
+
+    error / (NUM_SAMPLES * NUM_SAMPLES) as f64
+}
+
+/// Minimize a function of three variables with the Nelder-Mead downhill simplex method.
+fn nelder_mead<F>(start: [f64; 3], delta: f64, tolerance: f64, max_iterations: usize, mut f: F) -> [f64; 3]
+    where F: FnMut(&[f64; 3]) -> f64
+{
+    const REFLECT: f64 = 1.0;
+    const EXPAND: f64 = 2.0;
+    const CONTRACT: f64 = 0.5;
+    const SHRINK: f64 = 0.5;
+
+    let mut simplex = [start; 4];
+    for i in 1..4 {
+        simplex[i][i - 1] += delta;
+    }
+    let mut values = [0.0; 4];
+    for i in 0..4 {
+        values[i] = f(&simplex[i]);
+    }
+
+    let mut lo = 0;
+    for _ in 0..max_iterations {
+        lo = 0;
+        let mut hi = 0;
+        let mut nh = 0;
+        for i in 1..4 {
+            if values[i] < values[lo] {
+                lo = i;
+            }
+            if values[i] > values[hi] {
+                nh = hi;
+                hi = i;
+            } else if values[i] > values[nh] {
+                nh = i;
+            }
+        }
+
+        let a = values[lo].abs();
+        let b = values[hi].abs();
+        if 2.0 * (a - b).abs() < (a + b) * tolerance {
+            break;
+        }
+
+        let mut centroid = [0.0; 3];
+        for i in 0..4 {
+            if i != hi {
+                centroid = add(centroid, simplex[i]);
+            }
+        }
+        centroid = scale(1.0 / 3.0, centroid);
+
+        let towards = |t: f64| add(centroid, scale(t, add(centroid, scale(-1.0, simplex[hi]))));
+        let reflected = towards(REFLECT);
+        let value_reflected = f(&reflected);
+        if value_reflected < values[nh] {
+            if value_reflected < values[lo] {
+                let expanded = towards(EXPAND);
+                let value_expanded = f(&expanded);
+                if value_expanded < value_reflected {
+                    simplex[hi] = expanded;
+                    values[hi] = value_expanded;
+                    continue;
+                }
+            }
+            simplex[hi] = reflected;
+            values[hi] = value_reflected;
+            continue;
+        }
+
+        let contracted = towards(-CONTRACT);
+        let value_contracted = f(&contracted);
+        if value_contracted < values[hi] {
+            simplex[hi] = contracted;
+            values[hi] = value_contracted;
+            continue;
+        }
+
+        for k in 0..4 {
+            if k != lo {
+                simplex[k] = add(simplex[lo], scale(SHRINK, add(simplex[k], scale(-1.0, simplex[lo]))));
+                values[k] = f(&simplex[k]);
+            }
+        }
+    }
+
+    simplex[lo]
+}
+
+fn set_parameters(ltc: &mut Ltc, params: &[f64; 3], isotropic: bool) {
+    let m11 = params[0].max(1e-7);
+    let m22 = params[1].max(1e-7);
+    if isotropic {
+        ltc.m11 = m11;
+        ltc.m22 = m11;
+        ltc.m13 = 0.0;
+    } else {
+        ltc.m11 = m11;
+        ltc.m22 = m22;
+        ltc.m13 = params[2];
+    }
+    ltc.update();
+}
+
+fn fit(ltc: &mut Ltc, v: Vec3, alpha: f64, isotropic: bool) {
+    let start = [ltc.m11, ltc.m22, ltc.m13];
+    let result = nelder_mead(start, 0.05, 1e-5, 100, |params| {
+        let mut candidate = *ltc;
+        set_parameters(&mut candidate, params, isotropic);
+        compute_error(&candidate, v, alpha)
+    });
+    set_parameters(ltc, &result, isotropic);
+}
+
+/// The fitted tables. The first table stores the nonzero entries `(m00, m02, m20, m22)` of the
+/// inverse LTC matrix normalized by `m11`, and the second table stores the integral of the lobe.
+pub struct LtcTables {
+    pub inverse_matrices: Vec<[f32; 4]>,
+    pub magnitudes: Vec<f32>,
+}
+
+/// Fit the LTC tables. Each entry at row `t` and column `a` is stored at index `a + t * LTC_TABLE_SIZE`.
+pub fn fit_ltc_tables() -> LtcTables {
+    let n = LTC_TABLE_SIZE;
+    let mut matrices = vec![[[0.0; 3]; 3]; n * n];
+    let mut magnitudes = vec![0.0; n * n];
+    let mut ltc = Ltc::new();
+    for a in (0..n).rev() {
+        for t in 0..n {
+            let x = t as f64 / (n - 1) as f64;
+            let cos_theta = 1.0 - x * x;
+            let theta = cos_theta.acos().min(1.57);
+            let v = [theta.sin(), 0.0, theta.cos()];
+            let roughness = a as f64 / (n - 1) as f64;
+            let alpha = (roughness * roughness).max(MIN_ALPHA);
+
+            let (magnitude, average_dir) = average_terms(v, alpha);
+            ltc.magnitude = magnitude;
+
+            let isotropic = if t == 0 {
+                ltc.x = [1.0, 0.0, 0.0];
+                ltc.y = [0.0, 1.0, 0.0];
+                ltc.z = [0.0, 0.0, 1.0];
+                if a == n - 1 {
+                    ltc.m11 = 1.0;
+                    ltc.m22 = 1.0;
+                } else {
+                    // Start from the fit for the next roughest lobe at normal incidence.
+                    ltc.m11 = matrices[a + 1][0][0];
+                    ltc.m22 = matrices[a + 1][1][1];
+                }
+                ltc.m13 = 0.0;
+                ltc.update();
+
+                true
+            } else {
+                let l = average_dir;
+                ltc.x = [l[2], 0.0, -l[0]];
+                ltc.y = [0.0, 1.0, 0.0];
+                ltc.z = l;
+                ltc.update();
+
+                false
+            };
+
+            fit(&mut ltc, v, alpha, isotropic);
+
+            matrices[a + t * n] = ltc.m;
+            magnitudes[a + t * n] = ltc.magnitude;
+        }
+    }
+
+    let inverse_matrices = matrices.iter().map(|m| {
+        let inv_m = inverse(m);
+        let normalization = inv_m[1][1];
+        [
+            (inv_m[0][0] / normalization) as f32,
+            (inv_m[0][2] / normalization) as f32,
+            (inv_m[2][0] / normalization) as f32,
+            (inv_m[2][2] / normalization) as f32,
+        ]
+    }).collect();
+    let magnitudes = magnitudes.iter().map(|&m| m as f32).collect();
+
+    LtcTables {
+        inverse_matrices: inverse_matrices,
+        magnitudes: magnitudes,
+    }
+}
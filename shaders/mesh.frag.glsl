@@ -10,6 +10,8 @@ struct Camera {
 };
 
 struct FragData {
+    // The vertex position for a vertex in world space.
+    vec3 position_world;
     // The vertex position for a vertex in camera space.
     vec3 position_eye;
     // The normal vector for a fragment in camera space.
@@ -22,45 +24,696 @@ struct Material {
     vec3 diffuse;
     vec3 specular;
     float specular_exponent;
+    // The fraction of the light arriving from the direction of mirror reflection that
+    // the material reflects towards the camera.
+    vec3 reflectivity;
+    // Whether light passes through the material, bending as it enters.
+    bool refractive;
+    // The indices of refraction for red, green, and blue light.
+    vec3 index_of_refraction;
+    // The fraction of the light of each color that passes through the material.
+    vec3 transmittance;
 };
 
-// A point light with specular, diffuse, and ambient components. Each component is 
-// specified in units of 'intensity' which is an unspecified unit of the light's radiant
-// exitance on the interval [0, 1]. The three vectors approximate the spectral dependence
-// of light 'intensity' in terms of R, G, and B channels.
+// The light types the shader knows how to evaluate. These must agree with the
+// tags returned by `LightSource::type_id` on the application side.
+const int LIGHT_TYPE_POINT = 0;
+const int LIGHT_TYPE_DIRECTIONAL = 1;
+const int LIGHT_TYPE_SPOT = 2;
+
+// The distance attenuation models the shader knows how to evaluate. These must agree
+// with the tags returned by `Attenuation::type_id` on the application side.
+const int ATTENUATION_NONE = 0;
+const int ATTENUATION_POLYNOMIAL = 1;
+const int ATTENUATION_INVERSE_SQUARE = 2;
+
+// A light with specular, diffuse, and ambient components. Each component is the
+// luminous intensity of the light in candela, or the illuminance in lux for a directional
+// light, tinted by the light's color. The three vectors approximate the spectral dependence
+// of light intensity in terms of R, G, and B channels.
 struct Light {
-    // The position of the light in world space.
+    // The type of the light, one of the LIGHT_TYPE_* constants.
+    int light_type;
+    // The position of the light in world space. Directional lights ignore this.
+    vec3 position_world;
+    // The direction the light points in world space. Point lights ignore this.
+    vec3 direction_world;
+    // The cosine of the half-angle of the cone of full intensity of a spot light.
+    float cos_inner_cutoff;
+    // The cosine of the half-angle of the cone outside of which a spot light 
+    // contributes nothing.
+    float cos_outer_cutoff;
+    // The exponent shaping the transition between the inner and outer cones
+    // of a spot light.
+    float falloff;
+    // The distance attenuation model of the light, one of the ATTENUATION_* constants.
+    int attenuation_model;
+    // The coefficients of the attenuation model. The polynomial model uses the 
+    // constant, linear, and quadratic coefficients, and the inverse square model
+    // stores its cutoff radius in the first component.
+    vec3 attenuation;
+    // The index of the shadow map the light renders into, or -1 if the light does not 
+    // cast shadows. Point lights index the cube shadow maps, and the other lights 
+    // index the layers of the shadow maps.
+    int shadow_map_index;
+    // The radius of the emitting surface of a point or spot light, which sets the 
+    // size of the penumbrae of its soft shadows.
+    float radius;
+    // The ambient component of the light.
+    vec3 ambient;
+    // The diffuse component of the light.
+    vec3 diffuse;
+    // The specular component of the light.
+    vec3 specular;
+};
+
+const int MAX_LIGHTS = 8;
+// The number of layers of the shadow maps. This must agree with `MAX_SHADOW_MAPS` 
+// on the application side.
+const int MAX_SHADOW_MAPS = 4;
+// The number of point light cube shadow maps. This must agree with 
+// `MAX_POINT_SHADOW_MAPS` on the application side.
+const int MAX_POINT_SHADOW_MAPS = 4;
+const int CUBE_FACES = 6;
+// The radius in texels of the percentage closer filtering kernel.
+const int PCF_RADIUS = 1;
+
+// The shadow filtering techniques the shader knows how to evaluate. These must agree with 
+// the tags returned by `ShadowFilter::type_id` on the application side.
+const int SHADOW_FILTER_PCF = 0;
+const int SHADOW_FILTER_PCSS = 1;
+
+// The ways the shader lights the scene with its surroundings. These must agree with the
+// tags returned by `AmbientLighting::type_id` on the application side.
+const int AMBIENT_FLAT = 0;
+const int AMBIENT_SPHERICAL_HARMONICS = 1;
+const int AMBIENT_ENVIRONMENT_MAP = 2;
+// The number of spherical harmonics coefficients of the diffuse irradiance.
+const int SH_COEFFICIENTS = 9;
+
+// The largest radius of the blocker search and of the filter kernel of percentage 
+// closer soft shadows, in shadow map texture coordinates for projective shadow maps, 
+// and in radians for cube shadow maps. Larger kernels cost nothing more, but the fixed 
+// number of samples spreads too thin to cover them.
+const float MAX_PENUMBRA_UV = 0.02;
+const float MAX_PENUMBRA_ANGLE = 0.08;
+const int POISSON_SAMPLES = 16;
+// Sample offsets scattered evenly over the unit disk.
+const vec2 POISSON_DISK[POISSON_SAMPLES] = vec2[](
+    vec2(-0.94201624, -0.39906216), vec2( 0.94558609, -0.76890725),
+    vec2(-0.09418410, -0.92938870), vec2( 0.34495938,  0.29387760),
+    vec2(-0.91588581,  0.45771432), vec2(-0.81544232, -0.87912464),
+    vec2(-0.38277543,  0.27676845), vec2( 0.97484398,  0.75648379),
+    vec2( 0.44323325, -0.97511554), vec2( 0.53742981, -0.47373420),
+    vec2(-0.26496911, -0.41893023), vec2( 0.79197514,  0.19090188),
+    vec2(-0.24188840,  0.99706507), vec2(-0.81409955,  0.91437590),
+    vec2( 0.19984126,  0.78641367), vec2( 0.14383161, -0.14100790)
+);
+
+// The projection a directional or spot light renders its shadow map with.
+struct ShadowCaster {
+    // The transformation from world space to the clip space of the light.
+    mat4 light_space_mat;
+    // Whether the projection is a perspective projection, as opposed to an orthographic one.
+    bool perspective;
+    float near;
+    float far;
+    // The size of the light in shadow map texture coordinates. See `ShadowProjection`
+    // on the application side.
+    float light_size_uv;
+};
+
+// The shapes of area lights the shader knows how to evaluate. These must agree with
+// the tags returned by `AreaLight::shape_id` on the application side.
+const int AREA_LIGHT_SHAPE_RECTANGLE = 0;
+const int AREA_LIGHT_SHAPE_DISK = 1;
+
+// An area light emitting light from one side of a flat rectangle or disk. A disk is
+// approximated by a regular polygon with DISK_POLYGON_VERTICES vertices.
+struct AreaLight {
+    // The shape of the light, one of the AREA_LIGHT_SHAPE_* constants.
+    int shape;
+    // The center of the light in world space.
     vec3 position_world;
-    // The ambient component of the point light.
+    // The unit vectors spanning the plane of the light in world space. The 
+    // light emits in the direction of cross(right_world, up_world).
+    vec3 right_world;
+    vec3 up_world;
+    // The half width and half height of a rectangle, or the radius of a disk.
+    vec2 half_extents;
+    // The ambient component of the light.
     vec3 ambient;
-    // The diffuse component of the point light.
+    // The diffuse component of the light.
     vec3 diffuse;
-    // The specular component of the point light.
+    // The specular component of the light.
     vec3 specular;
 };
 
-const int num_lights = 3;
+const int MAX_AREA_LIGHTS = 4;
+const int DISK_POLYGON_VERTICES = 12;
+const int MAX_POLYGON_VERTICES = 12;
+// Clipping a polygon to the horizon adds at most one vertex.
+const int MAX_CLIPPED_VERTICES = MAX_POLYGON_VERTICES + 1;
+const float PI = 3.14159265358979;
 
 in FragData vertex_data;
 
 uniform mat4 model_mat;
 uniform Camera camera;
 uniform Material material;
-uniform Light lights[num_lights];
+uniform int num_lights;
+uniform Light lights[MAX_LIGHTS];
+uniform int num_area_lights;
+uniform AreaLight area_lights[MAX_AREA_LIGHTS];
+// The linearly transformed cosine lookup tables. The first table stores the inverse
+// transformation matrices, and the second table stores the magnitude of the specular lobe.
+uniform sampler2D ltc_1;
+uniform sampler2D ltc_2;
+// The projections of each shadow casting light.
+uniform ShadowCaster shadow_casters[MAX_SHADOW_MAPS];
+// The depth of the scene seen from each shadow casting light, one light per layer.
+uniform sampler2DArray shadow_maps;
+// The cube shadow maps of the shadow casting point lights. The six faces of each cube
+// occupy consecutive layers, and store the distance to the light divided by the far plane.
+uniform sampler2DArray point_shadow_maps;
+uniform float point_shadow_near_plane;
+uniform float point_shadow_far_plane;
+// The constant and slope scaled depth bias of the shadow maps in normalized depth units.
+uniform vec2 shadow_bias;
+// The constant and slope scaled depth bias of the cube shadow maps in world units.
+uniform vec2 point_shadow_bias;
+// The technique for filtering the shadow maps, one of the SHADOW_FILTER_* constants.
+uniform int shadow_filter;
+// The factor mapping the luminance of the scene in nits to display values, derived
+// from the exposure value of the camera.
+uniform float exposure;
+// How the surroundings light the scene, one of the AMBIENT_* constants.
+uniform int ambient_lighting;
+// The diffuse irradiance of the environment divided by pi, projected onto the first
+// nine spherical harmonics.
+uniform vec3 sh_irradiance[SH_COEFFICIENTS];
+// The diffuse irradiance of the environment divided by pi, for each world space normal.
+uniform samplerCube irradiance_map;
+// The environment prefiltered for glossy reflections. Level i is blurred with a Phong
+// lobe of exponent specular_map_exponent / 4^i, and the first level is not blurred.
+uniform samplerCube specular_map;
+uniform float specular_map_exponent;
+uniform int specular_map_levels;
+// The luminance in nits of an environment texel with a value of one.
+uniform float environment_luminance;
+// The rotation from world space directions to directions in the environment.
+uniform mat3 environment_rotation;
 
 out vec4 frag_color;
 
 
+// Calculate the direction from a fragment towards the light in camera space.
+vec3 light_direction_eye(Light light) {
+    if (light.light_type == LIGHT_TYPE_DIRECTIONAL) {
+        return normalize(-vec3(camera.view_mat * vec4(light.direction_world, 0.0)));
+    }
+
+    vec3 light_position_eye = vec3(camera.view_mat * vec4(light.position_world, 1.0));
+    return normalize(light_position_eye - vertex_data.position_eye);
+}
+
+// Calculate the fraction of the light's intensity that falls inside the cone 
+// of a spot light.
+float spot_factor(Light light, vec3 light_dir_eye) {
+    if (light.light_type != LIGHT_TYPE_SPOT) {
+        return 1.0;
+    }
+
+    vec3 spot_dir_eye = normalize(vec3(camera.view_mat * vec4(light.direction_world, 0.0)));
+    float cos_theta = dot(-light_dir_eye, spot_dir_eye);
+    float epsilon = max(light.cos_inner_cutoff - light.cos_outer_cutoff, 0.0001);
+    float ratio = clamp((cos_theta - light.cos_outer_cutoff) / epsilon, 0.0, 1.0);
+
+    return pow(ratio, light.falloff);
+}
+
+// Calculate the fraction of the light's intensity that reaches the fragment 
+// from the light. This must agree with `Attenuation::evaluate` on the application side.
+float attenuation_factor(Light light) {
+    if (light.light_type == LIGHT_TYPE_DIRECTIONAL) {
+        return 1.0;
+    }
+
+    vec3 light_position_eye = vec3(camera.view_mat * vec4(light.position_world, 1.0));
+    float light_distance = length(light_position_eye - vertex_data.position_eye);
+    if (light.attenuation_model == ATTENUATION_POLYNOMIAL) {
+        float k_constant = light.attenuation.x;
+        float k_linear = light.attenuation.y;
+        float k_quadratic = light.attenuation.z;
+
+        return 1.0 / (k_constant + k_linear * light_distance + k_quadratic * light_distance * light_distance);
+    } else if (light.attenuation_model == ATTENUATION_INVERSE_SQUARE) {
+        float ratio = light_distance / light.attenuation.x;
+        float ratio_4 = ratio * ratio * ratio * ratio;
+        float window = clamp(1.0 - ratio_4, 0.0, 1.0);
+
+        return (window * window) / (light_distance * light_distance + 1.0);
+    }
+
+    return 1.0;
+}
+
+// Convert a depth from a projective shadow map to the distance along the view direction
+// of the light.
+float linear_depth(ShadowCaster caster, float depth) {
+    if (caster.perspective) {
+        float z_ndc = 2.0 * depth - 1.0;
+        return (2.0 * caster.near * caster.far) / (caster.far + caster.near - z_ndc * (caster.far - caster.near));
+    }
+
+    return caster.near + depth * (caster.far - caster.near);
+}
+
+// Calculate the fraction of a light with a projective shadow map that reaches a fragment
+// at the shadow map texture coordinates `coords` with percentage closer soft shadows, 
+// c.f. `Fernando, Percentage-Closer Soft Shadows, 2005`. First, the average depth of 
+// the blockers in the region of the shadow map that can occlude the light is found. The 
+// penumbra then widens in proportion to the distance between the blockers and the fragment.
+float projected_pcss(ShadowCaster caster, int layer, vec3 coords, float bias) {
+    float receiver_depth = linear_depth(caster, coords.z);
+    float search_radius;
+    if (caster.perspective) {
+        search_radius = caster.light_size_uv * (receiver_depth - caster.near) / (receiver_depth * caster.near);
+    } else {
+        search_radius = caster.light_size_uv * (receiver_depth - caster.near);
+    }
+    search_radius = min(search_radius, MAX_PENUMBRA_UV);
+
+    float blocker_depth_sum = 0.0;
+    int blocker_count = 0;
+    for (int i = 0; i < POISSON_SAMPLES; i++) {
+        vec2 offset = POISSON_DISK[i] * search_radius;
+        float depth = texture(shadow_maps, vec3(coords.xy + offset, layer)).r;
+        if (coords.z - bias > depth) {
+            blocker_depth_sum += linear_depth(caster, depth);
+            blocker_count++;
+        }
+    }
+
+    if (blocker_count == 0) {
+        return 1.0;
+    }
+
+    float blocker_depth = blocker_depth_sum / float(blocker_count);
+    float penumbra_radius;
+    if (caster.perspective) {
+        penumbra_radius = caster.light_size_uv * (receiver_depth - blocker_depth) / (blocker_depth * receiver_depth);
+    } else {
+        penumbra_radius = caster.light_size_uv * (receiver_depth - blocker_depth);
+    }
+    float texel_size = 1.0 / float(textureSize(shadow_maps, 0).x);
+    float filter_radius = clamp(penumbra_radius, texel_size, MAX_PENUMBRA_UV);
+
+    float lit = 0.0;
+    for (int i = 0; i < POISSON_SAMPLES; i++) {
+        vec2 offset = POISSON_DISK[i] * filter_radius;
+        float closest_depth = texture(shadow_maps, vec3(coords.xy + offset, layer)).r;
+        lit += (coords.z - bias > closest_depth) ? 0.0 : 1.0;
+    }
+
+    return lit / float(POISSON_SAMPLES);
+}
+
+// Calculate the fraction of the light that reaches the fragment without being blocked
+// by other geometry, for a light with a projective shadow map. With plain percentage 
+// closer filtering, the shadow map is sampled over a small neighborhood of texels, and 
+// the results of the depth comparisons are averaged to soften the edge of the shadow.
+float projected_shadow_factor(Light light, vec3 norm_eye, vec3 light_dir_eye) {
+    ShadowCaster caster = shadow_casters[light.shadow_map_index];
+    vec4 position_light = caster.light_space_mat * vec4(vertex_data.position_world, 1.0);
+    vec3 position_ndc = position_light.xyz / position_light.w;
+    vec3 coords = position_ndc * 0.5 + 0.5;
+    // Fragments beyond the far plane of the light are never in shadow.
+    if (coords.z > 1.0) {
+        return 1.0;
+    }
+
+    // Offset the depth of the fragment to keep surfaces from shadowing themselves. 
+    // Surfaces at a grazing angle to the light need a larger offset.
+    float bias = max(shadow_bias.y * (1.0 - dot(norm_eye, light_dir_eye)), shadow_bias.x);
+    if (shadow_filter == SHADOW_FILTER_PCSS) {
+        return projected_pcss(caster, light.shadow_map_index, coords, bias);
+    }
+
+    vec2 texel_size = 1.0 / vec2(textureSize(shadow_maps, 0).xy);
+    float lit = 0.0;
+    for (int x = -PCF_RADIUS; x <= PCF_RADIUS; x++) {
+        for (int y = -PCF_RADIUS; y <= PCF_RADIUS; y++) {
+            vec2 offset = vec2(x, y) * texel_size;
+            float closest_depth = texture(shadow_maps, vec3(coords.xy + offset, light.shadow_map_index)).r;
+            lit += (coords.z - bias > closest_depth) ? 0.0 : 1.0;
+        }
+    }
+    float kernel_width = float(2 * PCF_RADIUS + 1);
+
+    return lit / (kernel_width * kernel_width);
+}
+
+// Select the face of a cube map that the direction `dir` points through, following the 
+// OpenGL cube map face selection rules. Returns the index of the face, and writes the
+// texture coordinates of the direction on that face to `uv`.
+int cube_face(vec3 dir, out vec2 uv) {
+    vec3 abs_dir = abs(dir);
+    int face;
+    float major;
+    vec2 sc_tc;
+    if (abs_dir.x >= abs_dir.y && abs_dir.x >= abs_dir.z) {
+        face = (dir.x > 0.0) ? 0 : 1;
+        major = abs_dir.x;
+        sc_tc = vec2((dir.x > 0.0) ? -dir.z : dir.z, -dir.y);
+    } else if (abs_dir.y >= abs_dir.z) {
+        face = (dir.y > 0.0) ? 2 : 3;
+        major = abs_dir.y;
+        sc_tc = vec2(dir.x, (dir.y > 0.0) ? dir.z : -dir.z);
+    } else {
+        face = (dir.z > 0.0) ? 4 : 5;
+        major = abs_dir.z;
+        sc_tc = vec2((dir.z > 0.0) ? dir.x : -dir.x, -dir.y);
+    }
+    uv = 0.5 * (sc_tc / major) + 0.5;
+
+    return face;
+}
+
+// Calculate the distance from a point light to the closest surface in the direction 
+// `dir` from its cube shadow map.
+float cube_closest_distance(int cube, vec3 dir) {
+    vec2 uv;
+    int face = cube_face(dir, uv);
+    float closest_depth = texture(point_shadow_maps, vec3(uv, cube * CUBE_FACES + face)).r;
+
+    return closest_depth * point_shadow_far_plane;
+}
+
+// Calculate the fraction of a point light that reaches a fragment with percentage closer
+// soft shadows. This works the same way as `projected_pcss`, with the kernel measured 
+// in radians around the direction from the light to the fragment.
+float cube_pcss(Light light, vec3 dir, vec3 tangent, vec3 bitangent, float current_distance, float bias) {
+    float search_angle = light.radius * (current_distance - point_shadow_near_plane) / 
+        (current_distance * point_shadow_near_plane);
+    search_angle = min(search_angle, MAX_PENUMBRA_ANGLE);
+
+    float blocker_distance_sum = 0.0;
+    int blocker_count = 0;
+    for (int i = 0; i < POISSON_SAMPLES; i++) {
+        vec2 offset = POISSON_DISK[i] * search_angle;
+        vec3 sample_dir = dir + offset.x * tangent + offset.y * bitangent;
+        float closest_distance = cube_closest_distance(light.shadow_map_index, sample_dir);
+        if (current_distance - bias > closest_distance) {
+            blocker_distance_sum += closest_distance;
+            blocker_count++;
+        }
+    }
+
+    if (blocker_count == 0) {
+        return 1.0;
+    }
+
+    float blocker_distance = blocker_distance_sum / float(blocker_count);
+    float penumbra_angle = light.radius * (current_distance - blocker_distance) / 
+        (blocker_distance * current_distance);
+    float texel_size = 2.0 / float(textureSize(point_shadow_maps, 0).x);
+    float filter_angle = clamp(penumbra_angle, texel_size, MAX_PENUMBRA_ANGLE);
+
+    float lit = 0.0;
+    for (int i = 0; i < POISSON_SAMPLES; i++) {
+        vec2 offset = POISSON_DISK[i] * filter_angle;
+        vec3 sample_dir = dir + offset.x * tangent + offset.y * bitangent;
+        float closest_distance = cube_closest_distance(light.shadow_map_index, sample_dir);
+        lit += (current_distance - bias > closest_distance) ? 0.0 : 1.0;
+    }
+
+    return lit / float(POISSON_SAMPLES);
+}
+
+// Calculate the fraction of the light that reaches the fragment without being blocked
+// by other geometry, for a point light with a cube shadow map. The filter kernel is 
+// built from directions around the direction to the fragment rather than from texture
+// coordinates, so that it spans the seams between the faces of the cube.
+float cube_shadow_factor(Light light, vec3 norm_eye, vec3 light_dir_eye) {
+    vec3 to_fragment = vertex_data.position_world - light.position_world;
+    float current_distance = length(to_fragment);
+    if (current_distance > point_shadow_far_plane) {
+        return 1.0;
+    }
+
+    float bias = max(point_shadow_bias.y * (1.0 - dot(norm_eye, light_dir_eye)), point_shadow_bias.x);
+    vec3 dir = to_fragment / current_distance;
+    vec3 helper = (abs(dir.y) < 0.999) ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+    vec3 tangent = normalize(cross(dir, helper));
+    vec3 bitangent = cross(dir, tangent);
+    if (shadow_filter == SHADOW_FILTER_PCSS) {
+        return cube_pcss(light, dir, tangent, bitangent, current_distance, bias);
+    }
+
+    // The width of a texel on the unit sphere around the light.
+    float texel_size = 2.0 / float(textureSize(point_shadow_maps, 0).x);
+    float lit = 0.0;
+    for (int x = -PCF_RADIUS; x <= PCF_RADIUS; x++) {
+        for (int y = -PCF_RADIUS; y <= PCF_RADIUS; y++) {
+            vec3 sample_dir = dir + (float(x) * tangent + float(y) * bitangent) * texel_size;
+            float closest_distance = cube_closest_distance(light.shadow_map_index, sample_dir);
+            lit += (current_distance - bias > closest_distance) ? 0.0 : 1.0;
+        }
+    }
+    float kernel_width = float(2 * PCF_RADIUS + 1);
+
+    return lit / (kernel_width * kernel_width);
+}
+
+// Calculate the fraction of the light that reaches the fragment without being blocked
+// by other geometry.
+float shadow_factor(Light light, vec3 norm_eye, vec3 light_dir_eye) {
+    if (light.shadow_map_index < 0) {
+        return 1.0;
+    }
+
+    if (light.light_type == LIGHT_TYPE_POINT) {
+        return cube_shadow_factor(light, norm_eye, light_dir_eye);
+    }
+
+    return projected_shadow_factor(light, norm_eye, light_dir_eye);
+}
+
+// Compute the vertices of the polygon bounding an area light in camera space. Returns
+// the number of vertices. The vertices wind clockwise when viewed from the emitting side.
+int area_light_polygon(AreaLight light, out vec3 points[MAX_POLYGON_VERTICES]) {
+    vec3 center = vec3(camera.view_mat * vec4(light.position_world, 1.0));
+    vec3 right = light.half_extents.x * vec3(camera.view_mat * vec4(light.right_world, 0.0));
+    vec3 up = light.half_extents.y * vec3(camera.view_mat * vec4(light.up_world, 0.0));
+    if (light.shape == AREA_LIGHT_SHAPE_DISK) {
+        for (int i = 0; i < DISK_POLYGON_VERTICES; i++) {
+            float angle = -2.0 * PI * float(i) / float(DISK_POLYGON_VERTICES);
+            points[i] = center + cos(angle) * right + sin(angle) * up;
+        }
+
+        return DISK_POLYGON_VERTICES;
+    }
+
+    points[0] = center + right + up;
+    points[1] = center + right - up;
+    points[2] = center - right - up;
+    points[3] = center - right + up;
+
+    return 4;
+}
+
+// Integrate the cosine distribution over the edge of a polygon projected onto the
+// unit sphere from v1 to v2.
+float integrate_edge(vec3 v1, vec3 v2) {
+    float cos_theta = clamp(dot(v1, v2), -0.9999, 0.9999);
+    float theta = acos(cos_theta);
+
+    return cross(v1, v2).z * ((theta > 0.001) ? theta / sin(theta) : 1.0);
+}
+
+// Integrate the linearly transformed cosine distribution with inverse transformation
+// `inverse_mat` over an area light polygon as seen from the point `position`. 
+// c.f. `Heitz, Dupuy, Hill, Neubelt, Real-Time Polygonal-Light Shading with Linearly 
+// Transformed Cosines, 2016`.
+float ltc_evaluate(
+    vec3 normal, vec3 view_dir, vec3 position, mat3 inverse_mat, 
+    vec3 points[MAX_POLYGON_VERTICES], int count) {
+
+    // Construct an orthonormal basis around the normal, oriented along the view direction.
+    vec3 tangent = normalize(view_dir - normal * dot(view_dir, normal));
+    vec3 bitangent = cross(normal, tangent);
+    mat3 transform = inverse_mat * transpose(mat3(tangent, bitangent, normal));
+
+    vec3 polygon[MAX_POLYGON_VERTICES];
+    for (int i = 0; i < count; i++) {
+        polygon[i] = transform * (points[i] - position);
+    }
+
+    // Clip the polygon to the upper hemisphere, where the cosine distribution is nonzero.
+    vec3 clipped[MAX_CLIPPED_VERTICES];
+    int clipped_count = 0;
+    for (int i = 0; i < count; i++) {
+        vec3 current = polygon[i];
+        vec3 next = polygon[(i + 1) % count];
+        if (current.z >= 0.0) {
+            clipped[clipped_count] = current;
+            clipped_count++;
+        }
+        if ((current.z >= 0.0) != (next.z >= 0.0)) {
+            float t = current.z / (current.z - next.z);
+            clipped[clipped_count] = mix(current, next, t);
+            clipped_count++;
+        }
+    }
+
+    if (clipped_count < 3) {
+        return 0.0;
+    }
+
+    float sum = 0.0;
+    for (int i = 0; i < clipped_count; i++) {
+        vec3 v1 = normalize(clipped[i]);
+        vec3 v2 = normalize(clipped[(i + 1) % clipped_count]);
+        sum += integrate_edge(v1, v2);
+    }
+
+    // The light only emits from one side.
+    return max(sum, 0.0) / (2.0 * PI);
+}
+
+// Calculate the light reflected from an area light towards the camera.
+vec3 area_light_contribution(AreaLight light, vec3 norm_eye, vec3 view_dir_eye) {
+    vec3 points[MAX_POLYGON_VERTICES];
+    int count = area_light_polygon(light, points);
+
+    // The lookup tables are parametrized by the roughness of the lobe, which we match
+    // to the width of the Blinn-Phong lobe with the material's specular exponent.
+    float alpha = sqrt(2.0 / (material.specular_exponent + 2.0));
+    float roughness = sqrt(alpha);
+    float cos_theta = clamp(dot(norm_eye, view_dir_eye), 0.0, 1.0);
+    float table_size = float(textureSize(ltc_1, 0).x);
+    vec2 uv = vec2(roughness, sqrt(1.0 - cos_theta));
+    uv = uv * ((table_size - 1.0) / table_size) + 0.5 / table_size;
+    vec4 t1 = texture(ltc_1, uv);
+    float magnitude = texture(ltc_2, uv).r;
+    mat3 inverse_mat = mat3(
+        vec3(t1.x, 0.0, t1.y),
+        vec3( 0.0, 1.0,  0.0),
+        vec3(t1.z, 0.0, t1.w)
+    );
+
+    vec3 position_eye = vertex_data.position_eye;
+    float specular = magnitude * ltc_evaluate(norm_eye, view_dir_eye, position_eye, inverse_mat, points, count);
+    float diffuse = ltc_evaluate(norm_eye, view_dir_eye, position_eye, mat3(1.0), points, count);
+
+    vec3 frag_ambient = ambient_lighting == AMBIENT_FLAT ? light.ambient * material.ambient : vec3(0.0);
+    vec3 frag_diffuse = light.diffuse * material.diffuse * diffuse;
+    vec3 frag_specular = light.specular * material.specular * specular;
+
+    return frag_ambient + frag_diffuse + frag_specular;
+}
+
+// Evaluate the spherical harmonics projection of the diffuse irradiance divided by pi for
+// a normal in the frame of the environment. The basis functions must agree with `sh::basis`
+// on the application side.
+vec3 evaluate_sh_irradiance(vec3 n) {
+    return sh_irradiance[0] * 0.282095
+        + sh_irradiance[1] * 0.488603 * n.y
+        + sh_irradiance[2] * 0.488603 * n.z
+        + sh_irradiance[3] * 0.488603 * n.x
+        + sh_irradiance[4] * 1.092548 * n.x * n.y
+        + sh_irradiance[5] * 1.092548 * n.y * n.z
+        + sh_irradiance[6] * 0.315392 * (3.0 * n.z * n.z - 1.0)
+        + sh_irradiance[7] * 1.092548 * n.x * n.z
+        + sh_irradiance[8] * 0.546274 * (n.x * n.x - n.y * n.y);
+}
+
+// The rotation from camera space directions to directions in the environment.
+mat3 eye_to_environment() {
+    // The view matrix is a rigid motion, so its transpose rotates back to world space,
+    // and from there the environment rotation turns directions into the environment.
+    return environment_rotation * transpose(mat3(camera.view_mat));
+}
+
+// The level of the prefiltered specular map matching the glossiness of the material.
+float specular_map_level() {
+    // A Blinn-Phong lobe around the half vector is about as wide as a Phong lobe around 
+    // the direction of reflection with a quarter of the exponent.
+    float phong_exponent = max(material.specular_exponent / 4.0, 1.0);
+    float level = 0.5 * log2(specular_map_exponent / phong_exponent);
+
+    return clamp(level, 0.0, float(specular_map_levels - 1));
+}
+
+// The fraction of the light a transparent material reflects when viewed at an angle 
+// with cosine cos_theta to the normal, using Schlick's approximation of the Fresnel
+// equations.
+vec3 fresnel_reflectance(float cos_theta) {
+    vec3 n = material.index_of_refraction;
+    vec3 reflectance_normal = ((n - 1.0) * (n - 1.0)) / ((n + 1.0) * (n + 1.0));
+
+    return reflectance_normal + (1.0 - reflectance_normal) * pow(1.0 - cos_theta, 5.0);
+}
+
+// Calculate the light the environment reflects off of the fragment towards the camera.
+// The diffuse part looks up the irradiance around the normal, either in the spherical 
+// harmonics or in the irradiance map. The reflection looks up the environment in the
+// direction of reflection, blurred to match the glossiness of the material, and weighted
+// by the reflectivity of the material. Transparent materials reflect as much light as 
+// the Fresnel equations predict instead.
+vec3 environment_contribution(vec3 norm_eye, vec3 view_dir_eye) {
+    mat3 to_environment = eye_to_environment();
+    vec3 norm_environment = normalize(to_environment * norm_eye);
+    vec3 reflect_environment = normalize(to_environment * reflect(-view_dir_eye, norm_eye));
+
+    vec3 irradiance;
+    if (ambient_lighting == AMBIENT_SPHERICAL_HARMONICS) {
+        // The projection rings slightly below zero opposite of bright light.
+        irradiance = max(evaluate_sh_irradiance(norm_environment), vec3(0.0));
+    } else {
+        irradiance = texture(irradiance_map, norm_environment).rgb;
+    }
+
+    vec3 reflected = textureLod(specular_map, reflect_environment, specular_map_level()).rgb;
+    vec3 reflectivity = material.reflectivity;
+    if (material.refractive) {
+        reflectivity = fresnel_reflectance(max(dot(norm_eye, view_dir_eye), 0.0));
+    }
+
+    return environment_luminance * (material.diffuse * irradiance + reflectivity * reflected);
+}
+
+// Calculate the light from the environment passing through a transparent material 
+// towards the camera. Each color bends by its own index of refraction as it enters the 
+// material, which splits white light into colors. The refracted direction looks up the
+// environment rather than the scene, so the material shows its surroundings bent, but
+// not the objects behind it.
+vec3 refraction_contribution(vec3 norm_eye, vec3 view_dir_eye) {
+    mat3 to_environment = eye_to_environment();
+    float level = specular_map_level();
+    vec3 refracted;
+    for (int channel = 0; channel < 3; channel++) {
+        float eta = 1.0 / material.index_of_refraction[channel];
+        vec3 refract_eye = refract(-view_dir_eye, norm_eye, eta);
+        vec3 refract_environment = normalize(to_environment * refract_eye);
+        refracted[channel] = textureLod(specular_map, refract_environment, level)[channel];
+    }
+    vec3 transmitted = 1.0 - fresnel_reflectance(max(dot(norm_eye, view_dir_eye), 0.0));
+
+    return environment_luminance * transmitted * material.transmittance * refracted;
+}
+
 void main() {
     vec3 frag_result = vec3(0.0, 0.0, 0.0);
     for (int i = 0; i < num_lights; i++) {
-        // Calculate the ambient part of the lighting model.
-        vec3 frag_ambient = lights[i].ambient * material.ambient;
+        // Calculate the ambient part of the lighting model. The environment replaces it
+        // when it lights the scene.
+        vec3 frag_ambient = ambient_lighting == AMBIENT_FLAT ? lights[i].ambient * material.ambient : vec3(0.0);
 
         // Calculate the diffuse part of the lighting model.
         vec3 norm_eye = normalize(vertex_data.normal_eye);
-        vec3 light_position_eye = vec3(camera.view_mat * vec4(lights[i].position_world, 1.0));
-        vec3 light_dir_eye = normalize(light_position_eye - vertex_data.position_eye);
+        vec3 light_dir_eye = light_direction_eye(lights[i]);
         float diff = max(dot(norm_eye, light_dir_eye), 0.0);
         vec3 frag_diffuse = lights[i].diffuse * (diff * material.diffuse);
 
@@ -71,8 +724,30 @@ void main() {
         float specular_factor = pow(dot_specular, material.specular_exponent);
         vec3 frag_specular = lights[i].specular * material.specular * specular_factor;
 
-        frag_result += frag_ambient + frag_diffuse + frag_specular;
+        // Restrict the direct lighting of a spot light to its cone.
+        float spot = spot_factor(lights[i], light_dir_eye);
+
+        // Diminish the light with the distance from the light.
+        float attenuation = attenuation_factor(lights[i]);
+
+        // Remove the direct lighting blocked by other objects.
+        float shadow = shadow_factor(lights[i], norm_eye, light_dir_eye);
+
+        frag_result += attenuation * (frag_ambient + spot * shadow * (frag_diffuse + frag_specular));
+    }
+
+    vec3 norm_eye = normalize(vertex_data.normal_eye);
+    vec3 view_dir_eye = normalize(-vertex_data.position_eye);
+    for (int i = 0; i < num_area_lights; i++) {
+        frag_result += area_light_contribution(area_lights[i], norm_eye, view_dir_eye);
+    }
+
+    if (ambient_lighting != AMBIENT_FLAT) {
+        frag_result += environment_contribution(norm_eye, view_dir_eye);
+    }
+    if (material.refractive) {
+        frag_result += refraction_contribution(norm_eye, view_dir_eye);
     }
 
-    frag_color = vec4(frag_result, 1.0);
+    frag_color = vec4(exposure * frag_result, 1.0);
 }
@@ -10,6 +10,8 @@ struct Camera {
 };
 
 struct FragData {
+    // The vertex position for a vertex in world space.
+    vec3 position_world;
     // The vertex position for a vertex in camera space.
     vec3 position_eye;
     // The normal vector for a fragment in camera space.
@@ -28,7 +30,8 @@ out FragData vertex_data;
 
 
 void main() {
-    vertex_data.position_eye = vec3(camera.view_mat * model_mat * vec4(v_pos, 1.0));
+    vertex_data.position_world = vec3(model_mat * vec4(v_pos, 1.0));
+    vertex_data.position_eye = vec3(camera.view_mat * vec4(vertex_data.position_world, 1.0));
     vertex_data.normal_eye = vec3(camera.view_mat * model_mat * vec4(v_norm, 0.0));
 
     gl_Position = camera.proj_mat * vec4(vertex_data.position_eye, 1.0);
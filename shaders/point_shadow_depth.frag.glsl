@@ -0,0 +1,15 @@
+#version 330 core
+
+in vec3 position_world;
+
+// The position of the point light in world space.
+uniform vec3 light_position_world;
+// The distance from the light beyond which nothing casts shadows.
+uniform float far_plane;
+
+
+void main() {
+    // Store the distance to the light instead of the projected depth, so that the
+    // mesh shader can compare distances without knowing which face it samples from.
+    gl_FragDepth = length(position_world - light_position_world) / far_plane;
+}
@@ -0,0 +1,18 @@
+#version 330 core
+
+layout(location = 0) in vec3 v_pos;
+
+// The coordinate transformation placing an object from model 
+// space to world space.
+uniform mat4 model_mat;
+// The coordinate transformation from world space to the clip 
+// space of the cube face being rendered.
+uniform mat4 light_space_mat;
+
+out vec3 position_world;
+
+
+void main() {
+    position_world = vec3(model_mat * vec4(v_pos, 1.0));
+    gl_Position = light_space_mat * vec4(position_world, 1.0);
+}
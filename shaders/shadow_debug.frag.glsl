@@ -0,0 +1,16 @@
+#version 330 core
+
+in vec2 tex_coord;
+
+// The shadow maps to display.
+uniform sampler2DArray shadow_maps;
+// The layer of the shadow maps to display.
+uniform int layer;
+
+out vec4 frag_color;
+
+
+void main() {
+    float depth = texture(shadow_maps, vec3(tex_coord, layer)).r;
+    frag_color = vec4(vec3(depth), 1.0);
+}
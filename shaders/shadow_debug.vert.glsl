@@ -0,0 +1,11 @@
+#version 330 core
+
+layout(location = 0) in vec2 v_pos;
+
+out vec2 tex_coord;
+
+
+void main() {
+    tex_coord = 0.5 * v_pos + 0.5;
+    gl_Position = vec4(v_pos, 0.0, 1.0);
+}
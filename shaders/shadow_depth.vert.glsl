@@ -0,0 +1,15 @@
+#version 330 core
+
+layout(location = 0) in vec3 v_pos;
+
+// The coordinate transformation placing an object from model 
+// space to world space.
+uniform mat4 model_mat;
+// The coordinate transformation from world space to the clip 
+// space of the light rendering the shadow map.
+uniform mat4 light_space_mat;
+
+
+void main() {
+    gl_Position = light_space_mat * model_mat * vec4(v_pos, 1.0);
+}
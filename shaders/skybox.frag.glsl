@@ -0,0 +1,21 @@
+#version 330 core
+
+in vec3 direction_world;
+
+// The environment surrounding the scene.
+uniform samplerCube skybox;
+// The rotation from world space directions to directions in the environment.
+uniform mat3 environment_rotation;
+// The luminance in nits of an environment texel with a value of one.
+uniform float environment_luminance;
+// The factor mapping the luminance of the scene in nits to display values, derived
+// from the exposure value of the camera.
+uniform float exposure;
+
+out vec4 frag_color;
+
+
+void main() {
+    vec3 radiance = texture(skybox, environment_rotation * normalize(direction_world)).rgb;
+    frag_color = vec4(exposure * environment_luminance * radiance, 1.0);
+}
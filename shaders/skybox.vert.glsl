@@ -0,0 +1,22 @@
+#version 330 core
+struct Camera {
+    mat4 proj_mat;
+    mat4 view_mat;
+};
+
+layout (location = 0) in vec3 v_pos;
+
+uniform Camera camera;
+
+// The world space direction from the camera through the vertex.
+out vec3 direction_world;
+
+
+void main() {
+    direction_world = v_pos;
+    // Drop the translation of the view matrix, so the skybox stays centered on the 
+    // camera and looks infinitely far away.
+    vec4 position = camera.proj_mat * mat4(mat3(camera.view_mat)) * vec4(v_pos, 1.0);
+    // Place the skybox on the far plane, behind everything else in the scene.
+    gl_Position = position.xyww;
+}
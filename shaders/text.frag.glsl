@@ -0,0 +1,17 @@
+#version 330 core
+
+in vec2 tex_coord;
+
+// The glyphs of the font.
+uniform sampler2D font_atlas;
+uniform vec3 text_color;
+
+out vec4 frag_color;
+
+
+void main() {
+    if (texture(font_atlas, tex_coord).r < 0.5) {
+        discard;
+    }
+    frag_color = vec4(text_color, 1.0);
+}
@@ -0,0 +1,17 @@
+#version 330 core
+
+layout(location = 0) in vec2 v_pos;
+layout(location = 1) in vec2 v_tex_coord;
+
+// The size of the window in pixels.
+uniform vec2 screen_size;
+// The distance in pixels to move the text by.
+uniform vec2 offset;
+
+out vec2 tex_coord;
+
+
+void main() {
+    tex_coord = v_tex_coord;
+    gl_Position = vec4(2.0 * (v_pos + offset) / screen_size - 1.0, 0.0, 1.0);
+}
@@ -6,8 +6,10 @@ use crate::gl::types::{
     GLenum, 
     GLfloat, 
     GLint, 
+    GLsizei,
     GLubyte, 
-    GLuint
+    GLuint,
+    GLvoid,
 };
 use glfw;
 use glfw::{
@@ -634,3 +636,313 @@ pub fn compile_from_reader<R1: Read, P1: AsRef<Path>, R2: Read, P2: AsRef<Path>>
 
     Ok(program)
 }
+
+/// The pixel formats of the textures the demo allocates on the GPU.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextureFormat {
+    R32F,
+    Rgb32F,
+    Rgba32F,
+    Depth32F,
+}
+
+impl TextureFormat {
+    fn internal_format(self) -> GLenum {
+        match self {
+            TextureFormat::R32F => gl::R32F,
+            TextureFormat::Rgb32F => gl::RGB32F,
+            TextureFormat::Rgba32F => gl::RGBA32F,
+            TextureFormat::Depth32F => gl::DEPTH_COMPONENT32F,
+        }
+    }
+
+    fn format(self) -> GLenum {
+        match self {
+            TextureFormat::R32F => gl::RED,
+            TextureFormat::Rgb32F => gl::RGB,
+            TextureFormat::Rgba32F => gl::RGBA,
+            TextureFormat::Depth32F => gl::DEPTH_COMPONENT,
+        }
+    }
+
+    /// The number of floating point components in each pixel.
+    fn components(self) -> usize {
+        match self {
+            TextureFormat::R32F => 1,
+            TextureFormat::Rgb32F => 3,
+            TextureFormat::Rgba32F => 4,
+            TextureFormat::Depth32F => 1,
+        }
+    }
+}
+
+/// A handle to a texture living on the GPU. Each texture is either a two dimensional 
+/// texture, an array of two dimensional textures with `layers` layers, or a cube map
+/// with six faces.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Texture {
+    pub id: GLuint,
+    pub target: GLenum,
+    pub width: u32,
+    pub height: u32,
+    pub layers: u32,
+    pub format: TextureFormat,
+}
+
+impl Texture {
+    /// Allocate a two dimensional texture on the GPU. If `data` is present, it must contain 
+    /// `width * height` pixels of the texture's format in row major order.
+    pub fn new_2d(width: u32, height: u32, format: TextureFormat, data: Option<&[f32]>) -> Texture {
+        if let Some(data) = data {
+            debug_assert_eq!(data.len(), (width * height) as usize * format.components());
+        }
+        let pixels = match data {
+            Some(data) => data.as_ptr() as *const GLvoid,
+            None => ptr::null(),
+        };
+
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+            gl::TexImage2D(
+                gl::TEXTURE_2D, 0, format.internal_format() as GLint, 
+                width as GLsizei, height as GLsizei, 0,
+                format.format(), gl::FLOAT, pixels
+            );
+        }
+        debug_assert!(id > 0);
+
+        let texture = Texture {
+            id: id,
+            target: gl::TEXTURE_2D,
+            width: width,
+            height: height,
+            layers: 1,
+            format: format,
+        };
+        texture.set_filter(gl::LINEAR);
+        texture.set_wrap(gl::CLAMP_TO_EDGE);
+
+        texture
+    }
+
+    /// Allocate an uninitialized array of two dimensional textures on the GPU.
+    pub fn new_2d_array(width: u32, height: u32, layers: u32, format: TextureFormat) -> Texture {
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, id);
+            gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY, 0, format.internal_format() as GLint,
+                width as GLsizei, height as GLsizei, layers as GLsizei, 0,
+                format.format(), gl::FLOAT, ptr::null()
+            );
+        }
+        debug_assert!(id > 0);
+
+        let texture = Texture {
+            id: id,
+            target: gl::TEXTURE_2D_ARRAY,
+            width: width,
+            height: height,
+            layers: layers,
+            format: format,
+        };
+        texture.set_filter(gl::LINEAR);
+        texture.set_wrap(gl::CLAMP_TO_EDGE);
+
+        texture
+    }
+
+    /// Allocate an uninitialized cube map on the GPU with `levels` mipmap levels, each
+    /// half the size of the one before it. The faces are `size` texels wide at the first level.
+    pub fn new_cube_map(size: u32, levels: u32, format: TextureFormat) -> Texture {
+        debug_assert!(levels > 0 && size >> (levels - 1) > 0);
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, id);
+            for level in 0..levels {
+                let level_size = (size >> level) as GLsizei;
+                for face in 0..6 {
+                    gl::TexImage2D(
+                        gl::TEXTURE_CUBE_MAP_POSITIVE_X + face, level as GLint, format.internal_format() as GLint,
+                        level_size, level_size, 0,
+                        format.format(), gl::FLOAT, ptr::null()
+                    );
+                }
+            }
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_BASE_LEVEL, 0);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAX_LEVEL, (levels - 1) as GLint);
+        }
+        debug_assert!(id > 0);
+
+        let texture = Texture {
+            id: id,
+            target: gl::TEXTURE_CUBE_MAP,
+            width: size,
+            height: size,
+            layers: 6,
+            format: format,
+        };
+        texture.set_filter(gl::LINEAR);
+        if levels > 1 {
+            unsafe {
+                gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as GLint);
+            }
+        }
+        texture.set_wrap(gl::CLAMP_TO_EDGE);
+
+        texture
+    }
+
+    /// Fill one face of one mipmap level of a cube map. The faces are numbered in the order
+    /// `+X`, `-X`, `+Y`, `-Y`, `+Z`, `-Z`, and `data` holds the pixels of the face in row major order.
+    pub fn upload_cube_map_face(&self, face: u32, level: u32, data: &[f32]) {
+        debug_assert_eq!(self.target, gl::TEXTURE_CUBE_MAP);
+        debug_assert!(face < 6);
+        let size = self.width >> level;
+        debug_assert_eq!(data.len(), (size * size) as usize * self.format.components());
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+            gl::TexSubImage2D(
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face, level as GLint, 0, 0,
+                size as GLsizei, size as GLsizei,
+                self.format.format(), gl::FLOAT, data.as_ptr() as *const GLvoid
+            );
+        }
+    }
+
+    /// Set the minification and magnification filters of the texture.
+    pub fn set_filter(&self, filter: GLenum) {
+        unsafe {
+            gl::BindTexture(self.target, self.id);
+            gl::TexParameteri(self.target, gl::TEXTURE_MIN_FILTER, filter as GLint);
+            gl::TexParameteri(self.target, gl::TEXTURE_MAG_FILTER, filter as GLint);
+        }
+    }
+
+    /// Set the wrapping mode of the texture along all of its texture coordinates.
+    pub fn set_wrap(&self, wrap: GLenum) {
+        unsafe {
+            gl::BindTexture(self.target, self.id);
+            gl::TexParameteri(self.target, gl::TEXTURE_WRAP_S, wrap as GLint);
+            gl::TexParameteri(self.target, gl::TEXTURE_WRAP_T, wrap as GLint);
+            if self.target == gl::TEXTURE_CUBE_MAP {
+                gl::TexParameteri(self.target, gl::TEXTURE_WRAP_R, wrap as GLint);
+            }
+        }
+    }
+
+    /// Set the color sampled outside of the texture when it wraps with `GL_CLAMP_TO_BORDER`.
+    pub fn set_border_color(&self, color: [f32; 4]) {
+        unsafe {
+            gl::BindTexture(self.target, self.id);
+            gl::TexParameterfv(self.target, gl::TEXTURE_BORDER_COLOR, color.as_ptr());
+        }
+    }
+
+    /// Bind the texture to the texture unit `unit`.
+    pub fn bind(&self, unit: GLuint) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(self.target, self.id);
+        }
+    }
+}
+
+/// An error from assembling a framebuffer that OpenGL cannot render into.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FramebufferError {
+    status: GLenum,
+}
+
+impl fmt::Display for FramebufferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self.status {
+            gl::FRAMEBUFFER_UNDEFINED => "GL_FRAMEBUFFER_UNDEFINED",
+            gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => "GL_FRAMEBUFFER_INCOMPLETE_ATTACHMENT",
+            gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => "GL_FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT",
+            gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => "GL_FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER",
+            gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => "GL_FRAMEBUFFER_INCOMPLETE_READ_BUFFER",
+            gl::FRAMEBUFFER_UNSUPPORTED => "GL_FRAMEBUFFER_UNSUPPORTED",
+            gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => "GL_FRAMEBUFFER_INCOMPLETE_MULTISAMPLE",
+            gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => "GL_FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS",
+            _ => "unknown status",
+        };
+
+        write!(f, "The framebuffer is incomplete. Got status {} ({:#x}).", reason, self.status)
+    }
+}
+
+impl error::Error for FramebufferError {}
+
+/// A framebuffer for rendering offscreen, for example into a shadow map.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Framebuffer {
+    pub id: GLuint,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Framebuffer {
+    /// Create a framebuffer with no attachments.
+    pub fn new(width: u32, height: u32) -> Framebuffer {
+        let mut id = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut id);
+        }
+        debug_assert!(id > 0);
+
+        Framebuffer {
+            id: id,
+            width: width,
+            height: height,
+        }
+    }
+
+    /// Make one layer of a depth texture array the only attachment of the framebuffer,
+    /// so that rendering into the framebuffer writes depth values only.
+    pub fn attach_depth_layer(&self, texture: &Texture, layer: u32) -> Result<(), FramebufferError> {
+        debug_assert_eq!(texture.format, TextureFormat::Depth32F);
+        debug_assert!(layer < texture.layers);
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            gl::FramebufferTextureLayer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, texture.id, 0, layer as GLint);
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+        }
+
+        self.check_status()
+    }
+
+    fn check_status(&self) -> Result<(), FramebufferError> {
+        let status = unsafe {
+            gl::CheckFramebufferStatus(gl::FRAMEBUFFER)
+        };
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            let error = FramebufferError { status: status };
+            error!("{}", error);
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    /// Bind the framebuffer as the render target and set the viewport to cover it.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            gl::Viewport(0, 0, self.width as GLint, self.height as GLint);
+        }
+    }
+}
+
+/// Bind the window's framebuffer as the render target and set the viewport to cover it.
+pub fn bind_default_framebuffer(context: &OpenGLContext) {
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        gl::Viewport(0, 0, context.width as GLint, context.height as GLint);
+    }
+}
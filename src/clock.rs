@@ -0,0 +1,248 @@
+/// The longest wall clock time a single frame can advance the simulation by. After a
+/// stall, such as dragging the window, the simulation falls behind instead of taking
+/// so many steps to catch up that the next frame stalls too.
+const MAX_FRAME_SECONDS: f64 = 0.25;
+/// The slowest and fastest the simulation can run relative to the wall clock.
+const MIN_TIME_SCALE: f64 = 1.0 / 16.0;
+const MAX_TIME_SCALE: f64 = 4.0;
+
+
+/// Where the clock gets the passage of time from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ClockMode {
+    /// Each frame advances the simulation by the wall clock time since the last frame.
+    RealTime,
+    /// Each frame advances the simulation by exactly one timestep regardless of how long
+    /// the frame took to render, so two runs produce identical frames.
+    Virtual,
+}
+
+/// The simulation steps to take for a frame.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClockTick {
+    /// The number of fixed timesteps to advance the simulation by.
+    pub steps: u32,
+    /// How far the frame lies between the state before the last step and the state after
+    /// it, for interpolating the rendered state between the two.
+    pub alpha: f32,
+}
+
+/// A simulation clock that advances the simulation in fixed timesteps. The simulation
+/// does not depend on the frame rate, and frames between two steps interpolate the
+/// state of the simulation on either side.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimClock {
+    mode: ClockMode,
+    /// The length of each simulation step in seconds.
+    timestep: f64,
+    /// The factor scaling the passage of time. Values below one run in slow motion.
+    time_scale: f64,
+    paused: bool,
+    /// Whether to take a single step on the next frame while paused.
+    step_requested: bool,
+    /// The time that has passed but is too short to take another step.
+    accumulator: f64,
+    /// The number of steps taken since the simulation started.
+    ticks: u64,
+}
+
+impl SimClock {
+    /// Construct a clock following the wall clock.
+    pub fn new(timestep: f32) -> SimClock {
+        debug_assert!(timestep > 0.0);
+        SimClock {
+            mode: ClockMode::RealTime,
+            timestep: timestep as f64,
+            time_scale: 1.0,
+            paused: false,
+            step_requested: false,
+            accumulator: 0.0,
+            ticks: 0,
+        }
+    }
+
+    /// Construct a clock that advances one timestep per frame. The simulation starts
+    /// `start_tick` steps in, so each start tick gives a different but reproducible scene.
+    pub fn new_virtual(timestep: f32, start_tick: u64) -> SimClock {
+        let mut clock = SimClock::new(timestep);
+        clock.mode = ClockMode::Virtual;
+        clock.ticks = start_tick;
+
+        clock
+    }
+
+    #[inline]
+    pub fn mode(&self) -> ClockMode {
+        self.mode
+    }
+
+    /// The length of each simulation step in seconds.
+    #[inline]
+    pub fn timestep(&self) -> f32 {
+        self.timestep as f32
+    }
+
+    /// The number of steps the simulation has taken.
+    #[inline]
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// The simulation time in seconds. This counts steps rather than summing elapsed
+    /// times, so it does not drift.
+    #[inline]
+    pub fn time(&self) -> f64 {
+        self.ticks as f64 * self.timestep
+    }
+
+    #[inline]
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale as f32
+    }
+
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.step_requested = false;
+    }
+
+    /// Take a single step on the next frame. This only has an effect while paused.
+    pub fn request_step(&mut self) {
+        if self.paused {
+            self.step_requested = true;
+        }
+    }
+
+    /// Multiply the time scale by `factor`, keeping it within the supported range.
+    pub fn scale_time(&mut self, factor: f32) {
+        self.time_scale = (self.time_scale * factor as f64).max(MIN_TIME_SCALE).min(MAX_TIME_SCALE);
+    }
+
+    /// Advance the clock by one frame that took `elapsed_seconds` of wall clock time.
+    pub fn advance(&mut self, elapsed_seconds: f64) -> ClockTick {
+        if self.paused {
+            let steps = if self.step_requested { 1 } else { 0 };
+            self.step_requested = false;
+            self.ticks += steps as u64;
+
+            return ClockTick { steps: steps, alpha: 1.0 };
+        }
+
+        let frame_seconds = match self.mode {
+            ClockMode::RealTime => elapsed_seconds.max(0.0).min(MAX_FRAME_SECONDS),
+            ClockMode::Virtual => self.timestep,
+        };
+        self.accumulator += frame_seconds * self.time_scale;
+        let mut steps = 0;
+        while self.accumulator >= self.timestep {
+            self.accumulator -= self.timestep;
+            steps += 1;
+        }
+        self.ticks += steps as u64;
+        let alpha = (self.accumulator / self.timestep) as f32;
+
+        ClockTick { steps: steps, alpha: alpha }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A timestep a binary fraction can represent exactly, so the steps add up exactly.
+    const TIMESTEP: f32 = 0.25;
+    const EPSILON: f32 = 1e-6;
+
+    fn assert_tick(tick: ClockTick, steps: u32, alpha: f32) {
+        assert_eq!(tick.steps, steps);
+        assert!((tick.alpha - alpha).abs() <= EPSILON, "Expected alpha {} but got {}.", alpha, tick.alpha);
+    }
+
+    /// The time left over after the whole steps carries over to the next frame, and the
+    /// fraction of a step it makes up interpolates the frame.
+    #[test]
+    fn test_accumulator_splits_frames_into_steps_and_alpha() {
+        let mut clock = SimClock::new(TIMESTEP);
+        assert_tick(clock.advance(0.2), 0, 0.8);
+        assert_tick(clock.advance(0.2), 1, 0.6);
+        assert_tick(clock.advance(0.1), 1, 0.0);
+        assert_eq!(clock.ticks(), 2);
+        assert_eq!(clock.time(), 0.5);
+    }
+
+    /// A long stall only advances the simulation by the longest frame.
+    #[test]
+    fn test_long_frames_are_clamped() {
+        let mut clock = SimClock::new(TIMESTEP);
+        assert_tick(clock.advance(10.0), 1, 0.0);
+        assert_tick(clock.advance(-1.0), 0, 0.0);
+        assert_eq!(clock.ticks(), 1);
+    }
+
+    #[test]
+    fn test_paused_clock_only_takes_requested_steps() {
+        let mut clock = SimClock::new(TIMESTEP);
+        clock.toggle_pause();
+        assert!(clock.is_paused());
+        assert_tick(clock.advance(0.2), 0, 1.0);
+        clock.request_step();
+        assert_tick(clock.advance(0.2), 1, 1.0);
+        assert_tick(clock.advance(0.2), 0, 1.0);
+        assert_eq!(clock.ticks(), 1);
+
+        // Resuming drops a pending step, and the clock carries on where it left off.
+        clock.request_step();
+        clock.toggle_pause();
+        assert_tick(clock.advance(0.25), 1, 0.0);
+        assert_eq!(clock.ticks(), 2);
+    }
+
+    #[test]
+    fn test_request_step_has_no_effect_while_running() {
+        let mut clock = SimClock::new(TIMESTEP);
+        clock.request_step();
+        clock.toggle_pause();
+        assert_tick(clock.advance(0.2), 0, 1.0);
+    }
+
+    #[test]
+    fn test_time_scale_is_clamped() {
+        let mut clock = SimClock::new(TIMESTEP);
+        clock.scale_time(1000.0);
+        assert_eq!(clock.time_scale(), 4.0);
+        assert_tick(clock.advance(0.25), 4, 0.0);
+
+        clock.scale_time(1e-6);
+        assert_eq!(clock.time_scale(), 1.0 / 16.0);
+        assert_tick(clock.advance(0.25), 0, 0.0625);
+    }
+
+    /// Virtual time takes exactly one step per frame however long the frame took, starting
+    /// from the start tick.
+    #[test]
+    fn test_virtual_clock_takes_one_step_per_frame() {
+        let mut clock = SimClock::new_virtual(TIMESTEP, 10);
+        assert_eq!(clock.mode(), ClockMode::Virtual);
+        assert_tick(clock.advance(0.0), 1, 0.0);
+        assert_tick(clock.advance(5.0), 1, 0.0);
+        assert_eq!(clock.ticks(), 12);
+        assert_eq!(clock.time(), 3.0);
+    }
+
+    /// The simulation time counts steps, so it does not drift with timesteps that binary
+    /// fractions cannot represent.
+    #[test]
+    fn test_time_does_not_drift() {
+        let timestep = 1.0 / 120.0;
+        let mut clock = SimClock::new_virtual(timestep, 0);
+        for _ in 0..120 * 60 {
+            clock.advance(0.0);
+        }
+        assert_eq!(clock.ticks(), 120 * 60);
+        assert_eq!(clock.time(), (120 * 60) as f64 * timestep as f64);
+    }
+}
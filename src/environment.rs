@@ -0,0 +1,472 @@
+use crate::hdr::HdrImage;
+use crate::vector::{
+    dot,
+    normalize,
+};
+
+
+/// The number of faces of a cube map.
+pub const CUBE_MAP_FACES: usize = 6;
+/// The width and height in texels of each face of the skybox.
+pub const SKYBOX_SIZE: u32 = 512;
+/// The width and height in texels of each face of the unfiltered environment, which is
+/// the first level of the prefiltered specular map.
+pub const SPECULAR_MAP_SIZE: u32 = 128;
+/// The number of levels of the prefiltered specular map. Each level halves the size of
+/// the one before it.
+pub const SPECULAR_MAP_LEVELS: u32 = 6;
+/// The Phong exponent the first level of the specular map stands for. Each following
+/// level blurs the environment with a lobe a quarter as sharp. The mesh shader picks the
+/// level matching the specular exponent of each material.
+pub const SPECULAR_MAP_EXPONENT: f32 = 4096.0;
+/// The width and height in texels of each face of the diffuse irradiance map. Irradiance
+/// varies slowly with the direction of the normal, so it needs very few texels.
+pub const IRRADIANCE_MAP_SIZE: u32 = 32;
+/// The size of the faces of the environment the irradiance map integrates over.
+const IRRADIANCE_SOURCE_SIZE: u32 = 16;
+/// The largest face size of the environment a level of the specular map integrates over.
+/// This bounds the time spent filtering the sharp levels.
+const MAX_PREFILTER_SOURCE_SIZE: u32 = 32;
+/// The smallest face size of the environment a level of the specular map integrates over.
+const MIN_PREFILTER_SOURCE_SIZE: u32 = 8;
+/// The fraction of the peak of a Phong lobe below which a direction does not contribute
+/// to the filtered environment.
+const PREFILTER_CUTOFF: f32 = 0.01;
+/// The width and height in texels of the blocks of a level of the specular map that
+/// share one list of the source texels close enough to contribute to them.
+const PREFILTER_TILE_SIZE: u32 = 8;
+
+
+/// The ways the mesh shader can light the scene with its surroundings.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AmbientLighting {
+    /// Each light adds a constant ambient term, the same in every direction, and nothing
+    /// reflects the environment.
+    Flat,
+    /// The diffuse irradiance of the environment, projected onto spherical harmonics, with
+    /// reflections from the prefiltered specular map.
+    SphericalHarmonics,
+    /// The diffuse irradiance map of the environment, with reflections from the 
+    /// prefiltered specular map.
+    EnvironmentMap,
+}
+
+impl AmbientLighting {
+    /// The integer tag identifying the ambient lighting in the shader. These must agree 
+    /// with the `AMBIENT_*` constants in `mesh.frag.glsl`.
+    pub fn type_id(&self) -> i32 {
+        match *self {
+            AmbientLighting::Flat => 0,
+            AmbientLighting::SphericalHarmonics => 1,
+            AmbientLighting::EnvironmentMap => 2,
+        }
+    }
+
+    /// The next ambient lighting to cycle to.
+    pub fn next(&self) -> AmbientLighting {
+        match *self {
+            AmbientLighting::Flat => AmbientLighting::SphericalHarmonics,
+            AmbientLighting::SphericalHarmonics => AmbientLighting::EnvironmentMap,
+            AmbientLighting::EnvironmentMap => AmbientLighting::Flat,
+        }
+    }
+}
+
+/// A cube map with three floating point channels per texel. The faces are in the order
+/// OpenGL numbers them: `+X`, `-X`, `+Y`, `-Y`, `+Z`, `-Z`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CubeMap {
+    /// The width and height in texels of each face.
+    pub size: u32,
+    /// The red, green, and blue values of the texels of each face in row major order.
+    pub faces: Vec<Vec<f32>>,
+}
+
+impl CubeMap {
+    /// Construct a cube map by evaluating `radiance` in the direction of the center of
+    /// each texel.
+    pub fn from_fn<F>(size: u32, radiance: F) -> CubeMap where F: Fn([f32; 3]) -> [f32; 3] {
+        let faces = (0..CUBE_MAP_FACES).map(|face| {
+            let mut texels = Vec::with_capacity(3 * (size * size) as usize);
+            for y in 0..size {
+                for x in 0..size {
+                    let value = radiance(texel_direction(face, x, y, size));
+                    texels.extend_from_slice(&value);
+                }
+            }
+
+            texels
+        });
+
+        CubeMap {
+            size: size,
+            faces: faces.collect(),
+        }
+    }
+
+    /// Project an equirectangular environment onto a cube map. The image spans the
+    /// full circle of longitude from left to right, and the poles from top to bottom,
+    /// with the top row looking along `+Y` and the center of the image along `-Z`.
+    pub fn from_equirectangular(image: &HdrImage, size: u32) -> CubeMap {
+        CubeMap::from_fn(size, |direction| sample_equirectangular(image, direction))
+    }
+
+    /// Assemble a cube map from an image for each face, in the order `+X`, `-X`, `+Y`, 
+    /// `-Y`, `+Z`, `-Z`. The faces must be square and of the same size, and are resampled 
+    /// to `size` texels wide.
+    pub fn from_faces(images: &[HdrImage], size: u32) -> Result<CubeMap, String> {
+        if images.len() != CUBE_MAP_FACES {
+            return Err(format!("Expected {} faces but got {}.", CUBE_MAP_FACES, images.len()));
+        }
+        let face_size = images[0].width;
+        for (face, image) in images.iter().enumerate() {
+            if image.width != image.height || image.width != face_size {
+                return Err(format!(
+                    "Face {} is {} x {}, but every face must be {} x {}.", 
+                    face, image.width, image.height, face_size, face_size
+                ));
+            }
+        }
+
+        Ok(CubeMap::from_fn(size, |direction| sample_cube_faces(images, direction)))
+    }
+
+    /// Halve the size of the cube map by averaging each block of two by two texels.
+    pub fn downsample(&self) -> CubeMap {
+        debug_assert!(self.size > 1 && self.size % 2 == 0);
+        let size = self.size / 2;
+        let faces = self.faces.iter().map(|texels| {
+            let mut downsampled = Vec::with_capacity(3 * (size * size) as usize);
+            for y in 0..size {
+                for x in 0..size {
+                    for channel in 0..3 {
+                        let texel = |dx: u32, dy: u32| {
+                            texels[3 * ((2 * y + dy) * self.size + 2 * x + dx) as usize + channel]
+                        };
+                        downsampled.push(0.25 * (texel(0, 0) + texel(1, 0) + texel(0, 1) + texel(1, 1)));
+                    }
+                }
+            }
+
+            downsampled
+        });
+
+        CubeMap {
+            size: size,
+            faces: faces.collect(),
+        }
+    }
+
+    /// Downsample the cube map until its faces are `size` texels wide.
+    fn downsample_to(&self, size: u32) -> CubeMap {
+        let mut cube_map = self.clone();
+        while cube_map.size > size {
+            cube_map = cube_map.downsample();
+        }
+
+        cube_map
+    }
+
+    /// The direction, solid angle, and radiance of every texel of the cube map.
+    fn texels(&self) -> Vec<Texel> {
+        let mut texels = Vec::with_capacity(CUBE_MAP_FACES * (self.size * self.size) as usize);
+        for (face, values) in self.faces.iter().enumerate() {
+            for y in 0..self.size {
+                for x in 0..self.size {
+                    let index = 3 * (y * self.size + x) as usize;
+                    texels.push(Texel {
+                        direction: texel_direction(face, x, y, self.size),
+                        solid_angle: texel_solid_angle(x, y, self.size),
+                        radiance: [values[index], values[index + 1], values[index + 2]],
+                    });
+                }
+            }
+        }
+
+        texels
+    }
+}
+
+/// A texel of a cube map, as a source of light arriving from its direction.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Texel {
+    direction: [f32; 3],
+    solid_angle: f32,
+    radiance: [f32; 3],
+}
+
+/// The unit vector from the center of the cube towards the center of the texel in
+/// column `x` and row `y` of the face `face`, following the OpenGL cube map conventions.
+pub fn texel_direction(face: usize, x: u32, y: u32, size: u32) -> [f32; 3] {
+    let s = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+    let t = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+
+    face_direction(face, s, t)
+}
+
+/// The unit vector from the center of the cube through the point `(s, t)` of the face
+/// `face`, with both coordinates in `[-1, 1]`.
+fn face_direction(face: usize, s: f32, t: f32) -> [f32; 3] {
+    let direction = match face {
+        0 => [1.0, -t, -s],
+        1 => [-1.0, -t, s],
+        2 => [s, 1.0, t],
+        3 => [s, -1.0, -t],
+        4 => [s, -t, 1.0],
+        5 => [-s, -t, -1.0],
+        _ => panic!("A cube map has no face {}.", face),
+    };
+
+    normalize(direction).unwrap()
+}
+
+/// The solid angle a texel of a cube map face subtends as seen from the center of the cube.
+pub fn texel_solid_angle(x: u32, y: u32, size: u32) -> f32 {
+    // The solid angle of the part of a face between its center and the point (s, t),
+    // c.f. "Cubemap Texel Solid Angle" by Rory Driscoll.
+    fn area(s: f32, t: f32) -> f32 {
+        (s * t).atan2((s * s + t * t + 1.0).sqrt())
+    }
+
+    let texel_size = 2.0 / size as f32;
+    let s0 = x as f32 * texel_size - 1.0;
+    let t0 = y as f32 * texel_size - 1.0;
+    let s1 = s0 + texel_size;
+    let t1 = t0 + texel_size;
+
+    area(s0, t0) - area(s0, t1) - area(s1, t0) + area(s1, t1)
+}
+
+/// Sample an equirectangular image in the direction `direction` with bilinear filtering.
+fn sample_equirectangular(image: &HdrImage, direction: [f32; 3]) -> [f32; 3] {
+    let longitude = direction[0].atan2(-direction[2]);
+    let latitude = direction[1].max(-1.0).min(1.0).acos();
+    let u = 0.5 + longitude / (2.0 * std::f32::consts::PI);
+    let v = latitude / std::f32::consts::PI;
+
+    // The image wraps around horizontally, and clamps at the poles.
+    let x = u * image.width as f32 - 0.5;
+    let y = (v * image.height as f32 - 0.5).max(0.0).min((image.height - 1) as f32);
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let fx = x - x0;
+    let fy = y - y0;
+    let column = |x: f32| (x as i64).rem_euclid(image.width as i64) as u32;
+    let row = |y: f32| (y as u32).min(image.height - 1);
+    let p00 = image.pixel(column(x0), row(y0));
+    let p10 = image.pixel(column(x0 + 1.0), row(y0));
+    let p01 = image.pixel(column(x0), row(y0 + 1.0));
+    let p11 = image.pixel(column(x0 + 1.0), row(y0 + 1.0));
+
+    let mut value = [0.0; 3];
+    for channel in 0..3 {
+        let top = p00[channel] + fx * (p10[channel] - p00[channel]);
+        let bottom = p01[channel] + fx * (p11[channel] - p01[channel]);
+        value[channel] = top + fy * (bottom - top);
+    }
+
+    value
+}
+
+/// Sample the cube map faces `images` in the direction `direction` with bilinear filtering.
+fn sample_cube_faces(images: &[HdrImage], direction: [f32; 3]) -> [f32; 3] {
+    // Find the face the direction points through, and the coordinates on the face 
+    // following the OpenGL cube map conventions.
+    let [x, y, z] = direction;
+    let (face, major, sc, tc) = if x.abs() >= y.abs() && x.abs() >= z.abs() {
+        if x > 0.0 { (0, x, -z, -y) } else { (1, x, z, -y) }
+    } else if y.abs() >= z.abs() {
+        if y > 0.0 { (2, y, x, z) } else { (3, y, x, -z) }
+    } else if z > 0.0 {
+        (4, z, x, -y)
+    } else {
+        (5, z, -x, -y)
+    };
+    let image = &images[face];
+    let size = image.width as f32;
+    let s = 0.5 * (sc / major.abs() + 1.0);
+    let t = 0.5 * (tc / major.abs() + 1.0);
+
+    // Each face clamps at its edges.
+    let px = (s * size - 0.5).max(0.0).min(size - 1.0);
+    let py = (t * size - 0.5).max(0.0).min(size - 1.0);
+    let x0 = px.floor();
+    let y0 = py.floor();
+    let fx = px - x0;
+    let fy = py - y0;
+    let x1 = (x0 + 1.0).min(size - 1.0);
+    let y1 = (y0 + 1.0).min(size - 1.0);
+    let p00 = image.pixel(x0 as u32, y0 as u32);
+    let p10 = image.pixel(x1 as u32, y0 as u32);
+    let p01 = image.pixel(x0 as u32, y1 as u32);
+    let p11 = image.pixel(x1 as u32, y1 as u32);
+
+    let mut value = [0.0; 3];
+    for channel in 0..3 {
+        let top = p00[channel] + fx * (p10[channel] - p00[channel]);
+        let bottom = p01[channel] + fx * (p11[channel] - p01[channel]);
+        value[channel] = top + fy * (bottom - top);
+    }
+
+    value
+}
+
+/// Convolve the environment with a cosine lobe around each direction. Each texel of the
+/// result holds the irradiance arriving at a surface facing its direction, divided by pi,
+/// so that multiplying it by the diffuse reflectance of a surface gives the radiance the
+/// surface reflects.
+pub fn irradiance_map(environment: &CubeMap, size: u32) -> CubeMap {
+    let source = environment.downsample_to(IRRADIANCE_SOURCE_SIZE).texels();
+
+    CubeMap::from_fn(size, |normal| {
+        let mut irradiance = [0.0; 3];
+        for texel in source.iter() {
+            let cos_theta = dot(normal, texel.direction);
+            if cos_theta > 0.0 {
+                let weight = cos_theta * texel.solid_angle;
+                for channel in 0..3 {
+                    irradiance[channel] += weight * texel.radiance[channel];
+                }
+            }
+        }
+
+        [
+            irradiance[0] / std::f32::consts::PI,
+            irradiance[1] / std::f32::consts::PI,
+            irradiance[2] / std::f32::consts::PI,
+        ]
+    })
+}
+
+/// The Phong exponent of the lobe level `level` of the specular map is filtered with.
+pub fn specular_map_exponent(level: u32) -> f32 {
+    SPECULAR_MAP_EXPONENT / 4_f32.powi(level as i32)
+}
+
+/// Filter the environment for glossy reflections. Level `i` of the result averages the
+/// environment around each direction, weighted by a Phong lobe with the exponent
+/// `specular_map_exponent(i)`, so that it stands in for the reflection of the environment
+/// off of a surface of that glossiness. The first level is the unfiltered environment.
+pub fn prefiltered_specular_map(environment: &CubeMap, levels: u32) -> Vec<CubeMap> {
+    debug_assert!(environment.size >> (levels - 1) >= 1);
+    let mut mip_levels = vec![environment.clone()];
+    for level in 1..levels {
+        let size = environment.size >> level;
+        let source_size = size.max(MIN_PREFILTER_SOURCE_SIZE).min(MAX_PREFILTER_SOURCE_SIZE);
+        let source = environment.downsample_to(source_size).texels();
+        mip_levels.push(filter_phong_lobe(&source, size, specular_map_exponent(level)));
+    }
+
+    mip_levels
+}
+
+/// Average the source texels `source` around the direction of each texel of a cube map
+/// `size` texels wide, weighted by a Phong lobe with the exponent `exponent`.
+fn filter_phong_lobe(source: &[Texel], size: u32, exponent: f32) -> CubeMap {
+    let min_cos_theta = PREFILTER_CUTOFF.powf(1.0 / exponent);
+    let lobe_angle = min_cos_theta.acos();
+    let tile_size = PREFILTER_TILE_SIZE.min(size);
+    let texel_size = 2.0 / size as f32;
+    let mut faces = vec![vec![0.0; 3 * (size * size) as usize]; CUBE_MAP_FACES];
+    for (face, texels) in faces.iter_mut().enumerate() {
+        for tile_y in (0..size).step_by(tile_size as usize) {
+            for tile_x in (0..size).step_by(tile_size as usize) {
+                // Every texel of the tile lies within `tile_angle` of its center, so only the
+                // source texels within `tile_angle` of the lobe around the center, plus a
+                // little room for rounding, contribute to the tile.
+                let s0 = tile_x as f32 * texel_size - 1.0;
+                let t0 = tile_y as f32 * texel_size - 1.0;
+                let s1 = s0 + tile_size as f32 * texel_size;
+                let t1 = t0 + tile_size as f32 * texel_size;
+                let center = face_direction(face, 0.5 * (s0 + s1), 0.5 * (t0 + t1));
+                let tile_angle = [(s0, t0), (s1, t0), (s0, t1), (s1, t1)].iter()
+                    .map(|&(s, t)| dot(center, face_direction(face, s, t)).min(1.0).acos())
+                    .fold(0.0, f32::max);
+                let min_cos_candidate = (tile_angle + lobe_angle + 1e-3).min(std::f32::consts::PI).cos();
+                let candidates: Vec<&Texel> = source.iter()
+                    .filter(|texel| dot(center, texel.direction) >= min_cos_candidate)
+                    .collect();
+
+                for y in tile_y..tile_y + tile_size {
+                    for x in tile_x..tile_x + tile_size {
+                        let reflection = texel_direction(face, x, y, size);
+                        let mut radiance = [0.0; 3];
+                        let mut total_weight = 0.0;
+                        for texel in candidates.iter() {
+                            let cos_theta = dot(reflection, texel.direction);
+                            if cos_theta > min_cos_theta {
+                                let weight = cos_theta.powf(exponent) * texel.solid_angle;
+                                for channel in 0..3 {
+                                    radiance[channel] += weight * texel.radiance[channel];
+                                }
+                                total_weight += weight;
+                            }
+                        }
+                        let value = if total_weight > 0.0 {
+                            [radiance[0] / total_weight, radiance[1] / total_weight, radiance[2] / total_weight]
+                        } else {
+                            // The lobe is narrower than a source texel, so the closest texel is
+                            // the best estimate of the filtered environment.
+                            let closest = source.iter()
+                                .max_by(|t1, t2| {
+                                    dot(reflection, t1.direction).partial_cmp(&dot(reflection, t2.direction)).unwrap()
+                                })
+                                .unwrap();
+                            closest.radiance
+                        };
+                        let index = 3 * (y * size + x) as usize;
+                        texels[index..index + 3].copy_from_slice(&value);
+                    }
+                }
+            }
+        }
+    }
+
+    CubeMap {
+        size: size,
+        faces: faces,
+    }
+}
+
+/// A simple sky to light the scene with when no environment image is available. The sky
+/// brightens from the horizon to the zenith, and the ground below the horizon is a dim
+/// uniform gray.
+pub fn gradient_sky(direction: [f32; 3]) -> [f32; 3] {
+    const ZENITH: [f32; 3] = [0.35, 0.55, 1.0];
+    const HORIZON: [f32; 3] = [0.9, 0.9, 0.85];
+    const GROUND: [f32; 3] = [0.2, 0.18, 0.16];
+
+    let elevation = direction[1];
+    if elevation < 0.0 {
+        return GROUND;
+    }
+
+    let t = elevation.sqrt();
+    [
+        HORIZON[0] + t * (ZENITH[0] - HORIZON[0]),
+        HORIZON[1] + t * (ZENITH[1] - HORIZON[1]),
+        HORIZON[2] + t * (ZENITH[2] - HORIZON[2]),
+    ]
+}
+
+/// The environment of the scene, filtered for image based lighting.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Environment {
+    /// The diffuse irradiance divided by pi for each direction of the surface normal.
+    pub irradiance: CubeMap,
+    /// The levels of the prefiltered specular map, from sharpest to blurriest.
+    pub specular: Vec<CubeMap>,
+}
+
+impl Environment {
+    /// Filter an environment for image based lighting. The environment is downsampled
+    /// to `SPECULAR_MAP_SIZE` first.
+    pub fn new(environment: &CubeMap) -> Environment {
+        let environment = environment.downsample_to(SPECULAR_MAP_SIZE);
+        Environment {
+            irradiance: irradiance_map(&environment, IRRADIANCE_MAP_SIZE),
+            specular: prefiltered_specular_map(&environment, SPECULAR_MAP_LEVELS),
+        }
+    }
+
+}
@@ -0,0 +1,302 @@
+use std::error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+
+/// The shortest and longest scanlines that can use the run length encoding of newer
+/// Radiance files. Other scanlines are always stored flat or with the older encoding.
+const MIN_RLE_SCANLINE: usize = 8;
+const MAX_RLE_SCANLINE: usize = 0x7fff;
+/// The longest run of pixels a single channel of a run length encoded scanline stores
+/// in two bytes.
+const MAX_RLE_RUN: usize = 127;
+/// The most pixels the repeat markers of a flat scanline repeat in twelve bytes, with
+/// three consecutive markers shifting their counts by zero, eight, and sixteen bits.
+const MAX_FLAT_RUN: usize = 255 + (255 << 8) + (255 << 16);
+
+
+/// A high dynamic range image with three floating point channels per pixel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    /// The red, green, and blue values of each pixel in row major order, starting from
+    /// the top row of the image.
+    pub pixels: Vec<f32>,
+}
+
+impl HdrImage {
+    /// Load a Radiance `.hdr` image from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<HdrImage, HdrError> {
+        let bytes = fs::read(&path).map_err(|e| {
+            HdrError::new(format!("Could not read `{}`: {}", path.as_ref().display(), e))
+        })?;
+
+        HdrImage::parse(&bytes)
+    }
+
+    /// Parse a Radiance `.hdr` image. The header is a list of lines ending with a blank
+    /// line, followed by the resolution and the pixels in RGBE format, each scanline
+    /// flat or run length encoded.
+    /// ```text
+    /// #?RADIANCE
+    /// FORMAT=32-bit_rle_rgbe
+    ///
+    /// -Y 512 +X 1024
+    /// ```
+    /// Only images stored top to bottom and left to right are supported, which is the
+    /// orientation every common tool writes.
+    pub fn parse(bytes: &[u8]) -> Result<HdrImage, HdrError> {
+        let mut reader = ByteReader::new(bytes);
+        let magic = reader.line()?;
+        if !magic.starts_with("#?") {
+            return Err(HdrError::new(String::from("The file does not start with `#?RADIANCE`.")));
+        }
+
+        let mut exposure = 1.0_f32;
+        loop {
+            let line = reader.line()?;
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+                return Err(HdrError::new(format!("Unsupported pixel format `{}`.", &line[7..])));
+            }
+            if line.starts_with("EXPOSURE=") {
+                // Radiance multiplies the pixels by each exposure it applies, so dividing
+                // by the product of all of them recovers the original radiance.
+                let value = line[9..].trim().parse::<f32>()
+                    .map_err(|_| HdrError::new(format!("Invalid exposure `{}`.", &line[9..])))?;
+                if value <= 0.0 || !value.is_finite() {
+                    return Err(HdrError::new(format!("Invalid exposure `{}`.", &line[9..])));
+                }
+                exposure *= value;
+            }
+        }
+
+        let resolution = reader.line()?;
+        let words: Vec<&str> = resolution.split_whitespace().collect();
+        let (height, width) = match words.as_slice() {
+            ["-Y", height, "+X", width] => {
+                match (height.parse::<usize>(), width.parse::<usize>()) {
+                    (Ok(height), Ok(width)) if height > 0 && width > 0 => (height, width),
+                    _ => return Err(HdrError::new(format!("Invalid resolution `{}`.", resolution))),
+                }
+            }
+            _ => {
+                return Err(HdrError::new(format!(
+                    "Unsupported resolution `{}`. Only `-Y <height> +X <width>` images are supported.", resolution
+                )));
+            }
+        };
+
+        // A corrupt resolution would otherwise allocate more pixels than the file can hold.
+        let remaining_bytes = bytes.len() - reader.position;
+        let fits = min_scanline_bytes(width).checked_mul(height)
+            .map_or(false, |min_bytes| min_bytes <= remaining_bytes);
+        if !fits {
+            return Err(HdrError::new(format!(
+                "The resolution `{}` needs more than the {} bytes left in the file.", 
+                resolution, remaining_bytes
+            )));
+        }
+
+        let mut pixels = Vec::with_capacity(3 * width * height);
+        let mut scanline = vec![[0_u8; 4]; width];
+        for row in 0..height {
+            read_scanline(&mut reader, &mut scanline)
+                .map_err(|e| HdrError::new(format!("Could not read scanline {}. {}", row, e.message)))?;
+            for rgbe in scanline.iter() {
+                let rgb = rgbe_to_rgb(*rgbe);
+                pixels.push(rgb[0] / exposure);
+                pixels.push(rgb[1] / exposure);
+                pixels.push(rgb[2] / exposure);
+            }
+        }
+
+        Ok(HdrImage {
+            width: width as u32,
+            height: height as u32,
+            pixels: pixels,
+        })
+    }
+
+    /// The red, green, and blue values of the pixel in column `x` and row `y`.
+    #[inline]
+    pub fn pixel(&self, x: u32, y: u32) -> [f32; 3] {
+        let index = 3 * (y * self.width + x) as usize;
+        [self.pixels[index], self.pixels[index + 1], self.pixels[index + 2]]
+    }
+}
+
+/// The fewest bytes any of the encodings Radiance writes can store a scanline of `width`
+/// pixels in.
+fn min_scanline_bytes(width: usize) -> usize {
+    // Rounding the runs down keeps this a lower bound.
+    let flat_bytes = 4 + 12 * ((width - 1) / MAX_FLAT_RUN);
+    if (MIN_RLE_SCANLINE..=MAX_RLE_SCANLINE).contains(&width) {
+        let rle_bytes = 4 + 4 * 2 * (width / MAX_RLE_RUN);
+        usize::min(flat_bytes, rle_bytes)
+    } else {
+        flat_bytes
+    }
+}
+
+/// Convert a pixel with a shared exponent to floating point.
+fn rgbe_to_rgb(rgbe: [u8; 4]) -> [f32; 3] {
+    if rgbe[3] == 0 {
+        return [0.0, 0.0, 0.0];
+    }
+
+    // The mantissas are fixed point values with eight fractional bits. Taking the
+    // middle of each interval halves the rounding error.
+    let scale = 2_f32.powi(rgbe[3] as i32 - (128 + 8));
+    [
+        (rgbe[0] as f32 + 0.5) * scale,
+        (rgbe[1] as f32 + 0.5) * scale,
+        (rgbe[2] as f32 + 0.5) * scale,
+    ]
+}
+
+/// Read one scanline of RGBE pixels in any of the encodings Radiance writes.
+fn read_scanline(reader: &mut ByteReader, scanline: &mut [[u8; 4]]) -> Result<(), HdrError> {
+    let width = scanline.len();
+    let first = reader.pixel()?;
+    let is_rle = width >= MIN_RLE_SCANLINE && width <= MAX_RLE_SCANLINE &&
+        first[0] == 2 && first[1] == 2 && first[2] & 0x80 == 0;
+    if !is_rle {
+        return read_flat_scanline(reader, first, scanline);
+    }
+
+    let encoded_width = ((first[2] as usize) << 8) | first[3] as usize;
+    if encoded_width != width {
+        return Err(HdrError::new(format!("Expected {} pixels but got {}.", width, encoded_width)));
+    }
+
+    // Each channel is stored separately as runs of a repeated byte and literal bytes.
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = reader.byte()? as usize;
+            if count > 128 {
+                let count = count - 128;
+                if x + count > width {
+                    return Err(HdrError::new(String::from("A run extends past the end of the scanline.")));
+                }
+                let value = reader.byte()?;
+                for pixel in scanline[x..(x + count)].iter_mut() {
+                    pixel[channel] = value;
+                }
+                x += count;
+            } else {
+                if count == 0 || x + count > width {
+                    return Err(HdrError::new(String::from("Invalid run of literal bytes.")));
+                }
+                for pixel in scanline[x..(x + count)].iter_mut() {
+                    pixel[channel] = reader.byte()?;
+                }
+                x += count;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Read a scanline stored flat, where a pixel of `(1, 1, 1, n)` repeats the previous
+/// pixel `n` times, shifted left by eight bits for each consecutive repeat marker.
+fn read_flat_scanline(reader: &mut ByteReader, first: [u8; 4], scanline: &mut [[u8; 4]]) -> Result<(), HdrError> {
+    let width = scanline.len();
+    let mut pixel = first;
+    let mut x = 0;
+    let mut shift = 0;
+    loop {
+        if pixel[0] == 1 && pixel[1] == 1 && pixel[2] == 1 {
+            if x == 0 || shift > 16 {
+                return Err(HdrError::new(String::from("Invalid repeat marker.")));
+            }
+            let count = (pixel[3] as usize) << shift;
+            if x + count > width {
+                return Err(HdrError::new(String::from("A run extends past the end of the scanline.")));
+            }
+            let previous = scanline[x - 1];
+            for repeated in scanline[x..(x + count)].iter_mut() {
+                *repeated = previous;
+            }
+            x += count;
+            shift += 8;
+        } else {
+            scanline[x] = pixel;
+            x += 1;
+            shift = 0;
+        }
+
+        if x >= width {
+            return Ok(());
+        }
+        pixel = reader.pixel()?;
+    }
+}
+
+/// A cursor over the bytes of an image file.
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> ByteReader<'a> {
+        ByteReader {
+            bytes: bytes,
+            position: 0,
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, HdrError> {
+        match self.bytes.get(self.position) {
+            Some(&byte) => {
+                self.position += 1;
+                Ok(byte)
+            }
+            None => Err(HdrError::new(String::from("Unexpected end of file."))),
+        }
+    }
+
+    fn pixel(&mut self) -> Result<[u8; 4], HdrError> {
+        Ok([self.byte()?, self.byte()?, self.byte()?, self.byte()?])
+    }
+
+    /// Read a line of the header without its line ending.
+    fn line(&mut self) -> Result<String, HdrError> {
+        let rest = &self.bytes[self.position..];
+        let end = rest.iter().position(|&byte| byte == b'\n')
+            .ok_or_else(|| HdrError::new(String::from("Unexpected end of file in the header.")))?;
+        self.position += end + 1;
+        let line = String::from_utf8_lossy(&rest[..end]);
+
+        Ok(String::from(line.trim_end_matches('\r')))
+    }
+}
+
+/// An error from reading a Radiance `.hdr` image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HdrError {
+    message: String,
+}
+
+impl HdrError {
+    fn new(message: String) -> HdrError {
+        HdrError {
+            message: message,
+        }
+    }
+}
+
+impl fmt::Display for HdrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid Radiance HDR image. {}", self.message)
+    }
+}
+
+impl error::Error for HdrError {}
@@ -0,0 +1,495 @@
+use cglinalg::{
+    Magnitude,
+    Matrix4,
+    Quaternion,
+    Radians,
+    Unit,
+    Vector3,
+};
+
+
+/// The position and orientation of the camera, for lights that move with the camera.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CameraFrame {
+    pub position: Vector3<f32>,
+    pub right: Vector3<f32>,
+    pub up: Vector3<f32>,
+    pub forward: Vector3<f32>,
+}
+
+impl CameraFrame {
+    /// Recover the camera frame from the camera's view matrix. The rows of the rotation
+    /// part of the view matrix are the camera's right, up, and backward axes.
+    pub fn from_view_mat(view_mat: &Matrix4<f32>) -> CameraFrame {
+        let right = Vector3::new(view_mat[0][0], view_mat[1][0], view_mat[2][0]);
+        let up = Vector3::new(view_mat[0][1], view_mat[1][1], view_mat[2][1]);
+        let backward = Vector3::new(view_mat[0][2], view_mat[1][2], view_mat[2][2]);
+        let translation = Vector3::new(view_mat[3][0], view_mat[3][1], view_mat[3][2]);
+        let position = -(right * translation[0] + up * translation[1] + backward * translation[2]);
+
+        CameraFrame {
+            position: position,
+            right: right,
+            up: up,
+            forward: -backward,
+        }
+    }
+
+    /// Compute the view matrix of a camera with this frame. This is the inverse of
+    /// `from_view_mat`.
+    pub fn view_mat(&self) -> Matrix4<f32> {
+        let right = self.right;
+        let up = self.up;
+        let backward = -self.forward;
+        let eye = self.position;
+
+        Matrix4::new(
+            right[0],          up[0],          backward[0],          0.0,
+            right[1],          up[1],          backward[1],          0.0,
+            right[2],          up[2],          backward[2],          0.0,
+            -right.dot(&eye),  -up.dot(&eye),  -backward.dot(&eye),  1.0,
+        )
+    }
+}
+
+/// A model of how a light moves through the scene.
+pub trait LightKinematics {
+    /// The position of the light in world space.
+    fn position(&self) -> Vector3<f32>;
+
+    /// The direction the light points in world space, for motion models that steer the
+    /// light as well as move it. Other motion models leave the direction alone.
+    fn direction(&self) -> Option<Vector3<f32>> {
+        None
+    }
+
+    /// Whether the light moves with the camera. The demo does not draw markers for these
+    /// lights, since they would cover the camera's view.
+    fn follows_camera(&self) -> bool {
+        false
+    }
+
+    /// Advance the motion of the light by `elapsed_seconds`.
+    fn update(&mut self, elapsed_seconds: f32, camera: &CameraFrame);
+
+    /// Place the light where its motion puts it `seconds` after the motion started. 
+    /// Every motion model is a function of time, so this computes the state directly
+    /// instead of taking every step up to it.
+    fn seek(&mut self, seconds: f64, camera: &CameraFrame);
+}
+
+/// A light that stays where it was placed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StaticKinematics {
+    position: Vector3<f32>,
+}
+
+impl StaticKinematics {
+    pub fn new(position: Vector3<f32>) -> StaticKinematics {
+        StaticKinematics {
+            position: position,
+        }
+    }
+}
+
+impl LightKinematics for StaticKinematics {
+    fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    fn update(&mut self, _elapsed_seconds: f32, _camera: &CameraFrame) {}
+
+    fn seek(&mut self, _seconds: f64, _camera: &CameraFrame) {}
+}
+
+/// A light moving at a constant speed on a circle. The circle is the path the starting
+/// position of the light sweeps out when rotating about an axis through the center.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CircularOrbit {
+    center: Vector3<f32>,
+    start: Vector3<f32>,
+    axis: Vector3<f32>,
+    /// The angular speed of the light in radians per second.
+    angular_speed: f32,
+    angle: f32,
+    position: Vector3<f32>,
+}
+
+impl CircularOrbit {
+    pub fn new(center: Vector3<f32>, start: Vector3<f32>, axis: Vector3<f32>, angular_speed: f32) -> CircularOrbit {
+        CircularOrbit {
+            center: center,
+            start: start,
+            axis: axis.normalize(),
+            angular_speed: angular_speed,
+            angle: 0.0,
+            position: start,
+        }
+    }
+
+    /// The position of the light after turning by `angle` radians from the start.
+    fn position_at(&self, angle: f32) -> Vector3<f32> {
+        let q = Quaternion::from_axis_angle(&Unit::from_value(self.axis), Radians(angle));
+        let rot_mat = Matrix4::from(q);
+        let offset = rot_mat * (self.start - self.center).extend(0.0);
+
+        self.center + offset.contract()
+    }
+}
+
+impl LightKinematics for CircularOrbit {
+    fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    fn update(&mut self, elapsed_seconds: f32, _camera: &CameraFrame) {
+        self.angle = (self.angle + self.angular_speed * elapsed_seconds) % (2.0 * std::f32::consts::PI);
+        self.position = self.position_at(self.angle);
+    }
+
+    fn seek(&mut self, seconds: f64, _camera: &CameraFrame) {
+        self.angle = ((self.angular_speed as f64 * seconds) % (2.0 * std::f64::consts::PI)) as f32;
+        self.position = self.position_at(self.angle);
+    }
+}
+
+/// A light moving on an ellipse the way a planet orbits a star. The star sits at one
+/// focus of the ellipse, and the light sweeps out equal areas in equal times, so it speeds
+/// up as it swings close to the focus and slows down far away from it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct KeplerOrbit {
+    focus: Vector3<f32>,
+    /// The unit vector pointing from the focus to the point of closest approach.
+    periapsis_direction: Vector3<f32>,
+    /// The unit vector in the plane of the orbit perpendicular to the periapsis direction,
+    /// pointing in the direction of motion at periapsis.
+    prograde_direction: Vector3<f32>,
+    semi_major_axis: f32,
+    eccentricity: f32,
+    /// The time in seconds to complete one orbit.
+    period: f32,
+    /// The fraction of the period elapsed since passing through periapsis, as an angle.
+    mean_anomaly: f32,
+    position: Vector3<f32>,
+}
+
+impl KeplerOrbit {
+    /// Construct an orbit around `focus` in the plane perpendicular to `normal`. The
+    /// eccentricity must lie in `[0, 1)`.
+    pub fn new(
+        focus: Vector3<f32>,
+        periapsis_direction: Vector3<f32>,
+        normal: Vector3<f32>,
+        semi_major_axis: f32,
+        eccentricity: f32,
+        period: f32) -> KeplerOrbit {
+
+        debug_assert!(eccentricity >= 0.0 && eccentricity < 1.0);
+        debug_assert!(period > 0.0);
+        let normal = normal.normalize();
+        // Remove any part of the periapsis direction outside of the plane of the orbit.
+        let periapsis_direction = (periapsis_direction - normal * normal.dot(&periapsis_direction)).normalize();
+        let prograde_direction = normal.cross(&periapsis_direction);
+        let mut orbit = KeplerOrbit {
+            focus: focus,
+            periapsis_direction: periapsis_direction,
+            prograde_direction: prograde_direction,
+            semi_major_axis: semi_major_axis,
+            eccentricity: eccentricity,
+            period: period,
+            mean_anomaly: 0.0,
+            position: focus,
+        };
+        orbit.position = orbit.position_at(0.0);
+
+        orbit
+    }
+
+    /// Solve Kepler's equation `M = E - e * sin(E)` for the eccentric anomaly `E` with
+    /// Newton's method.
+    fn eccentric_anomaly(&self, mean_anomaly: f32) -> f32 {
+        let e = self.eccentricity;
+        let mut eccentric_anomaly = if e < 0.8 { mean_anomaly } else { std::f32::consts::PI };
+        for _ in 0..8 {
+            let f = eccentric_anomaly - e * eccentric_anomaly.sin() - mean_anomaly;
+            let df = 1.0 - e * eccentric_anomaly.cos();
+            eccentric_anomaly -= f / df;
+        }
+
+        eccentric_anomaly
+    }
+
+    fn position_at(&self, mean_anomaly: f32) -> Vector3<f32> {
+        let e = self.eccentricity;
+        let a = self.semi_major_axis;
+        let eccentric_anomaly = self.eccentric_anomaly(mean_anomaly);
+        let x = a * (eccentric_anomaly.cos() - e);
+        let y = a * f32::sqrt(1.0 - e * e) * eccentric_anomaly.sin();
+
+        self.focus + self.periapsis_direction * x + self.prograde_direction * y
+    }
+}
+
+impl LightKinematics for KeplerOrbit {
+    fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    fn update(&mut self, elapsed_seconds: f32, _camera: &CameraFrame) {
+        let two_pi = 2.0 * std::f32::consts::PI;
+        self.mean_anomaly = (self.mean_anomaly + two_pi * elapsed_seconds / self.period) % two_pi;
+        self.position = self.position_at(self.mean_anomaly);
+    }
+
+    fn seek(&mut self, seconds: f64, _camera: &CameraFrame) {
+        let orbits = (seconds / self.period as f64).fract();
+        self.mean_anomaly = (2.0 * std::f64::consts::PI * orbits) as f32;
+        self.position = self.position_at(self.mean_anomaly);
+    }
+}
+
+/// A position the light passes through at a given time along a spline path.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    /// The time in seconds since the start of the path.
+    pub time: f32,
+    pub position: Vector3<f32>,
+}
+
+impl Keyframe {
+    pub fn new(time: f32, position: Vector3<f32>) -> Keyframe {
+        Keyframe {
+            time: time,
+            position: position,
+        }
+    }
+}
+
+/// A light moving along a Catmull-Rom spline passing through a sequence of keyframes.
+/// A looping path returns from its final keyframe to its first one and starts over.
+/// Otherwise, the light stops at the final keyframe.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SplinePath {
+    keyframes: Vec<Keyframe>,
+    /// The duration in seconds of one trip around a looping path, measured from the first
+    /// keyframe's time. It must exceed the time of the final keyframe.
+    period: Option<f32>,
+    time: f32,
+    position: Vector3<f32>,
+}
+
+impl SplinePath {
+    /// Construct a spline path through `keyframes`, which must be sorted by time.
+    pub fn new(keyframes: Vec<Keyframe>, period: Option<f32>) -> SplinePath {
+        debug_assert!(!keyframes.is_empty());
+        debug_assert!(keyframes.windows(2).all(|pair| pair[0].time < pair[1].time));
+        let time = keyframes[0].time;
+        let position = keyframes[0].position;
+        let mut path = SplinePath {
+            keyframes: keyframes,
+            period: period,
+            time: time,
+            position: position,
+        };
+        path.position = path.position_at(time);
+
+        path
+    }
+
+    /// The keyframe `index` positions after keyframe `0`, with its time shifted by
+    /// whole periods so that the times increase along a looping path.
+    fn keyframe(&self, index: isize) -> Keyframe {
+        let len = self.keyframes.len() as isize;
+        match self.period {
+            Some(period) => {
+                let wraps = index.div_euclid(len);
+                let keyframe = self.keyframes[index.rem_euclid(len) as usize];
+                Keyframe::new(keyframe.time + wraps as f32 * period, keyframe.position)
+            }
+            None => self.keyframes[index.max(0).min(len - 1) as usize],
+        }
+    }
+
+    /// The tangent of the spline at keyframe `index`, in units of distance per second.
+    fn tangent(&self, index: isize) -> Vector3<f32> {
+        let previous = self.keyframe(index - 1);
+        let next = self.keyframe(index + 1);
+        let duration = next.time - previous.time;
+        if duration <= 0.0 {
+            return Vector3::zero();
+        }
+
+        (next.position - previous.position) * (1.0 / duration)
+    }
+
+    fn position_at(&self, time: f32) -> Vector3<f32> {
+        let first = self.keyframes[0].time;
+        let time = match self.period {
+            Some(period) => first + (time - first).rem_euclid(period),
+            None => time,
+        };
+        let last_index = self.keyframes.len() as isize - 1;
+        if self.period.is_none() && time >= self.keyframes[last_index as usize].time {
+            return self.keyframes[last_index as usize].position;
+        }
+        if time <= first {
+            return self.keyframes[0].position;
+        }
+
+        // Find the segment containing the time. A looping path has one more segment,
+        // running from the final keyframe back to the first one.
+        let mut index = 0;
+        while index < last_index && self.keyframe(index + 1).time <= time {
+            index += 1;
+        }
+        let start = self.keyframe(index);
+        let end = self.keyframe(index + 1);
+        let duration = end.time - start.time;
+        let t = (time - start.time) / duration;
+        let tangent_start = self.tangent(index) * duration;
+        let tangent_end = self.tangent(index + 1) * duration;
+
+        // Evaluate the cubic Hermite basis.
+        let t2 = t * t;
+        let t3 = t2 * t;
+        let h00 = 2.0 * t3 - 3.0 * t2 + 1.0;
+        let h10 = t3 - 2.0 * t2 + t;
+        let h01 = -2.0 * t3 + 3.0 * t2;
+        let h11 = t3 - t2;
+
+        start.position * h00 + tangent_start * h10 + end.position * h01 + tangent_end * h11
+    }
+}
+
+impl LightKinematics for SplinePath {
+    fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    fn update(&mut self, elapsed_seconds: f32, _camera: &CameraFrame) {
+        let first = self.keyframes[0].time;
+        let last = self.keyframes[self.keyframes.len() - 1].time;
+        self.time = match self.period {
+            // Wrapping the time keeps it small enough to resolve a single timestep.
+            Some(period) => first + (self.time + elapsed_seconds - first).rem_euclid(period),
+            // The light rests at the final keyframe once it gets there.
+            None => (self.time + elapsed_seconds).min(last),
+        };
+        self.position = self.position_at(self.time);
+    }
+
+    fn seek(&mut self, seconds: f64, _camera: &CameraFrame) {
+        let first = self.keyframes[0].time;
+        let last = self.keyframes[self.keyframes.len() - 1].time;
+        let elapsed = match self.period {
+            Some(period) => seconds.rem_euclid(period as f64) as f32,
+            None => seconds.min((last - first) as f64) as f32,
+        };
+        self.time = first + elapsed;
+        self.position = self.position_at(self.time);
+    }
+}
+
+/// A light attached to the camera like a miner's headlamp. The light sits at a fixed
+/// offset from the camera and points where the camera looks.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Headlamp {
+    /// The offset of the light from the camera along the camera's right, up, and
+    /// forward axes.
+    offset: Vector3<f32>,
+    position: Vector3<f32>,
+    direction: Vector3<f32>,
+}
+
+impl Headlamp {
+    pub fn new(offset: Vector3<f32>) -> Headlamp {
+        Headlamp {
+            offset: offset,
+            position: offset,
+            direction: Vector3::new(0.0, 0.0, -1.0),
+        }
+    }
+}
+
+impl LightKinematics for Headlamp {
+    fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    fn direction(&self) -> Option<Vector3<f32>> {
+        Some(self.direction)
+    }
+
+    fn follows_camera(&self) -> bool {
+        true
+    }
+
+    fn update(&mut self, _elapsed_seconds: f32, camera: &CameraFrame) {
+        self.position = camera.position +
+            camera.right * self.offset[0] +
+            camera.up * self.offset[1] +
+            camera.forward * self.offset[2];
+        self.direction = camera.forward;
+    }
+
+    fn seek(&mut self, _seconds: f64, camera: &CameraFrame) {
+        self.update(0.0, camera);
+    }
+}
+
+/// The description of a light's motion model, from which the demo builds the model
+/// for each light.
+#[derive(Clone, Debug, PartialEq)]
+pub enum KinematicsConfig {
+    Static {
+        position: Vector3<f32>,
+    },
+    CircularOrbit {
+        center: Vector3<f32>,
+        start: Vector3<f32>,
+        axis: Vector3<f32>,
+        angular_speed: f32,
+    },
+    KeplerOrbit {
+        focus: Vector3<f32>,
+        periapsis_direction: Vector3<f32>,
+        normal: Vector3<f32>,
+        semi_major_axis: f32,
+        eccentricity: f32,
+        period: f32,
+    },
+    SplinePath {
+        keyframes: Vec<Keyframe>,
+        period: Option<f32>,
+    },
+    Headlamp {
+        offset: Vector3<f32>,
+    },
+}
+
+impl KinematicsConfig {
+    /// Construct the motion model the configuration describes.
+    pub fn build(&self) -> Box<dyn LightKinematics> {
+        match *self {
+            KinematicsConfig::Static { position } => {
+                Box::new(StaticKinematics::new(position))
+            }
+            KinematicsConfig::CircularOrbit { center, start, axis, angular_speed } => {
+                Box::new(CircularOrbit::new(center, start, axis, angular_speed))
+            }
+            KinematicsConfig::KeplerOrbit {
+                focus, periapsis_direction, normal, semi_major_axis, eccentricity, period
+            } => {
+                Box::new(KeplerOrbit::new(
+                    focus, periapsis_direction, normal, semi_major_axis, eccentricity, period
+                ))
+            }
+            KinematicsConfig::SplinePath { ref keyframes, period } => {
+                Box::new(SplinePath::new(keyframes.clone(), period))
+            }
+            KinematicsConfig::Headlamp { offset } => {
+                Box::new(Headlamp::new(offset))
+            }
+        }
+    }
+}
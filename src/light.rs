@@ -1,22 +1,515 @@
+use crate::photometry;
+use crate::photometry::LuminousPower;
 use cglinalg::{
     Vector3,
+    Magnitude,
+    Radians,
     ScalarFloat,
 };
 
 
+/// The model describing how the intensity of a light falls off with the distance 
+/// from the light.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Attenuation<S> {
+    /// The light illuminates every point identically regardless of its distance.
+    None,
+    /// The classic fixed function attenuation model 
+    /// `1 / (constant + linear * d + quadratic * d^2)`.
+    Polynomial {
+        constant: S,
+        linear: S,
+        quadratic: S,
+    },
+    /// A physically based inverse square falloff that is windowed to reach
+    /// zero smoothly at the cutoff radius, c.f. `Karis, Real Shading in Unreal Engine 4, 2013`.
+    InverseSquare {
+        radius: S,
+    },
+}
+
+impl<S> Attenuation<S> where S: ScalarFloat {
+    /// The integer tag identifying the attenuation model in the shader. These must agree
+    /// with the `ATTENUATION_*` constants in `mesh.frag.glsl`.
+    pub fn type_id(&self) -> i32 {
+        match *self {
+            Attenuation::None => 0,
+            Attenuation::Polynomial { .. } => 1,
+            Attenuation::InverseSquare { .. } => 2,
+        }
+    }
+
+    /// The coefficients of the attenuation model in the layout the shader expects. 
+    /// The polynomial model stores its constant, linear, and quadratic coefficients, and
+    /// the inverse square model stores its cutoff radius in the first component.
+    pub fn coefficients(&self) -> Vector3<S> {
+        match *self {
+            Attenuation::None => Vector3::zero(),
+            Attenuation::Polynomial { constant, linear, quadratic } => {
+                Vector3::new(constant, linear, quadratic)
+            }
+            Attenuation::InverseSquare { radius } => {
+                Vector3::new(radius, S::zero(), S::zero())
+            }
+        }
+    }
+
+    /// Compute the fraction of a light's intensity that reaches a point at a distance
+    /// `distance` from the light. This is the CPU reference implementation of the 
+    /// attenuation function in `mesh.frag.glsl`.
+    pub fn evaluate(&self, distance: S) -> S {
+        match *self {
+            Attenuation::None => S::one(),
+            Attenuation::Polynomial { constant, linear, quadratic } => {
+                S::one() / (constant + linear * distance + quadratic * distance * distance)
+            }
+            Attenuation::InverseSquare { radius } => {
+                let ratio = distance / radius;
+                let ratio_4 = ratio * ratio * ratio * ratio;
+                let window = (S::one() - ratio_4).max(S::zero()).min(S::one());
+
+                (window * window) / (distance * distance + S::one())
+            }
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct PointLight<S> {
     pub ambient: Vector3<S>,
     pub diffuse: Vector3<S>,
     pub specular: Vector3<S>,
+    pub attenuation: Attenuation<S>,
+    /// The radius of the sphere emitting the light. Larger lights cast softer shadows.
+    pub radius: S,
 }
 
 impl<S> PointLight<S> where S: ScalarFloat {
-    pub fn new(ambient: Vector3<S>, diffuse: Vector3<S>, specular: Vector3<S>) -> PointLight<S> {
+    pub fn new(
+        ambient: Vector3<S>,
+        diffuse: Vector3<S>,
+        specular: Vector3<S>,
+        attenuation: Attenuation<S>,
+        radius: S) -> PointLight<S> {
+
         PointLight {
             ambient: ambient,
             diffuse: diffuse,
             specular: specular,
+            attenuation: attenuation,
+            radius: radius,
+        }
+    }
+}
+
+impl PointLight<f32> {
+    /// Construct a point light from its color temperature in Kelvin and its luminous power. 
+    /// The diffuse and specular components of the light are its color scaled by its luminous 
+    /// intensity in candela, and the ambient component is the fraction `ambient_fraction` of 
+    /// the diffuse component.
+    pub fn from_photometric(
+        temperature: f32,
+        power: LuminousPower,
+        ambient_fraction: f32,
+        attenuation: Attenuation<f32>,
+        radius: f32) -> PointLight<f32> {
+
+        let intensity = photometry::luminous_intensity(power, std::f32::consts::PI);
+        let color = photometry::color_temperature_to_rgb(temperature) * intensity;
+
+        PointLight::new(color * ambient_fraction, color, color, attenuation, radius)
+    }
+}
+
+/// A directional light models a light source that is infinitely far away from the
+/// scene, such as the sun. Every fragment receives light from the same direction,
+/// regardless of where the light is placed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DirectionalLight<S> {
+    pub ambient: Vector3<S>,
+    pub diffuse: Vector3<S>,
+    pub specular: Vector3<S>,
+    /// The direction the light travels in world space.
+    pub direction: Vector3<S>,
+    /// The angular radius of the light's disk as seen from the scene. Larger lights 
+    /// cast softer shadows.
+    pub angular_radius: Radians<S>,
+}
+
+impl<S> DirectionalLight<S> where S: ScalarFloat {
+    pub fn new(
+        ambient: Vector3<S>,
+        diffuse: Vector3<S>,
+        specular: Vector3<S>,
+        direction: Vector3<S>,
+        angular_radius: Radians<S>) -> DirectionalLight<S> {
+
+        DirectionalLight {
+            ambient: ambient,
+            diffuse: diffuse,
+            specular: specular,
+            direction: direction.normalize(),
+            angular_radius: angular_radius,
+        }
+    }
+}
+
+impl DirectionalLight<f32> {
+    /// Construct a directional light from its color temperature in Kelvin and the 
+    /// illuminance in lux it casts onto a surface facing the light.
+    pub fn from_photometric(
+        temperature: f32,
+        illuminance: f32,
+        ambient_fraction: f32,
+        direction: Vector3<f32>,
+        angular_radius: Radians<f32>) -> DirectionalLight<f32> {
+
+        let color = photometry::color_temperature_to_rgb(temperature) * illuminance;
+
+        DirectionalLight::new(color * ambient_fraction, color, color, direction, angular_radius)
+    }
+}
+
+/// A spot light emits light from a point in a cone around its direction. Fragments
+/// inside the inner cone receive the full intensity of the light, fragments outside the
+/// outer cone receive none of it, and the falloff exponent shapes the transition between
+/// the two cones.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpotLight<S> {
+    pub ambient: Vector3<S>,
+    pub diffuse: Vector3<S>,
+    pub specular: Vector3<S>,
+    /// The direction the light points in world space.
+    pub direction: Vector3<S>,
+    /// The half-angle of the cone of full intensity.
+    pub inner_cutoff: Radians<S>,
+    /// The half-angle of the cone outside of which the light contributes nothing.
+    pub outer_cutoff: Radians<S>,
+    /// The exponent applied to the transition between the inner and outer cones.
+    pub falloff: S,
+    pub attenuation: Attenuation<S>,
+    /// The radius of the disk emitting the light. Larger lights cast softer shadows.
+    pub radius: S,
+}
+
+impl<S> SpotLight<S> where S: ScalarFloat {
+    pub fn new(
+        ambient: Vector3<S>,
+        diffuse: Vector3<S>,
+        specular: Vector3<S>,
+        direction: Vector3<S>,
+        inner_cutoff: Radians<S>,
+        outer_cutoff: Radians<S>,
+        falloff: S,
+        attenuation: Attenuation<S>,
+        radius: S) -> SpotLight<S> {
+
+        debug_assert!(inner_cutoff.0 <= outer_cutoff.0);
+        SpotLight {
+            ambient: ambient,
+            diffuse: diffuse,
+            specular: specular,
+            direction: direction.normalize(),
+            inner_cutoff: inner_cutoff,
+            outer_cutoff: outer_cutoff,
+            falloff: falloff,
+            attenuation: attenuation,
+            radius: radius,
+        }
+    }
+}
+
+impl SpotLight<f32> {
+    /// Construct a spot light from its color temperature in Kelvin and its luminous power.
+    /// A spot light given in lumens spreads its flux over its outer cone, so narrowing the 
+    /// cone concentrates the same flux into a brighter beam.
+    pub fn from_photometric(
+        temperature: f32,
+        power: LuminousPower,
+        ambient_fraction: f32,
+        direction: Vector3<f32>,
+        inner_cutoff: Radians<f32>,
+        outer_cutoff: Radians<f32>,
+        falloff: f32,
+        attenuation: Attenuation<f32>,
+        radius: f32) -> SpotLight<f32> {
+
+        let intensity = photometry::luminous_intensity(power, outer_cutoff.0);
+        let color = photometry::color_temperature_to_rgb(temperature) * intensity;
+
+        SpotLight::new(
+            color * ambient_fraction, color, color, direction, 
+            inner_cutoff, outer_cutoff, falloff, attenuation, radius
+        )
+    }
+}
+
+/// The shape of the emitting surface of an area light.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AreaLightShape<S> {
+    Rectangle {
+        half_width: S,
+        half_height: S,
+    },
+    Disk {
+        radius: S,
+    },
+}
+
+impl AreaLightShape<f32> {
+    /// The area of the emitting surface.
+    pub fn area(&self) -> f32 {
+        match *self {
+            AreaLightShape::Rectangle { half_width, half_height } => 4.0 * half_width * half_height,
+            AreaLightShape::Disk { radius } => std::f32::consts::PI * radius * radius,
+        }
+    }
+}
+
+/// An area light emits light from one side of a flat rectangle or disk, such as a studio
+/// softbox. The shader integrates the light over its surface using linearly transformed
+/// cosines, so the light produces soft highlights whose shape matches the light.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AreaLight<S> {
+    pub ambient: Vector3<S>,
+    pub diffuse: Vector3<S>,
+    pub specular: Vector3<S>,
+    /// The direction the emitting side of the light faces in world space.
+    pub direction: Vector3<S>,
+    pub shape: AreaLightShape<S>,
+}
+
+impl<S> AreaLight<S> where S: ScalarFloat {
+    pub fn new(
+        ambient: Vector3<S>,
+        diffuse: Vector3<S>,
+        specular: Vector3<S>,
+        direction: Vector3<S>,
+        shape: AreaLightShape<S>) -> AreaLight<S> {
+
+        AreaLight {
+            ambient: ambient,
+            diffuse: diffuse,
+            specular: specular,
+            direction: direction.normalize(),
+            shape: shape,
+        }
+    }
+
+    /// Compute the unit vectors `(right, up)` spanning the plane of the light. They 
+    /// satisfy `right x up == direction`.
+    pub fn basis(&self) -> (Vector3<S>, Vector3<S>) {
+        let threshold = S::one() - S::epsilon().sqrt();
+        let up_hint = if self.direction[1].abs() < threshold {
+            Vector3::unit_y()
+        } else {
+            Vector3::unit_z()
+        };
+        let right = self.direction.cross(&up_hint).normalize();
+        let up = self.direction.cross(&right);
+
+        (right, up)
+    }
+
+    /// The integer tag identifying the shape of the light in the shader. These must agree 
+    /// with the `AREA_LIGHT_SHAPE_*` constants in `mesh.frag.glsl`.
+    pub fn shape_id(&self) -> i32 {
+        match self.shape {
+            AreaLightShape::Rectangle { .. } => 0,
+            AreaLightShape::Disk { .. } => 1,
+        }
+    }
+
+    /// The half extents of the light along its right and up vectors.
+    pub fn half_extents(&self) -> (S, S) {
+        match self.shape {
+            AreaLightShape::Rectangle { half_width, half_height } => (half_width, half_height),
+            AreaLightShape::Disk { radius } => (radius, radius),
+        }
+    }
+}
+
+impl AreaLight<f32> {
+    /// Construct an area light from its color temperature in Kelvin and the luminous flux 
+    /// in lumens it emits. The flux is spread evenly over the surface of the light and the
+    /// hemisphere in front of it, so the light's components are its luminance in nits.
+    pub fn from_photometric(
+        temperature: f32,
+        lumens: f32,
+        ambient_fraction: f32,
+        direction: Vector3<f32>,
+        shape: AreaLightShape<f32>) -> AreaLight<f32> {
+
+        let luminance = lumens / (std::f32::consts::PI * shape.area());
+        let color = photometry::color_temperature_to_rgb(temperature) * luminance;
+
+        AreaLight::new(color * ambient_fraction, color, color, direction, shape)
+    }
+}
+
+/// The types of light sources the shaders know how to illuminate a scene with.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LightSource<S> {
+    Point(PointLight<S>),
+    Directional(DirectionalLight<S>),
+    Spot(SpotLight<S>),
+    Area(AreaLight<S>),
+}
+
+impl<S> LightSource<S> where S: ScalarFloat {
+    /// The integer tag identifying the light type in the shader. These must agree with
+    /// the `LIGHT_TYPE_*` constants in `mesh.frag.glsl`.
+    pub fn type_id(&self) -> i32 {
+        match *self {
+            LightSource::Point(_) => 0,
+            LightSource::Directional(_) => 1,
+            LightSource::Spot(_) => 2,
+            LightSource::Area(_) => 3,
+        }
+    }
+
+    pub fn ambient(&self) -> Vector3<S> {
+        match *self {
+            LightSource::Point(ref light) => light.ambient,
+            LightSource::Directional(ref light) => light.ambient,
+            LightSource::Spot(ref light) => light.ambient,
+            LightSource::Area(ref light) => light.ambient,
+        }
+    }
+
+    pub fn diffuse(&self) -> Vector3<S> {
+        match *self {
+            LightSource::Point(ref light) => light.diffuse,
+            LightSource::Directional(ref light) => light.diffuse,
+            LightSource::Spot(ref light) => light.diffuse,
+            LightSource::Area(ref light) => light.diffuse,
+        }
+    }
+
+    pub fn specular(&self) -> Vector3<S> {
+        match *self {
+            LightSource::Point(ref light) => light.specular,
+            LightSource::Directional(ref light) => light.specular,
+            LightSource::Spot(ref light) => light.specular,
+            LightSource::Area(ref light) => light.specular,
+        }
+    }
+
+    /// The direction of the light in world space. Point lights emit in every
+    /// direction, so they have no direction.
+    pub fn direction(&self) -> Vector3<S> {
+        match *self {
+            LightSource::Point(_) => Vector3::zero(),
+            LightSource::Directional(ref light) => light.direction,
+            LightSource::Spot(ref light) => light.direction,
+            LightSource::Area(ref light) => light.direction,
+        }
+    }
+
+    /// Point the light in the direction `direction` in world space. Point lights emit in
+    /// every direction, so this does nothing to them.
+    pub fn set_direction(&mut self, direction: Vector3<S>) {
+        let direction = direction.normalize();
+        match *self {
+            LightSource::Point(_) => {}
+            LightSource::Directional(ref mut light) => light.direction = direction,
+            LightSource::Spot(ref mut light) => light.direction = direction,
+            LightSource::Area(ref mut light) => light.direction = direction,
+        }
+    }
+
+    /// The cosines of the inner and outer cone angles of a spot light. Other light
+    /// types illuminate every direction, so their cones cover the whole sphere.
+    pub fn cos_cutoffs(&self) -> (S, S) {
+        match *self {
+            LightSource::Spot(ref light) => (light.inner_cutoff.0.cos(), light.outer_cutoff.0.cos()),
+            _ => (-S::one(), -S::one()),
+        }
+    }
+
+    pub fn falloff(&self) -> S {
+        match *self {
+            LightSource::Spot(ref light) => light.falloff,
+            _ => S::one(),
+        }
+    }
+
+    /// The radius of the emitting surface of a point or spot light. The size of a 
+    /// directional light is an angle rather than a distance, and area lights do not
+    /// cast shadows, so they report zero.
+    pub fn radius(&self) -> S {
+        match *self {
+            LightSource::Point(ref light) => light.radius,
+            LightSource::Spot(ref light) => light.radius,
+            _ => S::zero(),
+        }
+    }
+
+    /// The distance attenuation model of the light. Directional lights are infinitely
+    /// far away, so they do not attenuate. The falloff of an area light follows from
+    /// integrating over its surface, so it needs no separate attenuation model.
+    pub fn attenuation(&self) -> Attenuation<S> {
+        match *self {
+            LightSource::Point(ref light) => light.attenuation,
+            LightSource::Directional(_) => Attenuation::None,
+            LightSource::Spot(ref light) => light.attenuation,
+            LightSource::Area(_) => Attenuation::None,
+        }
+    }
+}
+
+impl LightSource<f32> {
+    /// The color of the light's diffuse component with its luminance normalized to one.
+    pub fn color(&self) -> Vector3<f32> {
+        let intensity = self.intensity();
+        if intensity > 0.0 {
+            self.diffuse() * (1.0 / intensity)
+        } else {
+            self.diffuse()
+        }
+    }
+
+    /// The luminance of the light's diffuse component. This is the luminous intensity in
+    /// candela of a point or spot light, the illuminance in lux of a directional light,
+    /// and the luminance in nits of an area light.
+    pub fn intensity(&self) -> f32 {
+        photometry::luminance(&self.diffuse())
+    }
+
+    /// Change the color and intensity of the light. The color is normalized to unit
+    /// luminance, and the ambient component keeps its proportion of the diffuse component.
+    pub fn set_emission(&mut self, color: Vector3<f32>, intensity: f32) {
+        let color_luminance = photometry::luminance(&color);
+        let color = if color_luminance > 0.0 { color * (1.0 / color_luminance) } else { color };
+        let old_intensity = self.intensity();
+        let ambient_fraction = if old_intensity > 0.0 {
+            photometry::luminance(&self.ambient()) / old_intensity
+        } else {
+            0.0
+        };
+        let emission = color * intensity;
+        let ambient = emission * ambient_fraction;
+        match *self {
+            LightSource::Point(ref mut light) => {
+                light.ambient = ambient;
+                light.diffuse = emission;
+                light.specular = emission;
+            }
+            LightSource::Directional(ref mut light) => {
+                light.ambient = ambient;
+                light.diffuse = emission;
+                light.specular = emission;
+            }
+            LightSource::Spot(ref mut light) => {
+                light.ambient = ambient;
+                light.diffuse = emission;
+                light.specular = emission;
+            }
+            LightSource::Area(ref mut light) => {
+                light.ambient = ambient;
+                light.diffuse = emission;
+                light.specular = emission;
+            }
         }
     }
 }
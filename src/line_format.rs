@@ -0,0 +1,70 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+use std::path::Path;
+
+
+/// Read the statements of a line based text file, the format of the light rig, input
+/// recording, and timeline files. Each line holds one statement as a list of words
+/// separated by whitespace. Blank lines and everything after a `#` are ignored. The
+/// function `statement` receives the line number, counted from one, and the words of
+/// each statement, and reading stops at the first error.
+pub fn read_statements<R, F>(reader: R, kind: &'static str, mut statement: F) -> Result<(), FormatError>
+where
+    R: BufRead,
+    F: FnMut(usize, &[&str]) -> Result<(), FormatError>,
+{
+    for (line_number, line) in reader.lines().enumerate() {
+        let line_number = line_number + 1;
+        let line = line.map_err(|e| FormatError::new(kind, line_number, e.to_string()))?;
+        let line = match line.find('#') {
+            Some(index) => &line[..index],
+            None => &line[..],
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+
+        statement(line_number, &words)?;
+    }
+
+    Ok(())
+}
+
+/// An error from reading or writing a line based text file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatError {
+    /// The kind of file, such as `light rig`, that the message names.
+    kind: &'static str,
+    /// The line of the file the error occurred on, or zero if it does not belong to a line.
+    line: usize,
+    message: String,
+}
+
+impl FormatError {
+    pub fn new(kind: &'static str, line: usize, message: String) -> FormatError {
+        FormatError {
+            kind: kind,
+            line: line,
+            message: message,
+        }
+    }
+
+    pub fn io<P: AsRef<Path>>(kind: &'static str, path: P, error: io::Error) -> FormatError {
+        FormatError::new(kind, 0, format!("Could not access `{}`: {}", path.as_ref().display(), error))
+    }
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line > 0 {
+            write!(f, "Invalid {} on line {}. {}", self.kind, self.line, self.message)
+        } else {
+            write!(f, "Invalid {}. {}", self.kind, self.message)
+        }
+    }
+}
+
+impl error::Error for FormatError {}
@@ -10,12 +10,42 @@ mod gl {
     include!(concat!(env!("OUT_DIR"), "/gl_bindings.rs"));
 }
 
+/// The linearly transformed cosine lookup tables for shading area lights. The build
+/// script fits these to the Blinn-Phong lobe, c.f. `ltc_fit.rs`.
+mod ltc {
+    include!(concat!(env!("OUT_DIR"), "/ltc_tables.rs"));
+}
+
 mod backend;
+mod clock;
+mod environment;
+mod hdr;
+mod kinematics;
 mod light;
+mod line_format;
 mod material;
+mod mesh;
+mod optimize;
+mod photometry;
+mod primitives;
+mod recording;
+mod rig;
+mod sh;
+mod shadow;
+mod subdivision;
+mod teapot;
+mod text;
+mod timeline;
+mod vector;
 
 use backend::{
     OpenGLContext,
+    Texture,
+    TextureFormat,
+};
+use clock::{
+    ClockMode,
+    SimClock,
 };
 use cgperspective::{
     SimpleCameraMovement,
@@ -27,21 +57,64 @@ use cgperspective::{
     CameraAttitudeSpec,
     Camera
 };
-use light::PointLight;
-use material::Material;
+use environment::{
+    AmbientLighting,
+    CubeMap,
+    Environment,
+    SKYBOX_SIZE,
+    SPECULAR_MAP_EXPONENT,
+};
+use hdr::HdrImage;
+use kinematics::{
+    CameraFrame,
+    LightKinematics,
+};
+use light::LightSource;
+use material::{
+    Material,
+    TEAPOTS_MATERIALS,
+};
+use mesh::IndexedMesh;
+use recording::{
+    InputRecording,
+    MovementInput,
+};
+use rig::{
+    Emitter,
+    LightRig,
+    LightSpec,
+};
+use sh::SphericalHarmonics;
+use shadow::{
+    DepthBias,
+    ShadowFilter,
+    ShadowMaps,
+    ShadowProjection,
+    CUBE_FACES,
+    MAX_POINT_SHADOW_MAPS,
+    MAX_SHADOW_MAPS,
+    POINT_SHADOW_FAR_PLANE,
+    POINT_SHADOW_NEAR_PLANE,
+    POINT_SHADOW_MAP_SIZE,
+    SHADOW_MAP_SIZE,
+};
+use subdivision::SubdivisionScheme;
+use text::FontAtlas;
+use timeline::{
+    Timeline,
+    TrackTarget,
+};
 use cglinalg::{
     Degrees,
-    Quaternion,
     Magnitude,
     Matrix4,
-    Radians,
     Vector3,
-    Unit,
 };
 use glfw::{
     Action, 
     Context, 
-    Key
+    Key,
+    WindowEvent,
 };
 use gl::types::{
     GLfloat,
@@ -52,8 +125,13 @@ use gl::types::{
 };
 use log::{info};
 use mini_obj::ObjMesh;
+use std::collections::hash_map::HashMap;
+use std::env;
 use std::io;
+use std::fs;
 use std::mem;
+use std::path::Path;
+use std::process;
 use std::ptr;
 
 
@@ -65,6 +143,96 @@ const CLEAR_DEPTH: [f32; 4] = [1.0_f32, 1.0_f32, 1.0_f32, 1.0_f32];
 const SCREEN_WIDTH: u32 = 800;
 const SCREEN_HEIGHT: u32 = 600;
 
+// The maximum number of lights the mesh shader can illuminate a scene with. This must 
+// agree with `MAX_LIGHTS` in `mesh.frag.glsl`.
+const MAX_LIGHTS: usize = 8;
+// The maximum number of area lights the mesh shader can illuminate a scene with. This must
+// agree with `MAX_AREA_LIGHTS` in `mesh.frag.glsl`.
+const MAX_AREA_LIGHTS: usize = 4;
+
+// The texture units the area light lookup tables are bound to.
+const LTC_1_TEXTURE_UNIT: GLuint = 0;
+const LTC_2_TEXTURE_UNIT: GLuint = 1;
+// The texture units the shadow maps are bound to.
+const SHADOW_MAPS_TEXTURE_UNIT: GLuint = 2;
+const POINT_SHADOW_MAPS_TEXTURE_UNIT: GLuint = 3;
+// The texture units the diffuse irradiance and prefiltered specular maps of the 
+// environment are bound to.
+const IRRADIANCE_MAP_TEXTURE_UNIT: GLuint = 4;
+const SPECULAR_MAP_TEXTURE_UNIT: GLuint = 5;
+// The texture unit the skybox is bound to.
+const SKYBOX_TEXTURE_UNIT: GLuint = 6;
+// The texture unit the glyphs of the label font are bound to.
+const FONT_ATLAS_TEXTURE_UNIT: GLuint = 7;
+// The width and height in pixels of each cube face in the shadow map debug view.
+const SHADOW_DEBUG_VIEW_SIZE: u32 = 160;
+
+// The radius of the sphere around the scene center enclosing every object that casts 
+// or receives shadows.
+const SCENE_RADIUS: f32 = 6.0;
+// The f-number, shutter time in seconds, and ISO sensitivity of the camera. The lights 
+// are specified in photometric units, so these map them to display values the way a 
+// camera set for a dim interior would, at about EV100 6.
+const DEFAULT_APERTURE: f32 = 2.0;
+const DEFAULT_SHUTTER_TIME: f32 = 1.0 / 60.0;
+const DEFAULT_ISO: f32 = 400.0;
+// The luminance in nits of an environment texel with a value of one. Environment images
+// store relative radiance, so this places them on the same scale as the lights.
+const ENVIRONMENT_LUMINANCE: f32 = 40.0;
+// The number of degrees each press of the environment rotation keys turns the environment
+// around the vertical axis.
+const ENVIRONMENT_ROTATION_STEP_DEGREES: f32 = 15.0;
+// The files of the faces of a cube map environment, in the order `+X`, `-X`, `+Y`, `-Y`, 
+// `+Z`, `-Z`.
+const ENVIRONMENT_FACE_FILES: [&str; 6] = ["px.hdr", "nx.hdr", "py.hdr", "ny.hdr", "pz.hdr", "nz.hdr"];
+// The half width of the ground plane the scene stands on.
+const GROUND_PLANE_HALF_SIZE: f32 = 4.0;
+// The positions on the ground plane and the sizes of the objects in the scene when no
+// meshes are loaded from files.
+const PREVIEW_PLACEMENTS: [([f32; 2], f32); 2] = [([0.0, 0.0], 1.0), ([-1.0, -1.0], 0.5)];
+// The number of quads along each side of the patches of the teapot.
+const TEAPOT_LEVEL_OF_DETAIL: usize = 10;
+// The number of times to subdivide the box for the rounded preview shape.
+const SUBDIVIDED_BOX_LEVELS: usize = 3;
+// The most levels of subdivision the command line accepts. Each level multiplies the
+// number of triangles by four.
+const MAX_SUBDIVISION_LEVELS: usize = 6;
+// The number of columns and rows of the material gallery, as in `teapots.c`.
+const GALLERY_COLUMNS: usize = 4;
+const GALLERY_ROWS: usize = 6;
+// The transparent materials without an opaque version in `teapots.c`, which the material
+// gallery shows in an extra row below the others.
+const GALLERY_EXTRA_MATERIALS: [&str; 2] = ["glass", "diamond"];
+// The width and height of each cell of the material gallery. The cells are wide enough 
+// that the longest labels of neighboring cells do not overlap.
+const GALLERY_CELL_WIDTH: f32 = 1.7;
+const GALLERY_CELL_HEIGHT: f32 = 0.8;
+// The depth of the plane the material gallery stands in, far enough from the camera
+// that every row fits on the screen.
+const GALLERY_DEPTH: f32 = -1.5;
+// The size of the objects of the material gallery relative to the unit cube.
+const GALLERY_OBJECT_SCALE: f32 = 0.9;
+// How far below the center of its cell each label of the material gallery hangs.
+const GALLERY_LABEL_DROP: f32 = 0.3;
+// The width and height in pixels of each pixel of the label font.
+const LABEL_SCALE: f32 = 2.0;
+// The distance between the centers of neighboring meshes loaded from files.
+const MESH_SPACING: f32 = 1.5;
+// The materials of the meshes loaded from files, in the order they are given.
+const MESH_MATERIALS: [&str; 6] = ["jade", "ruby", "gold", "pearl", "chrome", "turquoise"];
+// The length in seconds of each step of the simulation. The lights and the camera move
+// in steps of this length regardless of the frame rate.
+const SIMULATION_TIMESTEP: f32 = 1.0 / 120.0;
+// The latest tick the simulation can start at on virtual time, a little over a year of
+// simulated time.
+const MAX_START_TICK: u64 = 1 << 32;
+// The file the timeline is loaded from and saved to.
+const TIMELINE_PATH: &str = "timelines/demo.timeline";
+// The directory light rigs are saved to and loaded from.
+const RIGS_DIRECTORY: &str = "rigs";
+// The number of seconds each press of the scrub keys moves the timeline's playhead.
+const TIMELINE_SCRUB_SECONDS: f32 = 0.5;
+
 
 type PerspFovCamera<S> = Camera<S, PerspectiveFovProjection<S>, FreeKinematics<S>>;
 
@@ -125,128 +293,532 @@ fn create_camera(width: u32, height: u32) -> PerspFovCamera<f32> {
 }
 
 struct Light {
-    light: PointLight<f32>,
-    kinematics: LightKinematics,
-}
-
-fn create_lights(scene_center_world: Vector3<f32>) -> [Light; 3] {
-    let ambient_0 = Vector3::new(0.2, 0.2, 0.2);
-    let diffuse_0 = Vector3::new(0.5, 0.5, 0.5);
-    let specular_0 = Vector3::new(1.0, 1.0, 1.0);
-    let point_light_0 = PointLight::new(ambient_0, diffuse_0, specular_0);
-    let orbital_axis_0 = Vector3::new(0.0, 1.0, 1.0).normalize();
-    let orbital_speed_0 = 3.14159265;
-    let radial_speed_0 = 0.0;
-    let center_of_oscillation_0 = Vector3::new(3.0, 0.0, 0.0);
-    let radius_of_oscillation_0 = 0.4;
-    let kinematics_0= LightKinematics::new(
-        scene_center_world, radial_speed_0, 
-        center_of_oscillation_0, radius_of_oscillation_0, 
-        orbital_axis_0, orbital_speed_0
-    );
-    let light_0 = Light { light: point_light_0, kinematics: kinematics_0 };
-
-    let ambient_1 = Vector3::new(0.2, 0.2, 0.2);
-    let diffuse_1 = Vector3::new(0.5, 0.5, 0.5);
-    let specular_1 = Vector3::new(1.0, 1.0, 1.0);
-    let point_light_1 = PointLight::new(ambient_1, diffuse_1, specular_1);
-    let orbital_axis_1 = Vector3::new(0.0, 0.0, 1.0).normalize();
-    let orbital_speed_1 = 1.5;
-    let radial_speed_1 = 0.0;
-    let center_of_oscillation_1 = Vector3::new(0.0, 6.0, 0.0);
-    let radius_of_oscillation_1 = 1.0;
-    let kinematics_1= LightKinematics::new(
-        scene_center_world, radial_speed_1, 
-        center_of_oscillation_1, radius_of_oscillation_1, 
-        orbital_axis_1, orbital_speed_1
-    );
-    let light_1 = Light { light: point_light_1, kinematics: kinematics_1 };
-
-    let ambient_2 = Vector3::new(0.2, 0.2, 0.2);
-    let diffuse_2 = Vector3::new(0.5, 0.5, 0.5);
-    let specular_2 = Vector3::new(1.0, 1.0, 1.0);
-    let point_light_2 = PointLight::new(ambient_2, diffuse_2, specular_2);
-    let orbital_axis_2 = Vector3::new(1.0, 0.0, 0.0).normalize();
-    let orbital_speed_2 = 0.5;
-    let radial_speed_2 = 0.0;
-    let center_of_oscillation_2 = Vector3::new(0.0, 5.0, 5.0);
-    let radius_of_oscillation_2 = 0.25;
-    let kinematics_2= LightKinematics::new(
-        scene_center_world, radial_speed_2, 
-        center_of_oscillation_2, radius_of_oscillation_2, 
-        orbital_axis_2, orbital_speed_2
-    );
-    let light_2 = Light { light: point_light_2, kinematics: kinematics_2 };
+    light: LightSource<f32>,
+    kinematics: Box<dyn LightKinematics>,
+    casts_shadows: bool,
+    /// The position of the light before the last simulation step.
+    previous_position: Vector3<f32>,
+    /// The position of the light in the frame being rendered, which lies between its
+    /// positions before and after the last simulation step.
+    interpolated_position: Vector3<f32>,
+    /// The position the timeline places the light at, which takes precedence over the
+    /// position from the light's motion model.
+    position_override: Option<Vector3<f32>>,
+    /// The color the light was created with, which the light returns to when the 
+    /// timeline stops animating it.
+    color: Vector3<f32>,
+    /// The intensity the light was created with.
+    intensity: f32,
+}
 
-    [light_0, light_1, light_2]
+impl Light {
+    fn new(light: LightSource<f32>, kinematics: Box<dyn LightKinematics>, casts_shadows: bool) -> Light {
+        let position = kinematics.position();
+
+        Light {
+            light: light,
+            kinematics: kinematics,
+            casts_shadows: casts_shadows,
+            previous_position: position,
+            interpolated_position: position,
+            position_override: None,
+            color: light.color(),
+            intensity: light.intensity(),
+        }
+    }
+
+    /// The position of the light in world space.
+    fn position(&self) -> Vector3<f32> {
+        self.position_override.unwrap_or(self.interpolated_position)
+    }
+
+    /// Advance the motion of the light by one simulation step of `timestep` seconds.
+    fn step(&mut self, timestep: f32, camera: &CameraFrame) {
+        self.previous_position = self.kinematics.position();
+        self.kinematics.update(timestep, camera);
+        if let Some(direction) = self.kinematics.direction() {
+            self.light.set_direction(direction);
+        }
+    }
+
+    /// Place the light where its motion puts it `seconds` after the simulation started.
+    fn seek(&mut self, seconds: f64, camera: &CameraFrame) {
+        self.kinematics.seek(seconds, camera);
+        if let Some(direction) = self.kinematics.direction() {
+            self.light.set_direction(direction);
+        }
+        self.previous_position = self.kinematics.position();
+        self.interpolated_position = self.previous_position;
+    }
+
+    /// Place the light the fraction `alpha` of the way from its position before the last
+    /// simulation step to its position after it.
+    fn interpolate(&mut self, alpha: f32) {
+        let current_position = self.kinematics.position();
+        self.interpolated_position = self.previous_position + (current_position - self.previous_position) * alpha;
+    }
 }
 
-struct LightKinematics {
-    scene_center: Vector3<f32>,
-    radial_speed: f32,
-    center_of_oscillation: Vector3<f32>,
-    radius_of_oscillation: f32,
+/// Construct the lights of the light rig `rig`.
+fn create_lights(rig: &LightRig) -> Vec<Light> {
+    rig.lights.iter()
+        .map(|spec| Light::new(spec.light_source(), spec.kinematics.build(), spec.casts_shadows))
+        .collect()
+}
+
+/// Check that the shaders can illuminate the scene with every light in the rig `rig`.
+fn check_rig(rig: &LightRig) -> Result<(), String> {
+    let is_area = |spec: &&LightSpec| match spec.emitter { Emitter::Area { .. } => true, _ => false };
+    let num_area_lights = rig.lights.iter().filter(is_area).count();
+    let num_lights = rig.lights.len() - num_area_lights;
+    let shadow_casters = rig.lights.iter().filter(|spec| spec.casts_shadows);
+    let num_point_shadow_maps = shadow_casters.clone()
+        .filter(|spec| match spec.emitter { Emitter::Point { .. } => true, _ => false })
+        .count();
+    let num_shadow_maps = shadow_casters
+        .filter(|spec| match spec.emitter { Emitter::Directional { .. } | Emitter::Spot { .. } => true, _ => false })
+        .count();
+    if num_lights > MAX_LIGHTS {
+        return Err(format!("The rig has {} lights, but the shaders support {}.", num_lights, MAX_LIGHTS));
+    }
+    if num_area_lights > MAX_AREA_LIGHTS {
+        return Err(format!(
+            "The rig has {} area lights, but the shaders support {}.", num_area_lights, MAX_AREA_LIGHTS
+        ));
+    }
+    if num_shadow_maps > MAX_SHADOW_MAPS {
+        return Err(format!(
+            "The rig has {} directional and spot lights casting shadows, but the shaders support {}.", 
+            num_shadow_maps, MAX_SHADOW_MAPS
+        ));
+    }
+    if num_point_shadow_maps > MAX_POINT_SHADOW_MAPS {
+        return Err(format!(
+            "The rig has {} point lights casting shadows, but the shaders support {}.", 
+            num_point_shadow_maps, MAX_POINT_SHADOW_MAPS
+        ));
+    }
+
+    Ok(())
+}
+
+/// The path of the file the light rig with the name `name` is saved to.
+fn rig_path(name: &str) -> String {
+    format!("{}/{}.rig", RIGS_DIRECTORY, name)
+}
+
+/// The light rigs the user can switch between while the demo runs.
+struct RigSettings {
+    rigs: Vec<LightRig>,
+    /// The index of the rig illuminating the scene.
+    current: usize,
+    /// Whether the user switched rigs since the lights were last built.
+    changed: bool,
+}
+
+impl RigSettings {
+    /// Collect the preset rigs. A rig saved in the rigs directory replaces the preset 
+    /// with the same name, so the presets can be tuned without recompiling the demo.
+    fn new(scene_center_world: Vector3<f32>) -> RigSettings {
+        let mut rigs = rig::presets(scene_center_world);
+        for rig in rigs.iter_mut() {
+            let path = rig_path(&rig.name);
+            if !Path::new(&path).exists() {
+                continue;
+            }
+            let loaded = LightRig::load(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|loaded| check_rig(&loaded).map(|_| loaded));
+            match loaded {
+                Ok(loaded) => {
+                    info!("Loaded the light rig {} from {}", loaded.name, path);
+                    *rig = loaded;
+                }
+                Err(e) => info!("Could not load the light rig from {}. Got error: {}", path, e),
+            }
+        }
+
+        RigSettings {
+            rigs: rigs,
+            current: 0,
+            changed: false,
+        }
+    }
+
+    fn current(&self) -> &LightRig {
+        &self.rigs[self.current]
+    }
+}
+
+/// A light that renders a shadow map, together with the projection it renders with.
+struct ShadowCaster {
+    light_index: usize,
+    projection: ShadowProjection,
+}
+
+/// Collect the lights that render shadow maps. Each caster renders into the shadow map
+/// layer with the same index as the caster.
+fn create_shadow_casters(lights: &[Light], scene_center_world: &Vector3<f32>) -> Vec<ShadowCaster> {
+    let mut casters = vec![];
+    for (i, light) in lights.iter().enumerate() {
+        if !light.casts_shadows {
+            continue;
+        }
+        let projection = match light.light {
+            LightSource::Directional(ref directional_light) => {
+                shadow::directional_shadow_projection(directional_light, scene_center_world, SCENE_RADIUS)
+            }
+            LightSource::Spot(ref spot_light) => {
+                let position = light.position();
+                let far = (position - scene_center_world).magnitude() + SCENE_RADIUS;
+                shadow::spot_shadow_projection(spot_light, &position, far)
+            }
+            _ => continue,
+        };
+        casters.push(ShadowCaster { light_index: i, projection: projection });
+    }
+    debug_assert!(casters.len() <= MAX_SHADOW_MAPS);
+
+    casters
+}
+
+/// A point light that renders a cube shadow map, together with the transformations from
+/// world space to the clip space of each face of the cube.
+struct PointShadowCaster {
+    light_index: usize,
     position: Vector3<f32>,
-    radial_unit_velocity: f32,
-    orbital_axis: Vector3<f32>,
-    orbital_speed: f32,
-}
-
-impl LightKinematics {
-    pub fn new(
-        scene_center: Vector3<f32>, 
-        radial_speed: f32, 
-        center_of_oscillation: Vector3<f32>, 
-        radius_of_oscillation: f32,
-        orbital_axis: Vector3<f32>,
-        orbital_speed: f32) -> LightKinematics {
-        
-        let radial_unit_velocity = 1.0;
-        let position = center_of_oscillation;
-        LightKinematics {
-            scene_center: scene_center,
-            radial_speed: radial_speed,
-            center_of_oscillation: center_of_oscillation,
-            radius_of_oscillation: radius_of_oscillation,
-            position: position,
-            radial_unit_velocity: radial_unit_velocity,
-            orbital_axis: orbital_axis.normalize(),
-            orbital_speed: orbital_speed,
+    light_space_mats: [Matrix4<f32>; CUBE_FACES],
+}
+
+/// Collect the point lights that render cube shadow maps. Each caster renders into the 
+/// cube shadow map with the same index as the caster.
+fn create_point_shadow_casters(lights: &[Light]) -> Vec<PointShadowCaster> {
+    let mut casters = vec![];
+    for (i, light) in lights.iter().enumerate() {
+        if !light.casts_shadows {
+            continue;
+        }
+        if let LightSource::Point(_) = light.light {
+            let position = light.position();
+            let light_space_mats = shadow::point_light_space_mats(&position, POINT_SHADOW_FAR_PLANE);
+            casters.push(PointShadowCaster { 
+                light_index: i, 
+                position: position, 
+                light_space_mats: light_space_mats,
+            });
         }
     }
+    debug_assert!(casters.len() <= MAX_POINT_SHADOW_MAPS);
 
-    #[inline]
-    fn position(&self) -> Vector3<f32> {
-        self.position
-    }
-
-    fn update(&mut self, elapsed_seconds: f32) {
-        self.radial_unit_velocity = if self.radial_unit_velocity < 0.0 { -1.0 } else { 1.0 };
-        let radius_center_of_oscillation = (self.center_of_oscillation - self.scene_center).magnitude();
-        let radial_vector: Vector3<f32> = (self.position - self.scene_center).normalize();
-        let radius_perihelion = radius_center_of_oscillation - self.radius_of_oscillation;
-        let radius_aphelion = radius_center_of_oscillation + self.radius_of_oscillation;
-        let mut distance_from_scene_center = (self.position - self.scene_center).magnitude();
-        distance_from_scene_center = distance_from_scene_center + (self.radial_speed * elapsed_seconds) * self.radial_unit_velocity;
-        if distance_from_scene_center < radius_perihelion {
-            distance_from_scene_center = radius_perihelion;
-            self.radial_unit_velocity = 1.0;
-        } else if distance_from_scene_center > radius_aphelion {
-            distance_from_scene_center = radius_aphelion;
-            self.radial_unit_velocity = -1.0;
+    casters
+}
+
+/// The shadow settings the user can change while the demo runs.
+struct ShadowSettings {
+    /// The depth bias of the shadow maps of directional and spot lights.
+    bias: DepthBias,
+    /// The depth bias of the cube shadow maps of point lights.
+    point_bias: DepthBias,
+    /// The technique for filtering the shadow maps.
+    filter: ShadowFilter,
+    /// The index of the point light cube shadow map whose faces are displayed on top
+    /// of the scene, if any.
+    debug_cube: Option<usize>,
+}
+
+impl ShadowSettings {
+    fn new() -> ShadowSettings {
+        ShadowSettings {
+            bias: DepthBias::new(0.0005, 0.005),
+            point_bias: DepthBias::new(0.02, 0.1),
+            filter: ShadowFilter::Pcss,
+            debug_cube: None,
         }
-    
-        let q = Quaternion::from_axis_angle(
-            &Unit::from_value(self.orbital_axis), Radians(self.orbital_speed * elapsed_seconds)
-        );
-        let rot_mat = Matrix4::from(q);
-        let new_position = rot_mat * (distance_from_scene_center * radial_vector).extend(1.0);
+    }
+}
+
+/// How the environment lights the scene.
+struct EnvironmentSettings {
+    /// How the surroundings light the scene in place of the ambient terms of the lights.
+    ambient: AmbientLighting,
+    /// The luminance in nits of an environment texel with a value of one.
+    luminance: f32,
+    /// The angle in degrees the environment is turned by around the vertical axis.
+    rotation_degrees: f32,
+}
+
+impl EnvironmentSettings {
+    fn new(ambient: AmbientLighting) -> EnvironmentSettings {
+        EnvironmentSettings {
+            ambient: ambient,
+            luminance: ENVIRONMENT_LUMINANCE,
+            rotation_degrees: 0.0,
+        }
+    }
+
+    /// Brighten or darken the environment by `stops` stops.
+    fn expose(&mut self, stops: f32) {
+        self.luminance *= 2_f32.powf(stops);
+    }
+
+    /// Turn the environment by `degrees` degrees around the vertical axis.
+    fn rotate(&mut self, degrees: f32) {
+        self.rotation_degrees = (self.rotation_degrees + degrees).rem_euclid(360.0);
+    }
+
+    /// The rotation from world space directions to directions in the environment, in 
+    /// column major order.
+    fn rotation_mat(&self) -> [[f32; 3]; 3] {
+        let (sin_angle, cos_angle) = self.rotation_degrees.to_radians().sin_cos();
+        [
+            [cos_angle, 0.0, -sin_angle],
+            [0.0, 1.0, 0.0],
+            [sin_angle, 0.0, cos_angle],
+        ]
+    }
+}
+
+/// The shape of the objects in the scene when no meshes are loaded from files. Every
+/// shape fits in the unit cube around the origin.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PreviewShape {
+    Box,
+    SubdividedBox,
+    UvSphere,
+    Icosphere,
+    Torus,
+    Cylinder,
+    Cone,
+    Teapot,
+}
+
+impl PreviewShape {
+    /// The index of the shape's mesh in the list of preview meshes.
+    fn index(&self) -> usize {
+        match *self {
+            PreviewShape::Box => 0,
+            PreviewShape::SubdividedBox => 1,
+            PreviewShape::UvSphere => 2,
+            PreviewShape::Icosphere => 3,
+            PreviewShape::Torus => 4,
+            PreviewShape::Cylinder => 5,
+            PreviewShape::Cone => 6,
+            PreviewShape::Teapot => 7,
+        }
+    }
+
+    /// The next shape to cycle to.
+    fn next(&self) -> PreviewShape {
+        match *self {
+            PreviewShape::Box => PreviewShape::SubdividedBox,
+            PreviewShape::SubdividedBox => PreviewShape::UvSphere,
+            PreviewShape::UvSphere => PreviewShape::Icosphere,
+            PreviewShape::Icosphere => PreviewShape::Torus,
+            PreviewShape::Torus => PreviewShape::Cylinder,
+            PreviewShape::Cylinder => PreviewShape::Cone,
+            PreviewShape::Cone => PreviewShape::Teapot,
+            PreviewShape::Teapot => PreviewShape::Box,
+        }
+    }
+
+    fn create_mesh(&self) -> ObjMesh {
+        match *self {
+            PreviewShape::Box => create_box_mesh(),
+            PreviewShape::SubdividedBox => {
+                // Without creases, the box rounds off into a smooth surface.
+                subdivision::subdivide(&create_box_mesh(), SubdivisionScheme::CatmullClark, SUBDIVIDED_BOX_LEVELS, 180.0)
+            }
+            PreviewShape::UvSphere => primitives::uv_sphere(0.5, 64, 32),
+            PreviewShape::Icosphere => primitives::icosphere(0.5, 4),
+            PreviewShape::Torus => primitives::torus(0.35, 0.15, 64, 32),
+            PreviewShape::Cylinder => primitives::cylinder(0.5, 1.0, 64, 1),
+            PreviewShape::Cone => primitives::cone(0.5, 1.0, 64, 1),
+            PreviewShape::Teapot => mesh::fit_to_unit_cube(&teapot::teapot(TEAPOT_LEVEL_OF_DETAIL)),
+        }
+    }
+}
+
+const PREVIEW_SHAPES: [PreviewShape; 8] = [
+    PreviewShape::Box,
+    PreviewShape::SubdividedBox,
+    PreviewShape::UvSphere,
+    PreviewShape::Icosphere,
+    PreviewShape::Torus,
+    PreviewShape::Cylinder,
+    PreviewShape::Cone,
+    PreviewShape::Teapot,
+];
+
+/// A preview shape on the GPU.
+struct PreviewMesh {
+    vao: GLuint,
+    index_count: usize,
+    /// The lowest height of any vertex of the shape.
+    bottom: f32,
+}
+
+/// Where the environment surrounding the scene comes from.
+enum EnvironmentSource {
+    /// An equirectangular Radiance `.hdr` image.
+    Equirectangular(String),
+    /// A directory holding a Radiance `.hdr` image for each face of a cube map, named
+    /// after `ENVIRONMENT_FACE_FILES`.
+    CubeFaces(String),
+    /// The built in gradient sky.
+    GradientSky,
+}
+
+/// Load the environment surrounding the scene, and filter it for image based lighting.
+/// This also projects the environment onto spherical harmonics for cheap diffuse lighting.
+/// The demo prints the error and exits if it cannot load the environment files.
+fn load_environment(source: &EnvironmentSource) -> (CubeMap, Environment, SphericalHarmonics) {
+    let skybox = match source {
+        EnvironmentSource::Equirectangular(path) => {
+            let image = match HdrImage::load(path) {
+                Ok(image) => image,
+                Err(e) => {
+                    eprintln!("Could not load the environment {}. Got error: {}", path, e);
+                    process::exit(1);
+                }
+            };
+            info!("Loaded the {} x {} environment {}", image.width, image.height, path);
+            CubeMap::from_equirectangular(&image, SKYBOX_SIZE)
+        }
+        EnvironmentSource::CubeFaces(directory) => {
+            let images: Vec<HdrImage> = ENVIRONMENT_FACE_FILES.iter().map(|file| {
+                let path = Path::new(directory).join(file);
+                match HdrImage::load(&path) {
+                    Ok(image) => image,
+                    Err(e) => {
+                        eprintln!("Could not load the environment face {}. Got error: {}", path.display(), e);
+                        process::exit(1);
+                    }
+                }
+            }).collect();
+            let skybox = match CubeMap::from_faces(&images, SKYBOX_SIZE) {
+                Ok(skybox) => skybox,
+                Err(e) => {
+                    eprintln!("Could not assemble the environment in {}. Got error: {}", directory, e);
+                    process::exit(1);
+                }
+            };
+            info!("Loaded the {} x {} environment faces in {}", images[0].width, images[0].height, directory);
+            skybox
+        }
+        EnvironmentSource::GradientSky => CubeMap::from_fn(SKYBOX_SIZE, environment::gradient_sky),
+    };
+    let environment = Environment::new(&skybox);
+    let radiance = match source {
+        // The sky model is analytic, so project it directly.
+        EnvironmentSource::GradientSky => SphericalHarmonics::from_fn(environment::gradient_sky),
+        _ => SphericalHarmonics::from_cube_map(&environment.specular[0]),
+    };
+    let irradiance = radiance.irradiance();
+    info!("Filtered the environment for image based lighting");
+    info!("Environment irradiance at the zenith: {:?}", irradiance.evaluate([0.0, 1.0, 0.0]));
+
+    (skybox, environment, irradiance)
+}
+
+/// The timeline animating the scene, together with whether it drives the scene.
+struct TimelineSettings {
+    timeline: Timeline,
+    /// Whether the timeline animates the scene. Otherwise the user flies the camera and
+    /// the lights follow their motion models.
+    enabled: bool,
+}
 
-        self.position = new_position.contract();
+impl TimelineSettings {
+    /// Load the timeline from the file at `path`. The demo starts with an empty timeline
+    /// if the file is missing or invalid.
+    fn new(path: &str) -> TimelineSettings {
+        let timeline = match Timeline::load(path) {
+            Ok(timeline) => {
+                info!("Loaded the timeline from {}", path);
+                timeline
+            }
+            Err(e) => {
+                info!("Could not load the timeline from {}. Got error: {}", path, e);
+                Timeline::new(0.0)
+            }
+        };
+
+        TimelineSettings {
+            timeline: timeline,
+            enabled: false,
+        }
+    }
+
+    /// The pose of the camera at the playhead, if the timeline animates the camera.
+    fn camera_frame(&self, camera: &CameraFrame) -> Option<CameraFrame> {
+        if self.enabled {
+            self.timeline.sample_camera(camera)
+        } else {
+            None
+        }
     }
 
-    fn model_mat(&self) -> Matrix4<f32> {
-        Matrix4::from_affine_translation(&self.position)
+    /// Place the lights and set their colors and intensities to the values of their
+    /// tracks at the playhead. Lights without tracks keep their own values.
+    fn apply_to_lights(&self, lights: &mut [Light]) {
+        for (i, light) in lights.iter_mut().enumerate() {
+            if !self.enabled {
+                light.position_override = None;
+                light.light.set_emission(light.color, light.intensity);
+                continue;
+            }
+            let color = self.timeline.sample_vector(TrackTarget::LightColor(i)).unwrap_or(light.color);
+            let intensity = self.timeline.sample_scalar(TrackTarget::LightIntensity(i)).unwrap_or(light.intensity);
+            light.position_override = self.timeline.sample_vector(TrackTarget::LightPosition(i));
+            light.light.set_emission(color, intensity);
+        }
+    }
+
+    /// The material of the object with the index `index` at the playhead.
+    fn material(&self, index: usize, material: &Material<f32>) -> Material<f32> {
+        if !self.enabled {
+            return *material;
+        }
+
+        let timeline = &self.timeline;
+        let mut material = *material;
+        if let Some(ambient) = timeline.sample_vector(TrackTarget::MaterialAmbient(index)) {
+            material.ambient = ambient;
+        }
+        if let Some(diffuse) = timeline.sample_vector(TrackTarget::MaterialDiffuse(index)) {
+            material.diffuse = diffuse;
+        }
+        if let Some(specular) = timeline.sample_vector(TrackTarget::MaterialSpecular(index)) {
+            material.specular = specular;
+        }
+        if let Some(specular_exponent) = timeline.sample_scalar(TrackTarget::MaterialSpecularExponent(index)) {
+            material.specular_exponent = specular_exponent;
+        }
+
+        material
+    }
+}
+
+/// Compute the model matrix for the marker showing where a light is. Area lights
+/// are drawn as thin panels with the size and orientation of the light.
+fn light_model_mat(light: &Light) -> Matrix4<f32> {
+    match light.light {
+        LightSource::Area(ref area_light) => {
+            let (right, up) = area_light.basis();
+            let (half_width, half_height) = area_light.half_extents();
+            let x_axis = right * (2.0 * half_width);
+            let y_axis = up * (2.0 * half_height);
+            let z_axis = area_light.direction * 0.02;
+            let position = light.position();
+
+            Matrix4::new(
+                x_axis[0],   x_axis[1],   x_axis[2],   0.0,
+                y_axis[0],   y_axis[1],   y_axis[2],   0.0,
+                z_axis[0],   z_axis[1],   z_axis[2],   0.0,
+                position[0], position[1], position[2], 1.0,
+            )
+        }
+        _ => Matrix4::from_affine_translation(&light.position()) * Matrix4::from_affine_scale(0.2),
     }
 }
 
@@ -262,7 +834,9 @@ fn send_to_gpu_uniforms_mesh(shader: GLuint, model_mat: &Matrix4<f32>) {
     }
 }
 
-fn send_to_gpu_uniforms_camera(shader: GLuint, camera: &PerspFovCamera<f32>) {
+/// Send the camera's projection and the view matrix `view_mat` to the GPU. The view matrix
+/// comes from the timeline instead of the camera while the timeline animates the camera.
+fn send_to_gpu_uniforms_camera(shader: GLuint, camera: &PerspFovCamera<f32>, view_mat: &Matrix4<f32>) {
     let camera_proj_mat_loc = unsafe {
         gl::GetUniformLocation(shader, backend::gl_str("camera.proj_mat").as_ptr())
     };
@@ -275,7 +849,7 @@ fn send_to_gpu_uniforms_camera(shader: GLuint, camera: &PerspFovCamera<f32>) {
     unsafe {
         gl::UseProgram(shader);
         gl::UniformMatrix4fv(camera_proj_mat_loc, 1, gl::FALSE, camera.projection().as_ptr());
-        gl::UniformMatrix4fv(camera_view_mat_loc, 1, gl::FALSE, camera.view_matrix().as_ptr());
+        gl::UniformMatrix4fv(camera_view_mat_loc, 1, gl::FALSE, view_mat.as_ptr());
     }
 }
 
@@ -285,80 +859,214 @@ fn send_to_gpu_uniforms_camera(shader: GLuint, camera: &PerspFovCamera<f32>) {
 /// and each struct is a struct of uniforms. Consequently, if every element of an array of struct uniforms
 /// is not used in the shader, OpenGL will optimize those uniform locations out at runtime. This
 /// will cause OpenGL to return a `GL_INVALID_VALUE` on a call to `glGetUniformLocation`.
-fn send_to_gpu_uniforms_light(shader: GLuint, lights: &[Light; 3]) {
-    let light_position_world_loc = unsafe {
-        gl::GetUniformLocation(shader, backend::gl_str("lights[0].position_world").as_ptr())
-    };
-    debug_assert!(light_position_world_loc > -1);
-    let light_ambient_loc = unsafe {
-        gl::GetUniformLocation(shader, backend::gl_str("lights[0].ambient").as_ptr())
-    };
-    debug_assert!(light_ambient_loc > -1);
-    let light_diffuse_loc = unsafe {
-        gl::GetUniformLocation(shader, backend::gl_str("lights[0].diffuse").as_ptr())
-    };
-    debug_assert!(light_diffuse_loc > -1);
-    let light_specular_loc = unsafe { 
-        gl::GetUniformLocation(shader, backend::gl_str("lights[0].specular").as_ptr())
+/// The shader loops over the first `num_lights` elements of the array, so every member of
+/// every light it reads must be sent here.
+fn send_to_gpu_uniforms_light(
+    shader: GLuint, 
+    lights: &[Light], 
+    shadow_casters: &[ShadowCaster], 
+    point_shadow_casters: &[PointShadowCaster]) {
+
+    let area_lights: Vec<&Light> = lights.iter()
+        .filter(|light| match light.light { LightSource::Area(_) => true, _ => false })
+        .collect();
+    let lights: Vec<(usize, &Light)> = lights.iter()
+        .enumerate()
+        .filter(|(_, light)| match light.light { LightSource::Area(_) => false, _ => true })
+        .collect();
+    debug_assert!(lights.len() <= MAX_LIGHTS);
+    let num_lights_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("num_lights").as_ptr())
     };
-    debug_assert!(light_specular_loc > -1);
+    debug_assert!(num_lights_loc > -1);
 
     unsafe {
         gl::UseProgram(shader);
-        gl::Uniform3fv(light_position_world_loc, 1, lights[0].kinematics.position().as_ptr());
-        gl::Uniform3fv(light_ambient_loc, 1, lights[0].light.ambient.as_ptr());
-        gl::Uniform3fv(light_diffuse_loc, 1, lights[0].light.diffuse.as_ptr());
-        gl::Uniform3fv(light_specular_loc, 1, lights[0].light.specular.as_ptr());
+        gl::Uniform1i(num_lights_loc, lights.len() as GLint);
     }
 
-    let light_position_world_loc = unsafe {
-        gl::GetUniformLocation(shader, backend::gl_str("lights[1].position_world").as_ptr())
-    };
-    debug_assert!(light_position_world_loc > -1);
-    let light_ambient_loc = unsafe {
-        gl::GetUniformLocation(shader, backend::gl_str("lights[1].ambient").as_ptr())
-    };
-    debug_assert!(light_ambient_loc > -1);
-    let light_diffuse_loc = unsafe {
-        gl::GetUniformLocation(shader, backend::gl_str("lights[1].diffuse").as_ptr())
-    };
-    debug_assert!(light_diffuse_loc > -1);
-    let light_specular_loc = unsafe { 
-        gl::GetUniformLocation(shader, backend::gl_str("lights[1].specular").as_ptr())
-    };
-    debug_assert!(light_specular_loc > -1);
+    for (i, &(light_index, light)) in lights.iter().enumerate() {
+        let light_type_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("lights[{}].light_type", i)).as_ptr())
+        };
+        debug_assert!(light_type_loc > -1);
+        let light_position_world_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("lights[{}].position_world", i)).as_ptr())
+        };
+        debug_assert!(light_position_world_loc > -1);
+        let light_direction_world_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("lights[{}].direction_world", i)).as_ptr())
+        };
+        debug_assert!(light_direction_world_loc > -1);
+        let light_cos_inner_cutoff_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("lights[{}].cos_inner_cutoff", i)).as_ptr())
+        };
+        debug_assert!(light_cos_inner_cutoff_loc > -1);
+        let light_cos_outer_cutoff_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("lights[{}].cos_outer_cutoff", i)).as_ptr())
+        };
+        debug_assert!(light_cos_outer_cutoff_loc > -1);
+        let light_falloff_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("lights[{}].falloff", i)).as_ptr())
+        };
+        debug_assert!(light_falloff_loc > -1);
+        let light_attenuation_model_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("lights[{}].attenuation_model", i)).as_ptr())
+        };
+        debug_assert!(light_attenuation_model_loc > -1);
+        let light_attenuation_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("lights[{}].attenuation", i)).as_ptr())
+        };
+        debug_assert!(light_attenuation_loc > -1);
+        let light_shadow_map_index_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("lights[{}].shadow_map_index", i)).as_ptr())
+        };
+        debug_assert!(light_shadow_map_index_loc > -1);
+        let light_radius_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("lights[{}].radius", i)).as_ptr())
+        };
+        debug_assert!(light_radius_loc > -1);
+        let light_ambient_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("lights[{}].ambient", i)).as_ptr())
+        };
+        debug_assert!(light_ambient_loc > -1);
+        let light_diffuse_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("lights[{}].diffuse", i)).as_ptr())
+        };
+        debug_assert!(light_diffuse_loc > -1);
+        let light_specular_loc = unsafe { 
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("lights[{}].specular", i)).as_ptr())
+        };
+        debug_assert!(light_specular_loc > -1);
+
+        let (cos_inner_cutoff, cos_outer_cutoff) = light.light.cos_cutoffs();
+        let attenuation = light.light.attenuation();
+        let shadow_map_index = match light.light {
+            LightSource::Point(_) => point_shadow_casters.iter()
+                .position(|caster| caster.light_index == light_index),
+            _ => shadow_casters.iter()
+                .position(|caster| caster.light_index == light_index),
+        };
+        let shadow_map_index = shadow_map_index.map_or(-1, |index| index as GLint);
+        unsafe {
+            gl::UseProgram(shader);
+            gl::Uniform1i(light_type_loc, light.light.type_id());
+            gl::Uniform3fv(light_position_world_loc, 1, light.position().as_ptr());
+            gl::Uniform3fv(light_direction_world_loc, 1, light.light.direction().as_ptr());
+            gl::Uniform1f(light_cos_inner_cutoff_loc, cos_inner_cutoff);
+            gl::Uniform1f(light_cos_outer_cutoff_loc, cos_outer_cutoff);
+            gl::Uniform1f(light_falloff_loc, light.light.falloff());
+            gl::Uniform1i(light_attenuation_model_loc, attenuation.type_id());
+            gl::Uniform3fv(light_attenuation_loc, 1, attenuation.coefficients().as_ptr());
+            gl::Uniform1i(light_shadow_map_index_loc, shadow_map_index);
+            gl::Uniform1f(light_radius_loc, light.light.radius());
+            gl::Uniform3fv(light_ambient_loc, 1, light.light.ambient().as_ptr());
+            gl::Uniform3fv(light_diffuse_loc, 1, light.light.diffuse().as_ptr());
+            gl::Uniform3fv(light_specular_loc, 1, light.light.specular().as_ptr());
+        }
+    }
 
-    unsafe {
-        gl::UseProgram(shader);
-        gl::Uniform3fv(light_position_world_loc, 1, lights[1].kinematics.position().as_ptr());
-        gl::Uniform3fv(light_ambient_loc, 1, lights[1].light.ambient.as_ptr());
-        gl::Uniform3fv(light_diffuse_loc, 1, lights[1].light.diffuse.as_ptr());
-        gl::Uniform3fv(light_specular_loc, 1, lights[1].light.specular.as_ptr());
+    for (i, caster) in shadow_casters.iter().enumerate() {
+        let light_space_mat_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("shadow_casters[{}].light_space_mat", i)).as_ptr())
+        };
+        debug_assert!(light_space_mat_loc > -1);
+        let perspective_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("shadow_casters[{}].perspective", i)).as_ptr())
+        };
+        debug_assert!(perspective_loc > -1);
+        let near_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("shadow_casters[{}].near", i)).as_ptr())
+        };
+        debug_assert!(near_loc > -1);
+        let far_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("shadow_casters[{}].far", i)).as_ptr())
+        };
+        debug_assert!(far_loc > -1);
+        let light_size_uv_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("shadow_casters[{}].light_size_uv", i)).as_ptr())
+        };
+        debug_assert!(light_size_uv_loc > -1);
+
+        let projection = &caster.projection;
+        unsafe {
+            gl::UseProgram(shader);
+            gl::UniformMatrix4fv(light_space_mat_loc, 1, gl::FALSE, projection.light_space_mat.as_ptr());
+            gl::Uniform1i(perspective_loc, projection.perspective as GLint);
+            gl::Uniform1f(near_loc, projection.near);
+            gl::Uniform1f(far_loc, projection.far);
+            gl::Uniform1f(light_size_uv_loc, projection.light_size_uv);
+        }
     }
 
-    let light_position_world_loc = unsafe {
-        gl::GetUniformLocation(shader, backend::gl_str("lights[2].position_world").as_ptr())
-    };
-    debug_assert!(light_position_world_loc > -1);
-    let light_ambient_loc = unsafe {
-        gl::GetUniformLocation(shader, backend::gl_str("lights[2].ambient").as_ptr())
-    };
-    debug_assert!(light_ambient_loc > -1);
-    let light_diffuse_loc = unsafe {
-        gl::GetUniformLocation(shader, backend::gl_str("lights[2].diffuse").as_ptr())
-    };
-    debug_assert!(light_diffuse_loc > -1);
-    let light_specular_loc = unsafe { 
-        gl::GetUniformLocation(shader, backend::gl_str("lights[2].specular").as_ptr())
+    send_to_gpu_uniforms_area_light(shader, &area_lights);
+}
+
+/// Send the uniforms for the area lights to the GPU for the mesh. The same caveats about
+/// arrays of struct uniforms apply here as in `send_to_gpu_uniforms_light`.
+fn send_to_gpu_uniforms_area_light(shader: GLuint, area_lights: &[&Light]) {
+    debug_assert!(area_lights.len() <= MAX_AREA_LIGHTS);
+    let num_area_lights_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("num_area_lights").as_ptr())
     };
-    debug_assert!(light_specular_loc > -1);
+    debug_assert!(num_area_lights_loc > -1);
 
     unsafe {
         gl::UseProgram(shader);
-        gl::Uniform3fv(light_position_world_loc, 1, lights[2].kinematics.position().as_ptr());
-        gl::Uniform3fv(light_ambient_loc, 1, lights[2].light.ambient.as_ptr());
-        gl::Uniform3fv(light_diffuse_loc, 1, lights[2].light.diffuse.as_ptr());
-        gl::Uniform3fv(light_specular_loc, 1, lights[2].light.specular.as_ptr());
+        gl::Uniform1i(num_area_lights_loc, area_lights.len() as GLint);
+    }
+
+    for (i, light) in area_lights.iter().enumerate() {
+        let area_light = match light.light {
+            LightSource::Area(ref area_light) => area_light,
+            _ => continue,
+        };
+        let shape_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("area_lights[{}].shape", i)).as_ptr())
+        };
+        debug_assert!(shape_loc > -1);
+        let position_world_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("area_lights[{}].position_world", i)).as_ptr())
+        };
+        debug_assert!(position_world_loc > -1);
+        let right_world_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("area_lights[{}].right_world", i)).as_ptr())
+        };
+        debug_assert!(right_world_loc > -1);
+        let up_world_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("area_lights[{}].up_world", i)).as_ptr())
+        };
+        debug_assert!(up_world_loc > -1);
+        let half_extents_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("area_lights[{}].half_extents", i)).as_ptr())
+        };
+        debug_assert!(half_extents_loc > -1);
+        let ambient_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("area_lights[{}].ambient", i)).as_ptr())
+        };
+        debug_assert!(ambient_loc > -1);
+        let diffuse_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("area_lights[{}].diffuse", i)).as_ptr())
+        };
+        debug_assert!(diffuse_loc > -1);
+        let specular_loc = unsafe {
+            gl::GetUniformLocation(shader, backend::gl_str(&format!("area_lights[{}].specular", i)).as_ptr())
+        };
+        debug_assert!(specular_loc > -1);
+
+        let (right, up) = area_light.basis();
+        let (half_width, half_height) = area_light.half_extents();
+        unsafe {
+            gl::UseProgram(shader);
+            gl::Uniform1i(shape_loc, area_light.shape_id());
+            gl::Uniform3fv(position_world_loc, 1, light.position().as_ptr());
+            gl::Uniform3fv(right_world_loc, 1, right.as_ptr());
+            gl::Uniform3fv(up_world_loc, 1, up.as_ptr());
+            gl::Uniform2f(half_extents_loc, half_width, half_height);
+            gl::Uniform3fv(ambient_loc, 1, area_light.ambient.as_ptr());
+            gl::Uniform3fv(diffuse_loc, 1, area_light.diffuse.as_ptr());
+            gl::Uniform3fv(specular_loc, 1, area_light.specular.as_ptr());
+        }
     }
 }
 
@@ -379,6 +1087,29 @@ fn send_to_gpu_uniforms_material(shader: GLuint, material: &Material<f32>) {
         gl::GetUniformLocation(shader, backend::gl_str("material.specular_exponent").as_ptr())
     };
     debug_assert!(material_specular_exponent_loc > -1);
+    let material_reflectivity_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("material.reflectivity").as_ptr())
+    };
+    debug_assert!(material_reflectivity_loc > -1);
+    let material_refractive_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("material.refractive").as_ptr())
+    };
+    debug_assert!(material_refractive_loc > -1);
+    let material_index_of_refraction_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("material.index_of_refraction").as_ptr())
+    };
+    debug_assert!(material_index_of_refraction_loc > -1);
+    let material_transmittance_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("material.transmittance").as_ptr())
+    };
+    debug_assert!(material_transmittance_loc > -1);
+
+    let reflectivity = material.reflectivity();
+    // Opaque materials pass no light through, and bend nothing.
+    let (index_of_refraction, transmittance) = match material.refraction {
+        Some(refraction) => (refraction.indices_of_refraction(), refraction.transmittance),
+        None => (Vector3::new(1.0, 1.0, 1.0), Vector3::zero()),
+    };
 
     unsafe {
         gl::UseProgram(shader);
@@ -386,21 +1117,48 @@ fn send_to_gpu_uniforms_material(shader: GLuint, material: &Material<f32>) {
         gl::Uniform3fv(material_diffuse_loc, 1, material.diffuse.as_ptr());
         gl::Uniform3fv(material_specular_loc, 1, material.specular.as_ptr());
         gl::Uniform1f(material_specular_exponent_loc, material.specular_exponent);
+        gl::Uniform3fv(material_reflectivity_loc, 1, reflectivity.as_ptr());
+        gl::Uniform1i(material_refractive_loc, material.refraction.is_some() as GLint);
+        gl::Uniform3fv(material_index_of_refraction_loc, 1, index_of_refraction.as_ptr());
+        gl::Uniform3fv(material_transmittance_loc, 1, transmittance.as_ptr());
     }
 }
 
-fn send_to_gpu_mesh(shader: GLuint, mesh: &ObjMesh) -> (GLuint, GLuint, GLuint) {
+/// An object in the scene drawn with the mesh shader.
+struct SceneObject {
+    vao: GLuint,
+    /// The number of indices of the object's mesh.
+    index_count: usize,
+    model_mat: Matrix4<f32>,
+    material: Material<f32>,
+    /// The transparent version of the material, if it has one.
+    transparent_material: Option<Material<f32>>,
+}
+
+/// Send an indexed mesh to the GPU. The element buffer is bound to the vertex array, so 
+/// the mesh draws with `glDrawElements` using 32 bit indices. Shaders that do not read 
+/// normals get no normal attribute. The triangles and vertices are reordered first so the 
+/// GPU transforms and fetches fewer vertices and shades fewer hidden fragments.
+fn send_to_gpu_mesh(shader: GLuint, mesh: &IndexedMesh) -> (GLuint, GLuint, GLuint, GLuint) {
+    let unoptimized_acmr = optimize::average_cache_miss_ratio(&mesh.indices, mesh.vertex_count(), optimize::VERTEX_CACHE_SIZE);
+    let mesh = &optimize::optimize(mesh);
+    let optimized_acmr = optimize::average_cache_miss_ratio(&mesh.indices, mesh.vertex_count(), optimize::VERTEX_CACHE_SIZE);
+    info!(
+        "Reordered a mesh with {} triangles for the vertex cache, from {:.3} to {:.3} vertex cache misses per triangle",
+        mesh.index_count() / 3, unoptimized_acmr, optimized_acmr
+    );
+
     let v_pos_loc = unsafe {
         gl::GetAttribLocation(shader, backend::gl_str("v_pos").as_ptr())
     };
     debug_assert!(v_pos_loc > -1);
     let v_pos_loc = v_pos_loc as u32;
 
+    // The normals are only missing when the shader compiles them out.
     let v_norm_loc = unsafe {
         gl::GetAttribLocation(shader, backend::gl_str("v_norm").as_ptr())
     };
-    debug_assert!(v_norm_loc > -1);
-    let v_norm_loc = v_norm_loc as u32;
+    debug_assert_eq!(mesh.normals.len(), mesh.points.len());
 
     let mut v_pos_vbo = 0;
     unsafe {
@@ -408,7 +1166,7 @@ fn send_to_gpu_mesh(shader: GLuint, mesh: &ObjMesh) -> (GLuint, GLuint, GLuint)
         gl::BindBuffer(gl::ARRAY_BUFFER, v_pos_vbo);
         gl::BufferData(
             gl::ARRAY_BUFFER,
-            (3 * mem::size_of::<GLfloat>() * mesh.points.len()) as GLsizeiptr,
+            mem::size_of_val(mesh.points.as_slice()) as GLsizeiptr,
             mesh.points.as_ptr() as *const GLvoid,
             gl::STATIC_DRAW
         );
@@ -421,7 +1179,7 @@ fn send_to_gpu_mesh(shader: GLuint, mesh: &ObjMesh) -> (GLuint, GLuint, GLuint)
         gl::BindBuffer(gl::ARRAY_BUFFER, v_norm_vbo);
         gl::BufferData(
             gl::ARRAY_BUFFER,
-            mesh.normals.len_bytes() as GLsizeiptr,
+            mem::size_of_val(mesh.normals.as_slice()) as GLsizeiptr,
             mesh.normals.as_ptr() as *const GLvoid,
             gl::STATIC_DRAW
         );
@@ -429,19 +1187,449 @@ fn send_to_gpu_mesh(shader: GLuint, mesh: &ObjMesh) -> (GLuint, GLuint, GLuint)
     debug_assert!(v_norm_vbo > 0);
 
     let mut vao = 0;
+    let mut ebo = 0;
     unsafe {
         gl::GenVertexArrays(1, &mut vao);
         gl::BindVertexArray(vao);
         gl::BindBuffer(gl::ARRAY_BUFFER, v_pos_vbo);
         gl::VertexAttribPointer(v_pos_loc, 3, gl::FLOAT, gl::FALSE, 0, ptr::null());
-        gl::BindBuffer(gl::ARRAY_BUFFER, v_norm_vbo);
-        gl::VertexAttribPointer(v_norm_loc, 3, gl::FLOAT, gl::FALSE, 0, ptr::null());
         gl::EnableVertexAttribArray(v_pos_loc);
-        gl::EnableVertexAttribArray(v_norm_loc);
+        if v_norm_loc > -1 {
+            gl::BindBuffer(gl::ARRAY_BUFFER, v_norm_vbo);
+            gl::VertexAttribPointer(v_norm_loc as u32, 3, gl::FLOAT, gl::FALSE, 0, ptr::null());
+            gl::EnableVertexAttribArray(v_norm_loc as u32);
+        }
+        gl::GenBuffers(1, &mut ebo);
+        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+        gl::BufferData(
+            gl::ELEMENT_ARRAY_BUFFER,
+            mem::size_of_val(mesh.indices.as_slice()) as GLsizeiptr,
+            mesh.indices.as_ptr() as *const GLvoid,
+            gl::STATIC_DRAW
+        );
+        gl::BindVertexArray(0);
+    }
+    debug_assert!(vao > 0);
+    debug_assert!(ebo > 0);
+
+    let saved_bytes = mesh.unindexed_len_bytes() as isize - mesh.len_bytes() as isize;
+    info!(
+        "Uploaded a mesh with {} vertices for {} triangles in {} bytes, saving {} bytes ({:.1}%) over unindexed triangles",
+        mesh.vertex_count(), mesh.index_count() / 3, mesh.len_bytes(), 
+        saved_bytes, 100.0 * saved_bytes as f64 / mesh.unindexed_len_bytes() as f64
+    );
+
+    (vao, v_pos_vbo, v_norm_vbo, ebo)
+}
+
+/// Send the linearly transformed cosine lookup tables for shading area lights to the GPU.
+fn send_to_gpu_ltc_tables(shader: GLuint) -> (Texture, Texture) {
+    let ltc_1_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("ltc_1").as_ptr())
+    };
+    debug_assert!(ltc_1_loc > -1);
+    let ltc_2_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("ltc_2").as_ptr())
+    };
+    debug_assert!(ltc_2_loc > -1);
+
+    let size = ltc::LTC_TABLE_SIZE as u32;
+    let inverse_matrices: Vec<f32> = ltc::LTC_INVERSE_MATRICES.iter()
+        .flat_map(|m| m.iter().cloned())
+        .collect();
+    let ltc_1_tex = Texture::new_2d(size, size, TextureFormat::Rgba32F, Some(&inverse_matrices));
+    let ltc_2_tex = Texture::new_2d(size, size, TextureFormat::R32F, Some(&ltc::LTC_MAGNITUDES));
+
+    unsafe {
+        gl::UseProgram(shader);
+        gl::Uniform1i(ltc_1_loc, LTC_1_TEXTURE_UNIT as GLint);
+        gl::Uniform1i(ltc_2_loc, LTC_2_TEXTURE_UNIT as GLint);
+    }
+
+    (ltc_1_tex, ltc_2_tex)
+}
+
+/// Allocate a cube map on the GPU holding `levels` as its mipmap levels.
+fn send_to_gpu_cube_map(levels: &[CubeMap]) -> Texture {
+    let texture = Texture::new_cube_map(levels[0].size, levels.len() as u32, TextureFormat::Rgb32F);
+    for (level, cube_map) in levels.iter().enumerate() {
+        for (face, texels) in cube_map.faces.iter().enumerate() {
+            texture.upload_cube_map_face(face as u32, level as u32, texels);
+        }
+    }
+
+    texture
+}
+
+/// Send the filtered environment for image based lighting to the GPU.
+fn send_to_gpu_environment(shader: GLuint, environment: &Environment) -> (Texture, Texture) {
+    let irradiance_map_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("irradiance_map").as_ptr())
+    };
+    debug_assert!(irradiance_map_loc > -1);
+    let specular_map_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("specular_map").as_ptr())
+    };
+    debug_assert!(specular_map_loc > -1);
+    let specular_map_exponent_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("specular_map_exponent").as_ptr())
+    };
+    debug_assert!(specular_map_exponent_loc > -1);
+    let specular_map_levels_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("specular_map_levels").as_ptr())
+    };
+    debug_assert!(specular_map_levels_loc > -1);
+
+    let irradiance_tex = send_to_gpu_cube_map(std::slice::from_ref(&environment.irradiance));
+    let specular_tex = send_to_gpu_cube_map(&environment.specular);
+
+    unsafe {
+        gl::UseProgram(shader);
+        gl::Uniform1i(irradiance_map_loc, IRRADIANCE_MAP_TEXTURE_UNIT as GLint);
+        gl::Uniform1i(specular_map_loc, SPECULAR_MAP_TEXTURE_UNIT as GLint);
+        gl::Uniform1f(specular_map_exponent_loc, SPECULAR_MAP_EXPONENT);
+        gl::Uniform1i(specular_map_levels_loc, environment.specular.len() as GLint);
+    }
+
+    (irradiance_tex, specular_tex)
+}
+
+/// Send the spherical harmonics projection of the diffuse irradiance of the environment
+/// to the GPU.
+fn send_to_gpu_uniforms_sh_irradiance(shader: GLuint, irradiance: &SphericalHarmonics) {
+    let sh_irradiance_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("sh_irradiance").as_ptr())
+    };
+    debug_assert!(sh_irradiance_loc > -1);
+
+    unsafe {
+        gl::UseProgram(shader);
+        gl::Uniform3fv(
+            sh_irradiance_loc, 
+            irradiance.coefficients.len() as GLint, 
+            irradiance.coefficients.as_ptr() as *const GLfloat
+        );
+    }
+}
+
+/// Send the orientation and brightness of the environment to the GPU.
+fn send_to_gpu_uniforms_environment(shader: GLuint, settings: &EnvironmentSettings) {
+    let environment_rotation_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("environment_rotation").as_ptr())
+    };
+    debug_assert!(environment_rotation_loc > -1);
+    let environment_luminance_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("environment_luminance").as_ptr())
+    };
+    debug_assert!(environment_luminance_loc > -1);
+
+    let rotation_mat = settings.rotation_mat();
+    unsafe {
+        gl::UseProgram(shader);
+        gl::UniformMatrix3fv(environment_rotation_loc, 1, gl::FALSE, rotation_mat.as_ptr() as *const GLfloat);
+        gl::Uniform1f(environment_luminance_loc, settings.luminance);
+    }
+}
+
+fn send_to_gpu_uniforms_ambient_lighting(shader: GLuint, ambient: AmbientLighting) {
+    let ambient_lighting_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("ambient_lighting").as_ptr())
+    };
+    debug_assert!(ambient_lighting_loc > -1);
+
+    unsafe {
+        gl::UseProgram(shader);
+        gl::Uniform1i(ambient_lighting_loc, ambient.type_id());
+    }
+}
+
+/// Send the skybox surrounding the scene to the GPU.
+fn send_to_gpu_skybox(shader: GLuint, skybox: &CubeMap) -> Texture {
+    let skybox_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("skybox").as_ptr())
+    };
+    debug_assert!(skybox_loc > -1);
+
+    let skybox_tex = send_to_gpu_cube_map(std::slice::from_ref(skybox));
+    unsafe {
+        gl::UseProgram(shader);
+        gl::Uniform1i(skybox_loc, SKYBOX_TEXTURE_UNIT as GLint);
+    }
+
+    skybox_tex
+}
+
+/// Send the camera exposure for mapping the luminance of the scene to display values
+/// to the GPU.
+fn send_to_gpu_uniforms_exposure(shader: GLuint, ev100: f32) {
+    let exposure_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("exposure").as_ptr())
+    };
+    debug_assert!(exposure_loc > -1);
+
+    unsafe {
+        gl::UseProgram(shader);
+        gl::Uniform1f(exposure_loc, photometry::exposure_from_ev100(ev100));
+    }
+}
+
+/// Bind the shadow maps to the mesh shader.
+fn send_to_gpu_shadow_maps(shader: GLuint, shadow_maps: &ShadowMaps, point_shadow_maps: &ShadowMaps) {
+    let shadow_maps_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("shadow_maps").as_ptr())
+    };
+    debug_assert!(shadow_maps_loc > -1);
+    let point_shadow_maps_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("point_shadow_maps").as_ptr())
+    };
+    debug_assert!(point_shadow_maps_loc > -1);
+    let point_shadow_near_plane_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("point_shadow_near_plane").as_ptr())
+    };
+    debug_assert!(point_shadow_near_plane_loc > -1);
+    let point_shadow_far_plane_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("point_shadow_far_plane").as_ptr())
+    };
+    debug_assert!(point_shadow_far_plane_loc > -1);
+
+    shadow_maps.texture.bind(SHADOW_MAPS_TEXTURE_UNIT);
+    point_shadow_maps.texture.bind(POINT_SHADOW_MAPS_TEXTURE_UNIT);
+    unsafe {
+        gl::UseProgram(shader);
+        gl::Uniform1i(shadow_maps_loc, SHADOW_MAPS_TEXTURE_UNIT as GLint);
+        gl::Uniform1i(point_shadow_maps_loc, POINT_SHADOW_MAPS_TEXTURE_UNIT as GLint);
+        gl::Uniform1f(point_shadow_near_plane_loc, POINT_SHADOW_NEAR_PLANE);
+        gl::Uniform1f(point_shadow_far_plane_loc, POINT_SHADOW_FAR_PLANE);
+    }
+}
+
+fn send_to_gpu_uniforms_shadow_settings(shader: GLuint, settings: &ShadowSettings) {
+    let shadow_bias_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("shadow_bias").as_ptr())
+    };
+    debug_assert!(shadow_bias_loc > -1);
+    let point_shadow_bias_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("point_shadow_bias").as_ptr())
+    };
+    debug_assert!(point_shadow_bias_loc > -1);
+    let shadow_filter_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("shadow_filter").as_ptr())
+    };
+    debug_assert!(shadow_filter_loc > -1);
+
+    unsafe {
+        gl::UseProgram(shader);
+        gl::Uniform2f(shadow_bias_loc, settings.bias.constant, settings.bias.slope);
+        gl::Uniform2f(point_shadow_bias_loc, settings.point_bias.constant, settings.point_bias.slope);
+        gl::Uniform1i(shadow_filter_loc, settings.filter.type_id());
+    }
+}
+
+fn send_to_gpu_uniforms_shadow(shader: GLuint, light_space_mat: &Matrix4<f32>) {
+    let light_space_mat_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("light_space_mat").as_ptr())
+    };
+    debug_assert!(light_space_mat_loc > -1);
+
+    unsafe {
+        gl::UseProgram(shader);
+        gl::UniformMatrix4fv(light_space_mat_loc, 1, gl::FALSE, light_space_mat.as_ptr());
+    }
+}
+
+fn send_to_gpu_uniforms_point_shadow(shader: GLuint, light_space_mat: &Matrix4<f32>, position: &Vector3<f32>) {
+    let light_space_mat_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("light_space_mat").as_ptr())
+    };
+    debug_assert!(light_space_mat_loc > -1);
+    let light_position_world_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("light_position_world").as_ptr())
+    };
+    debug_assert!(light_position_world_loc > -1);
+    let far_plane_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("far_plane").as_ptr())
+    };
+    debug_assert!(far_plane_loc > -1);
+
+    unsafe {
+        gl::UseProgram(shader);
+        gl::UniformMatrix4fv(light_space_mat_loc, 1, gl::FALSE, light_space_mat.as_ptr());
+        gl::Uniform3fv(light_position_world_loc, 1, position.as_ptr());
+        gl::Uniform1f(far_plane_loc, POINT_SHADOW_FAR_PLANE);
+    }
+}
+
+/// Send a quad covering the whole viewport to the GPU for the shadow map debug view.
+fn send_to_gpu_debug_quad(shader: GLuint) -> (GLuint, GLuint) {
+    let v_pos_loc = unsafe {
+        gl::GetAttribLocation(shader, backend::gl_str("v_pos").as_ptr())
+    };
+    debug_assert!(v_pos_loc > -1);
+    let v_pos_loc = v_pos_loc as u32;
+
+    let points: [[f32; 2]; 6] = [
+        [-1.0, -1.0], [1.0, -1.0], [1.0, 1.0],
+        [-1.0, -1.0], [1.0,  1.0], [-1.0, 1.0],
+    ];
+    let mut v_pos_vbo = 0;
+    unsafe {
+        gl::GenBuffers(1, &mut v_pos_vbo);
+    }
+    debug_assert!(v_pos_vbo > 0);
+    unsafe {
+        gl::BindBuffer(gl::ARRAY_BUFFER, v_pos_vbo);
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            mem::size_of_val(&points) as GLsizeiptr,
+            points.as_ptr() as *const GLvoid,
+            gl::STATIC_DRAW
+        );
+    }
+
+    let mut vao = 0;
+    unsafe {
+        gl::GenVertexArrays(1, &mut vao);
+    }
+    debug_assert!(vao > 0);
+    unsafe {
+        gl::BindVertexArray(vao);
+        gl::BindBuffer(gl::ARRAY_BUFFER, v_pos_vbo);
+        gl::VertexAttribPointer(v_pos_loc, 2, gl::FLOAT, gl::FALSE, 0, ptr::null());
+        gl::EnableVertexAttribArray(v_pos_loc);
+    }
+
+    (vao, v_pos_vbo)
+}
+
+/// Draw the faces of one cube shadow map in a row along the top of the window.
+fn draw_shadow_debug_view(
+    context: &OpenGLContext, shader: GLuint, vao: GLuint, point_shadow_maps: &ShadowMaps, cube: usize) {
+
+    let shadow_maps_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("shadow_maps").as_ptr())
+    };
+    debug_assert!(shadow_maps_loc > -1);
+    let layer_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("layer").as_ptr())
+    };
+    debug_assert!(layer_loc > -1);
+
+    point_shadow_maps.texture.bind(POINT_SHADOW_MAPS_TEXTURE_UNIT);
+    let size = SHADOW_DEBUG_VIEW_SIZE as GLint;
+    let top = context.height as GLint - size;
+    unsafe {
+        gl::Disable(gl::DEPTH_TEST);
+        gl::UseProgram(shader);
+        gl::Uniform1i(shadow_maps_loc, POINT_SHADOW_MAPS_TEXTURE_UNIT as GLint);
+        gl::BindVertexArray(vao);
+    }
+    for face in 0..CUBE_FACES {
+        unsafe {
+            gl::Viewport(face as GLint * size, top, size, size);
+            gl::Uniform1i(layer_loc, (cube * CUBE_FACES + face) as GLint);
+            gl::DrawArrays(gl::TRIANGLES, 0, 6);
+        }
+    }
+    unsafe {
+        gl::Enable(gl::DEPTH_TEST);
+        gl::Viewport(0, 0, context.width as GLint, context.height as GLint);
+    }
+}
+
+/// Send the glyphs of the label font to the GPU.
+fn send_to_gpu_font_atlas(shader: GLuint, atlas: &FontAtlas) -> Texture {
+    let font_atlas_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("font_atlas").as_ptr())
+    };
+    debug_assert!(font_atlas_loc > -1);
+
+    let texture = Texture::new_2d(atlas.width, atlas.height, TextureFormat::R32F, Some(&atlas.pixels));
+    texture.set_filter(gl::NEAREST);
+    unsafe {
+        gl::UseProgram(shader);
+        gl::Uniform1i(font_atlas_loc, FONT_ATLAS_TEXTURE_UNIT as GLint);
+    }
+
+    texture
+}
+
+/// Allocate the vertex buffer for text, which is filled again each time text is drawn.
+fn send_to_gpu_text_buffer(shader: GLuint) -> (GLuint, GLuint) {
+    let v_pos_loc = unsafe {
+        gl::GetAttribLocation(shader, backend::gl_str("v_pos").as_ptr())
+    };
+    debug_assert!(v_pos_loc > -1);
+    let v_pos_loc = v_pos_loc as u32;
+
+    let v_tex_coord_loc = unsafe {
+        gl::GetAttribLocation(shader, backend::gl_str("v_tex_coord").as_ptr())
+    };
+    debug_assert!(v_tex_coord_loc > -1);
+    let v_tex_coord_loc = v_tex_coord_loc as u32;
+
+    let mut vbo = 0;
+    unsafe {
+        gl::GenBuffers(1, &mut vbo);
+    }
+    debug_assert!(vbo > 0);
+
+    let stride = (4 * mem::size_of::<GLfloat>()) as GLint;
+    let mut vao = 0;
+    unsafe {
+        gl::GenVertexArrays(1, &mut vao);
+        gl::BindVertexArray(vao);
+        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+        gl::VertexAttribPointer(v_pos_loc, 2, gl::FLOAT, gl::FALSE, stride, ptr::null());
+        gl::VertexAttribPointer(
+            v_tex_coord_loc, 2, gl::FLOAT, gl::FALSE, stride, (2 * mem::size_of::<GLfloat>()) as *const GLvoid
+        );
+        gl::EnableVertexAttribArray(v_pos_loc);
+        gl::EnableVertexAttribArray(v_tex_coord_loc);
     }
     debug_assert!(vao > 0);
 
-    (vao, v_pos_vbo, v_norm_vbo)
+    (vao, vbo)
+}
+
+/// Draw text on top of the scene, in white with a black shadow so that it stands out
+/// against any background.
+fn draw_text(context: &OpenGLContext, shader: GLuint, vao: GLuint, vbo: GLuint, vertices: &[[f32; 4]]) {
+    let screen_size_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("screen_size").as_ptr())
+    };
+    debug_assert!(screen_size_loc > -1);
+    let offset_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("offset").as_ptr())
+    };
+    debug_assert!(offset_loc > -1);
+    let text_color_loc = unsafe {
+        gl::GetUniformLocation(shader, backend::gl_str("text_color").as_ptr())
+    };
+    debug_assert!(text_color_loc > -1);
+
+    if vertices.is_empty() {
+        return;
+    }
+    unsafe {
+        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            mem::size_of_val(vertices) as GLsizeiptr,
+            vertices.as_ptr() as *const GLvoid,
+            gl::DYNAMIC_DRAW
+        );
+        gl::Disable(gl::DEPTH_TEST);
+        gl::UseProgram(shader);
+        gl::Uniform2f(screen_size_loc, context.width as GLfloat, context.height as GLfloat);
+        gl::BindVertexArray(vao);
+        gl::Uniform2f(offset_loc, LABEL_SCALE, -LABEL_SCALE);
+        gl::Uniform3f(text_color_loc, 0.0, 0.0, 0.0);
+        gl::DrawArrays(gl::TRIANGLES, 0, vertices.len() as GLint);
+        gl::Uniform2f(offset_loc, 0.0, 0.0);
+        gl::Uniform3f(text_color_loc, 1.0, 1.0, 1.0);
+        gl::DrawArrays(gl::TRIANGLES, 0, vertices.len() as GLint);
+        gl::Enable(gl::DEPTH_TEST);
+    }
 }
 
 fn send_to_gpu_light_mesh(shader: GLuint, mesh: &ObjMesh) -> (GLuint, GLuint) {
@@ -509,6 +1697,66 @@ fn create_light_shader_source() -> ShaderSource {
     }
 }
 
+fn create_shadow_shader_source() -> ShaderSource {
+    let vert_source = include_str!("../shaders/shadow_depth.vert.glsl");
+    let frag_source = include_str!("../shaders/shadow_depth.frag.glsl");
+
+    ShaderSource {
+        vert_name: "shadow_depth.vert.glsl",
+        vert_source: vert_source,
+        frag_name: "shadow_depth.frag.glsl",
+        frag_source: frag_source,
+    }
+}
+
+fn create_point_shadow_shader_source() -> ShaderSource {
+    let vert_source = include_str!("../shaders/point_shadow_depth.vert.glsl");
+    let frag_source = include_str!("../shaders/point_shadow_depth.frag.glsl");
+
+    ShaderSource {
+        vert_name: "point_shadow_depth.vert.glsl",
+        vert_source: vert_source,
+        frag_name: "point_shadow_depth.frag.glsl",
+        frag_source: frag_source,
+    }
+}
+
+fn create_skybox_shader_source() -> ShaderSource {
+    let vert_source = include_str!("../shaders/skybox.vert.glsl");
+    let frag_source = include_str!("../shaders/skybox.frag.glsl");
+
+    ShaderSource {
+        vert_name: "skybox.vert.glsl",
+        vert_source: vert_source,
+        frag_name: "skybox.frag.glsl",
+        frag_source: frag_source,
+    }
+}
+
+fn create_text_shader_source() -> ShaderSource {
+    let vert_source = include_str!("../shaders/text.vert.glsl");
+    let frag_source = include_str!("../shaders/text.frag.glsl");
+
+    ShaderSource {
+        vert_name: "text.vert.glsl",
+        vert_source: vert_source,
+        frag_name: "text.frag.glsl",
+        frag_source: frag_source,
+    }
+}
+
+fn create_shadow_debug_shader_source() -> ShaderSource {
+    let vert_source = include_str!("../shaders/shadow_debug.vert.glsl");
+    let frag_source = include_str!("../shaders/shadow_debug.frag.glsl");
+
+    ShaderSource {
+        vert_name: "shadow_debug.vert.glsl",
+        vert_source: vert_source,
+        frag_name: "shadow_debug.frag.glsl",
+        frag_source: frag_source,
+    }
+}
+
 fn send_to_gpu_shaders(context: &mut backend::OpenGLContext, source: ShaderSource) -> GLuint {
     let mut vert_reader = io::Cursor::new(source.vert_source);
     let mut frag_reader = io::Cursor::new(source.frag_source);
@@ -557,7 +1805,7 @@ fn framebuffer_size_callback(context: &mut OpenGLContext, width: u32, height: u3
     }
 }
 
-fn process_input(context: &mut OpenGLContext) -> CameraMovement {
+fn process_input(context: &mut OpenGLContext) -> MovementInput {
     match context.window.get_key(Key::Escape) {
         Action::Press | Action::Repeat => {
             context.window.set_should_close(true);
@@ -565,7 +1813,7 @@ fn process_input(context: &mut OpenGLContext) -> CameraMovement {
         _ => {}
     }
 
-    let mut movement = CameraMovement::new();
+    let mut movement = MovementInput::new();
     match context.window.get_key(Key::A) {
         Action::Press | Action::Repeat => {
             movement += SimpleCameraMovement::MoveLeft;
@@ -642,28 +1890,639 @@ fn process_input(context: &mut OpenGLContext) -> CameraMovement {
     movement
 }
 
+/// Process the key presses that toggle settings, as opposed to the keys held down to
+/// move the camera. The square brackets shrink and grow the shadow depth bias, `P` toggles
+/// between hard and soft shadows, `V` cycles the shadow map debug view through the 
+/// point lights, and the minus and equals keys close and open the camera by half a stop.
+/// `T` hands the scene to the timeline and back, the space bar plays and pauses the 
+/// timeline, the comma and period keys scrub it backward and forward, `K` records the 
+/// camera pose as a key at the playhead, and `F5` and `F9` save and load the timeline.
+/// `B` pauses the simulation, `N` advances a paused simulation by a single step, and
+/// `9` and `0` halve and double the speed of the simulation. `L` switches to the next 
+/// light rig, and `F6` saves the current rig to the rigs directory. `I` cycles the ambient
+/// lighting between flat, spherical harmonics, and the irradiance map, `7` and `8` dim and
+/// brighten the environment by half a stop, and `G` and `H` rotate it about the vertical 
+/// axis. `M` toggles the transparent materials, and `U` cycles the shape of the objects
+/// when no meshes are loaded from files.
+fn process_events(
+    context: &mut OpenGLContext, 
+    settings: &mut ShadowSettings, 
+    ev100: &mut f32, 
+    num_point_shadow_maps: usize,
+    timeline_settings: &mut TimelineSettings,
+    camera_frame: &CameraFrame,
+    clock: &mut SimClock,
+    rig_settings: &mut RigSettings,
+    environment_settings: &mut EnvironmentSettings,
+    show_transparent: &mut bool,
+    preview_shape: &mut PreviewShape) {
+
+    let timeline = &mut timeline_settings.timeline;
+    for (_, event) in glfw::flush_messages(&context.events) {
+        match event {
+            WindowEvent::Key(Key::L, _, Action::Press, _) => {
+                rig_settings.current = (rig_settings.current + 1) % rig_settings.rigs.len();
+                rig_settings.changed = true;
+                info!("Light rig: {}", rig_settings.current().name);
+            }
+            WindowEvent::Key(Key::F6, _, Action::Press, _) => {
+                let rig = rig_settings.current();
+                let path = rig_path(&rig.name);
+                let saved = fs::create_dir_all(RIGS_DIRECTORY)
+                    .map_err(|e| e.to_string())
+                    .and_then(|_| rig.save(&path).map_err(|e| e.to_string()));
+                match saved {
+                    Ok(()) => info!("Saved the light rig {} to {}", rig.name, path),
+                    Err(e) => info!("Could not save the light rig to {}. Got error: {}", path, e),
+                }
+            }
+            WindowEvent::Key(Key::I, _, Action::Press, _) => {
+                environment_settings.ambient = environment_settings.ambient.next();
+                info!("Ambient lighting: {:?}", environment_settings.ambient);
+            }
+            WindowEvent::Key(Key::M, _, Action::Press, _) => {
+                *show_transparent = !*show_transparent;
+                info!("Transparent materials: {}", *show_transparent);
+            }
+            WindowEvent::Key(Key::U, _, Action::Press, _) => {
+                *preview_shape = preview_shape.next();
+                info!("Preview shape: {:?}", *preview_shape);
+            }
+            WindowEvent::Key(Key::Num7, _, Action::Press, _) => {
+                environment_settings.expose(-0.5);
+                info!("Environment luminance: {} nits", environment_settings.luminance);
+            }
+            WindowEvent::Key(Key::Num8, _, Action::Press, _) => {
+                environment_settings.expose(0.5);
+                info!("Environment luminance: {} nits", environment_settings.luminance);
+            }
+            WindowEvent::Key(Key::G, _, Action::Press, _) | 
+            WindowEvent::Key(Key::G, _, Action::Repeat, _) => {
+                environment_settings.rotate(-ENVIRONMENT_ROTATION_STEP_DEGREES);
+                info!("Environment rotation: {} degrees", environment_settings.rotation_degrees);
+            }
+            WindowEvent::Key(Key::H, _, Action::Press, _) | 
+            WindowEvent::Key(Key::H, _, Action::Repeat, _) => {
+                environment_settings.rotate(ENVIRONMENT_ROTATION_STEP_DEGREES);
+                info!("Environment rotation: {} degrees", environment_settings.rotation_degrees);
+            }
+            WindowEvent::Key(Key::B, _, Action::Press, _) => {
+                clock.toggle_pause();
+                info!("Simulation paused: {} at {} s (tick {})", clock.is_paused(), clock.time(), clock.ticks());
+            }
+            WindowEvent::Key(Key::N, _, Action::Press, _) | 
+            WindowEvent::Key(Key::N, _, Action::Repeat, _) => {
+                clock.request_step();
+            }
+            WindowEvent::Key(Key::Num9, _, Action::Press, _) => {
+                clock.scale_time(0.5);
+                info!("Simulation time scale: {}", clock.time_scale());
+            }
+            WindowEvent::Key(Key::Num0, _, Action::Press, _) => {
+                clock.scale_time(2.0);
+                info!("Simulation time scale: {}", clock.time_scale());
+            }
+            WindowEvent::Key(Key::T, _, Action::Press, _) => {
+                timeline_settings.enabled = !timeline_settings.enabled;
+                info!("Timeline enabled: {}", timeline_settings.enabled);
+            }
+            WindowEvent::Key(Key::Space, _, Action::Press, _) => {
+                timeline.toggle_playback();
+                info!("Timeline playing: {} at {} s", timeline.is_playing(), timeline.time());
+            }
+            WindowEvent::Key(Key::Comma, _, Action::Press, _) | 
+            WindowEvent::Key(Key::Comma, _, Action::Repeat, _) => {
+                timeline.scrub(-TIMELINE_SCRUB_SECONDS);
+                info!("Timeline time: {} s", timeline.time());
+            }
+            WindowEvent::Key(Key::Period, _, Action::Press, _) | 
+            WindowEvent::Key(Key::Period, _, Action::Repeat, _) => {
+                timeline.scrub(TIMELINE_SCRUB_SECONDS);
+                info!("Timeline time: {} s", timeline.time());
+            }
+            WindowEvent::Key(Key::K, _, Action::Press, _) => {
+                timeline.insert_camera_key(camera_frame);
+                info!("Recorded a camera key at {} s", timeline.time());
+            }
+            WindowEvent::Key(Key::F5, _, Action::Press, _) => {
+                match timeline.save(TIMELINE_PATH) {
+                    Ok(()) => info!("Saved the timeline to {}", TIMELINE_PATH),
+                    Err(e) => info!("Could not save the timeline to {}. Got error: {}", TIMELINE_PATH, e),
+                }
+            }
+            WindowEvent::Key(Key::F9, _, Action::Press, _) => {
+                match Timeline::load(TIMELINE_PATH) {
+                    Ok(loaded) => {
+                        *timeline = loaded;
+                        info!("Loaded the timeline from {}", TIMELINE_PATH);
+                    }
+                    Err(e) => info!("Could not load the timeline from {}. Got error: {}", TIMELINE_PATH, e),
+                }
+            }
+            WindowEvent::Key(Key::Minus, _, Action::Press, _) => {
+                *ev100 += 0.5;
+                info!("Exposure: EV100 {}", *ev100);
+            }
+            WindowEvent::Key(Key::Equal, _, Action::Press, _) => {
+                *ev100 -= 0.5;
+                info!("Exposure: EV100 {}", *ev100);
+            }
+            WindowEvent::Key(Key::LeftBracket, _, Action::Press, _) => {
+                settings.bias.scale(0.8);
+                settings.point_bias.scale(0.8);
+                info!("Shadow depth bias: {:?}. Point shadow depth bias: {:?}", settings.bias, settings.point_bias);
+            }
+            WindowEvent::Key(Key::RightBracket, _, Action::Press, _) => {
+                settings.bias.scale(1.25);
+                settings.point_bias.scale(1.25);
+                info!("Shadow depth bias: {:?}. Point shadow depth bias: {:?}", settings.bias, settings.point_bias);
+            }
+            WindowEvent::Key(Key::P, _, Action::Press, _) => {
+                settings.filter = settings.filter.toggle();
+                info!("Shadow filter: {:?}", settings.filter);
+            }
+            WindowEvent::Key(Key::V, _, Action::Press, _) => {
+                settings.debug_cube = match settings.debug_cube {
+                    None if num_point_shadow_maps > 0 => Some(0),
+                    Some(cube) if cube + 1 < num_point_shadow_maps => Some(cube + 1),
+                    _ => None,
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The options the demo accepts on the command line.
+struct CommandLineOptions {
+    /// The tick the simulation starts at, if it runs on virtual time instead of the wall
+    /// clock.
+    virtual_time_start_tick: Option<u64>,
+    /// The file to record the camera movement input of the session to.
+    record_path: Option<String>,
+    /// The file to replay camera movement input from instead of the keyboard.
+    replay_path: Option<String>,
+    /// The environment surrounding and lighting the scene.
+    environment: EnvironmentSource,
+    /// The `.obj` files to show instead of the boxes.
+    mesh_paths: Vec<String>,
+    /// The crease angle in degrees to generate new normals for the meshes with, replacing 
+    /// the normals in their files.
+    crease_angle_degrees: Option<f32>,
+    /// The scheme and number of levels to subdivide the meshes with.
+    subdivision: Option<(SubdivisionScheme, usize)>,
+    /// Whether to show every material of `teapots.c` side by side instead of the boxes.
+    gallery: bool,
+}
+
+const USAGE: &str = 
+    "Usage: material_demo [--virtual-time <start tick>] [--record <file>] [--replay <file>] \
+     [--environment <file.hdr> | --environment-faces <directory>] [--gallery | [--crease-angle <degrees>] [--subdivide <loop | catmull-clark> <levels>] <mesh.obj>...]";
+
+/// Parse the command line. The demo prints its usage and exits if it does not 
+/// recognize an argument.
+fn parse_command_line() -> CommandLineOptions {
+    let mut options = CommandLineOptions {
+        virtual_time_start_tick: None,
+        record_path: None,
+        replay_path: None,
+        environment: EnvironmentSource::GradientSky,
+        mesh_paths: vec![],
+        crease_angle_degrees: None,
+        subdivision: None,
+        gallery: false,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--virtual-time" => {
+                let start_tick = args.next().and_then(|start_tick| start_tick.parse::<u64>().ok());
+                match start_tick {
+                    Some(start_tick) if start_tick <= MAX_START_TICK => {
+                        options.virtual_time_start_tick = Some(start_tick);
+                    }
+                    _ => {
+                        eprintln!(
+                            "Expected a start tick between 0 and {} after `--virtual-time`.\n{}", MAX_START_TICK, USAGE
+                        );
+                        process::exit(1);
+                    }
+                }
+            }
+            "--gallery" => options.gallery = true,
+            "--crease-angle" => {
+                let angle = args.next().and_then(|angle| angle.parse::<f32>().ok());
+                match angle {
+                    Some(angle) if (0.0..=180.0).contains(&angle) => options.crease_angle_degrees = Some(angle),
+                    _ => {
+                        eprintln!("Expected an angle between 0 and 180 degrees after `--crease-angle`.\n{}", USAGE);
+                        process::exit(1);
+                    }
+                }
+            }
+            "--subdivide" => {
+                let scheme = args.next().and_then(|scheme| SubdivisionScheme::parse(&scheme));
+                let levels = args.next().and_then(|levels| levels.parse::<usize>().ok());
+                match (scheme, levels) {
+                    (Some(scheme), Some(levels)) if levels <= MAX_SUBDIVISION_LEVELS => {
+                        options.subdivision = Some((scheme, levels));
+                    }
+                    _ => {
+                        eprintln!(
+                            "Expected `loop` or `catmull-clark` and at most {} levels after `--subdivide`.\n{}", 
+                            MAX_SUBDIVISION_LEVELS, USAGE
+                        );
+                        process::exit(1);
+                    }
+                }
+            }
+            "--record" | "--replay" | "--environment" | "--environment-faces" => {
+                let path = match args.next() {
+                    Some(path) => path,
+                    None => {
+                        eprintln!("Expected a file after `{}`.\n{}", arg, USAGE);
+                        process::exit(1);
+                    }
+                };
+                match arg.as_str() {
+                    "--record" => options.record_path = Some(path),
+                    "--replay" => options.replay_path = Some(path),
+                    "--environment" => options.environment = EnvironmentSource::Equirectangular(path),
+                    _ => options.environment = EnvironmentSource::CubeFaces(path),
+                }
+            }
+            _ if !arg.starts_with("--") => options.mesh_paths.push(arg),
+            _ => {
+                eprintln!("Unknown argument `{}`.\n{}", arg, USAGE);
+                process::exit(1);
+            }
+        }
+    }
+    if options.gallery && !options.mesh_paths.is_empty() {
+        eprintln!("The gallery shows the preview shapes and cannot show meshes from files.\n{}", USAGE);
+        process::exit(1);
+    }
+
+    options
+}
+
+/// Load the meshes given on the command line, scaled to the size of the box. With a 
+/// crease angle, the meshes get new smooth normals. With a subdivision scheme, the
+/// meshes are subdivided, keeping the edges sharper than the crease angle sharp. The 
+/// demo prints the error and exits if it cannot load one of them.
+fn load_meshes(
+    paths: &[String], 
+    crease_angle_degrees: Option<f32>, 
+    subdivision: Option<(SubdivisionScheme, usize)>) -> Vec<ObjMesh> {
+    paths.iter()
+        .map(|path| {
+            let mesh = match mesh::load_obj(path) {
+                Ok(mesh) => mesh,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            };
+            info!("Loaded {} triangles from {}", mesh.len() / 3, path);
+            let mesh = match (subdivision, crease_angle_degrees) {
+                (Some((scheme, levels)), angle) => {
+                    let angle = angle.unwrap_or(mesh::DEFAULT_CREASE_ANGLE_DEGREES);
+                    let subdivided_mesh = subdivision::subdivide(&mesh, scheme, levels, angle);
+                    info!("Subdivided {} into {} triangles", path, subdivided_mesh.len() / 3);
+                    subdivided_mesh
+                }
+                (None, Some(angle)) => mesh::with_smooth_normals(&mesh, angle),
+                (None, None) => mesh,
+            };
+
+            mesh::fit_to_unit_cube(&mesh)
+        })
+        .collect()
+}
+
+/// Create the scene objects for meshes loaded from files. The meshes stand on the ground
+/// plane in a row across the center of the scene.
+fn create_mesh_objects(
+    shader: GLuint, 
+    meshes: &[ObjMesh], 
+    materials: &HashMap<&str, Material<f32>>) -> Vec<SceneObject> {
+
+    let row_width = MESH_SPACING * (meshes.len() - 1) as f32;
+    meshes.iter().enumerate()
+        .map(|(i, mesh)| {
+            let indexed_mesh = IndexedMesh::from_obj_mesh(mesh);
+            let (vao, _v_pos_vbo, _v_norm_vbo, _ebo) = send_to_gpu_mesh(shader, &indexed_mesh);
+            let (min, _) = mesh::bounds(mesh);
+            let position = Vector3::new(MESH_SPACING * i as f32 - 0.5 * row_width, -0.5 - min[1], 0.0);
+
+            SceneObject {
+                vao: vao,
+                index_count: indexed_mesh.index_count(),
+                model_mat: Matrix4::from_affine_translation(&position),
+                material: materials[MESH_MATERIALS[i % MESH_MATERIALS.len()]],
+                transparent_material: None,
+            }
+        })
+        .collect()
+}
+
+/// Generate the preview shapes and send them to the GPU, in the order of their indices.
+fn send_to_gpu_preview_meshes(shader: GLuint) -> Vec<PreviewMesh> {
+    PREVIEW_SHAPES.iter()
+        .map(|shape| {
+            let mesh = shape.create_mesh();
+            let indexed_mesh = IndexedMesh::from_obj_mesh(&mesh);
+            let (vao, _v_pos_vbo, _v_norm_vbo, _ebo) = send_to_gpu_mesh(shader, &indexed_mesh);
+            let (min, _) = mesh::bounds(&mesh);
+            info!("Generated the {:?} preview shape with {} triangles", shape, mesh.len() / 3);
+
+            PreviewMesh {
+                vao: vao,
+                index_count: indexed_mesh.index_count(),
+                bottom: min[1],
+            }
+        })
+        .collect()
+}
+
+/// Give the objects at the preview placements the shape of `preview_mesh`, standing on
+/// the ground plane.
+fn place_preview_objects(objects: &mut [SceneObject], preview_mesh: &PreviewMesh) {
+    for (object, &(position, scale)) in objects.iter_mut().zip(PREVIEW_PLACEMENTS.iter()) {
+        let height = -0.5 - scale * preview_mesh.bottom;
+        object.vao = preview_mesh.vao;
+        object.index_count = preview_mesh.index_count;
+        object.model_mat = Matrix4::from_affine_translation(&Vector3::new(position[0], height, position[1])) *
+            Matrix4::from_affine_scale(scale);
+    }
+}
+
+/// The names of the materials of the gallery in the order of its objects: those of
+/// `teapots.c` first, then the extra row.
+fn gallery_materials() -> impl Iterator<Item = &'static str> {
+    TEAPOTS_MATERIALS.iter().chain(GALLERY_EXTRA_MATERIALS.iter()).cloned()
+}
+
+/// The center of cell `index` of the material gallery. The cells of the `teapots.c` 
+/// materials fill each column from the top down before moving on to the next column, 
+/// like `teapots.c` does. The extra materials sit centered in a row below them.
+fn gallery_position(index: usize) -> Vector3<f32> {
+    let (column, row, columns) = if index < TEAPOTS_MATERIALS.len() {
+        (index / GALLERY_ROWS, index % GALLERY_ROWS, GALLERY_COLUMNS)
+    } else {
+        (index - TEAPOTS_MATERIALS.len(), GALLERY_ROWS, GALLERY_EXTRA_MATERIALS.len())
+    };
+    let x = GALLERY_CELL_WIDTH * (column as f32 - 0.5 * (columns - 1) as f32);
+    let y = GALLERY_CELL_HEIGHT * (0.5 * GALLERY_ROWS as f32 - row as f32);
+
+    Vector3::new(x, y, GALLERY_DEPTH)
+}
+
+/// Create an object for each material of `teapots.c`, and for glass and diamond, to be 
+/// given a shape by `place_gallery_objects`. The gems show their transparent versions too,
+/// so with the transparent materials on, the gallery shows every material of 
+/// `material_table`.
+fn create_gallery_objects(materials: &HashMap<&str, Material<f32>>) -> Vec<SceneObject> {
+    debug_assert_eq!(TEAPOTS_MATERIALS.len(), GALLERY_COLUMNS * GALLERY_ROWS);
+    gallery_materials()
+        .map(|name| {
+            SceneObject {
+                vao: 0,
+                index_count: 0,
+                model_mat: Matrix4::identity(),
+                material: materials[name],
+                transparent_material: materials.get(format!("clear {}", name).as_str()).cloned(),
+            }
+        })
+        .collect()
+}
+
+/// Give the objects of the material gallery the shape of `preview_mesh`, each centered
+/// in its cell.
+fn place_gallery_objects(objects: &mut [SceneObject], preview_mesh: &PreviewMesh) {
+    for (i, object) in objects.iter_mut().enumerate() {
+        object.vao = preview_mesh.vao;
+        object.index_count = preview_mesh.index_count;
+        object.model_mat = Matrix4::from_affine_translation(&gallery_position(i)) * 
+            Matrix4::from_affine_scale(GALLERY_OBJECT_SCALE);
+    }
+}
+
+/// The vertices of the labels naming the material of each cell of the gallery, centered
+/// below the objects as the camera sees them. The labels of the gems name their 
+/// transparent versions while those are shown. Labels behind the camera are left out.
+fn gallery_label_vertices(
+    context: &OpenGLContext, 
+    proj_mat: &Matrix4<f32>, 
+    view_mat: &Matrix4<f32>,
+    objects: &[SceneObject],
+    show_transparent: bool) -> Vec<[f32; 4]> {
+
+    let mut vertices = vec![];
+    for (i, (name, object)) in gallery_materials().zip(objects.iter()).enumerate() {
+        let name = if show_transparent && object.transparent_material.is_some() {
+            format!("clear {}", name)
+        } else {
+            String::from(name)
+        };
+        let anchor = gallery_position(i) - Vector3::new(0.0, GALLERY_LABEL_DROP, 0.0);
+        let clip = *proj_mat * (*view_mat * anchor.extend(1.0));
+        if clip[3] <= 0.0 {
+            continue;
+        }
+        let x = 0.5 * (clip[0] / clip[3] + 1.0) * context.width as f32;
+        let y = 0.5 * (clip[1] / clip[3] + 1.0) * context.height as f32;
+        let width = text::text_width(&name, LABEL_SCALE);
+        // Snap the labels to whole pixels so the glyphs stay sharp.
+        let left = (x - 0.5 * width).round();
+        let bottom = (y - text::GLYPH_HEIGHT as f32 * LABEL_SCALE).round();
+        vertices.extend(text::text_vertices(&name, left, bottom, LABEL_SCALE));
+    }
+
+    vertices
+}
+
+/// Advance the camera, the timeline, and the lights by one simulation step of `timestep`
+/// seconds. Every step of the same length with the same input moves the scene the same
+/// way, so the simulation does not depend on the frame rate.
+fn step_simulation(
+    camera: &mut PerspFovCamera<f32>,
+    lights: &mut [Light],
+    timeline_settings: &mut TimelineSettings,
+    movement: CameraMovement,
+    timestep: f32) {
+
+    camera.update_movement(movement, timestep);
+    if timeline_settings.enabled {
+        timeline_settings.timeline.update(timestep);
+    }
+    let user_camera_frame = CameraFrame::from_view_mat(&camera.view_matrix());
+    let camera_frame = timeline_settings.camera_frame(&user_camera_frame).unwrap_or(user_camera_frame);
+    for light in lights.iter_mut() {
+        light.step(timestep, &camera_frame);
+    }
+}
+
 fn main() {
-    let mesh = create_box_mesh();
-    let light_mesh = create_box_mesh();
     init_logger("opengl_demo.log");
     info!("BEGIN LOG");
+    let options = parse_command_line();
+    let loaded_meshes = load_meshes(&options.mesh_paths, options.crease_angle_degrees, options.subdivision);
+    let ground_plane_mesh = IndexedMesh::from_obj_mesh(&primitives::plane(2.0 * GROUND_PLANE_HALF_SIZE, 1));
+    let light_mesh = create_box_mesh();
+    let skybox_mesh = create_box_mesh();
     let scene_center_world = Vector3::<f32>::zero();
     let mut camera = create_camera(SCREEN_WIDTH, SCREEN_HEIGHT);
-    let mut lights: [Light; 3] = create_lights(scene_center_world);
-    let material = material::material_table()["jade"];
+    let mut rig_settings = RigSettings::new(scene_center_world);
+    let mut lights: Vec<Light> = create_lights(rig_settings.current());
+    for (i, light) in lights.iter().enumerate() {
+        let distance = (light.position() - scene_center_world).magnitude();
+        let attenuation = light.light.attenuation().evaluate(distance);
+        info!("Light {} attenuation at the scene center: {}", i, attenuation);
+    }
+    let materials = material::material_table();
     let mut context = init_gl(SCREEN_WIDTH, SCREEN_HEIGHT);
 
-    //  Load the model.
-    let mesh_model_mat = Matrix4::identity();
+    //  Load the models. Without meshes from the command line, the box stands on the ground 
+    //  plane next to a smaller box, so that it casts shadows onto both of them.
     let mesh_shader_source = create_mesh_shader_source();
     let mesh_shader = send_to_gpu_shaders(&mut context, mesh_shader_source);
     let (
-        mesh_vao, 
-        mesh_v_pos_vbo, 
-        mesh_v_norm_vbo) = send_to_gpu_mesh(mesh_shader, &mesh);
-    send_to_gpu_uniforms_mesh(mesh_shader, &mesh_model_mat);
-    send_to_gpu_uniforms_camera(mesh_shader, &camera);
-    send_to_gpu_uniforms_material(mesh_shader, &material);
+        ground_plane_vao,
+        ground_plane_v_pos_vbo,
+        ground_plane_v_norm_vbo,
+        ground_plane_ebo) = send_to_gpu_mesh(mesh_shader, &ground_plane_mesh);
+    let preview_meshes = send_to_gpu_preview_meshes(mesh_shader);
+    let mut preview_shape = if options.gallery { PreviewShape::Teapot } else { PreviewShape::Box };
+    let mut objects = if options.gallery {
+        let mut objects = create_gallery_objects(&materials);
+        place_gallery_objects(&mut objects, &preview_meshes[preview_shape.index()]);
+
+        objects
+    } else if loaded_meshes.is_empty() {
+        let mut objects = vec![
+            SceneObject {
+                vao: 0,
+                index_count: 0,
+                model_mat: Matrix4::identity(),
+                material: materials["jade"],
+                transparent_material: None,
+            },
+            SceneObject {
+                vao: 0,
+                index_count: 0,
+                model_mat: Matrix4::identity(),
+                material: materials["ruby"],
+                transparent_material: Some(materials["clear ruby"]),
+            },
+        ];
+        place_preview_objects(&mut objects, &preview_meshes[preview_shape.index()]);
+
+        objects
+    } else {
+        create_mesh_objects(mesh_shader, &loaded_meshes, &materials)
+    };
+    // The gallery floats in front of the camera like the original demo, without a floor.
+    if !options.gallery {
+        objects.push(
+            SceneObject {
+                vao: ground_plane_vao,
+                index_count: ground_plane_mesh.index_count(),
+                model_mat: Matrix4::from_affine_translation(&Vector3::new(0.0, -0.5, 0.0)),
+                material: materials["white plastic"],
+                transparent_material: None,
+            }
+        );
+    }
+    send_to_gpu_uniforms_camera(mesh_shader, &camera, &camera.view_matrix());
+    let (ltc_1_tex, ltc_2_tex) = send_to_gpu_ltc_tables(mesh_shader);
+    let (skybox, environment, sh_irradiance) = load_environment(&options.environment);
+    let (irradiance_tex, specular_tex) = send_to_gpu_environment(mesh_shader, &environment);
+    send_to_gpu_uniforms_sh_irradiance(mesh_shader, &sh_irradiance);
+    // Reflect the environment by default only when the user supplies one.
+    let mut environment_settings = EnvironmentSettings::new(match options.environment {
+        EnvironmentSource::GradientSky => AmbientLighting::SphericalHarmonics,
+        _ => AmbientLighting::EnvironmentMap,
+    });
+
+    // Load the shadow maps.
+    let mut shadow_settings = ShadowSettings::new();
+    let mut ev100 = photometry::ev100_from_camera(DEFAULT_APERTURE, DEFAULT_SHUTTER_TIME, DEFAULT_ISO);
+    info!(
+        "Exposure: f/{} at {} s and ISO {}, EV100 {}", 
+        DEFAULT_APERTURE, DEFAULT_SHUTTER_TIME, DEFAULT_ISO, ev100
+    );
+    // Whether objects with a transparent version of their material show it.
+    let mut show_transparent = false;
+    let mut timeline_settings = TimelineSettings::new(TIMELINE_PATH);
+    let mut clock = match options.virtual_time_start_tick {
+        Some(start_tick) => SimClock::new_virtual(SIMULATION_TIMESTEP, start_tick),
+        None => SimClock::new(SIMULATION_TIMESTEP),
+    };
+    if clock.mode() == ClockMode::Virtual {
+        // Move the lights to where they are at the tick the simulation starts at.
+        info!("Running the simulation on virtual time from tick {}", clock.ticks());
+        let camera_frame = CameraFrame::from_view_mat(&camera.view_matrix());
+        for light in lights.iter_mut() {
+            light.seek(clock.time(), &camera_frame);
+        }
+    }
+    let mut replay = options.replay_path.as_ref().map(|path| {
+        let replay = match InputRecording::load(path) {
+            Ok(replay) => replay,
+            Err(e) => {
+                eprintln!("Could not load the input recording {}. Got error: {}\n{}", path, e, USAGE);
+                process::exit(1);
+            }
+        };
+        if replay.timestep() != clock.timestep() {
+            eprintln!(
+                "The input recording {} has a timestep of {} s, but the simulation runs with {} s.\n{}", 
+                path, replay.timestep(), clock.timestep(), USAGE
+            );
+            process::exit(1);
+        }
+        if replay.start_tick() != clock.ticks() {
+            info!(
+                "The input recording {} starts at tick {}, but the simulation starts at tick {}. \
+                 The recording replays from its start, but the lights will not match the recorded session.", 
+                path, replay.start_tick(), clock.ticks()
+            );
+        }
+        info!("Replaying {} s of camera input from {}", replay.duration(), path);
+
+        replay
+    });
+    // The recording replays from its first tick whichever tick the simulation starts at.
+    let first_replayed_tick = clock.ticks();
+    let mut recording = options.record_path.as_ref()
+        .map(|_| InputRecording::new(clock.timestep(), clock.ticks()));
+    let shadow_shader_source = create_shadow_shader_source();
+    let shadow_shader = send_to_gpu_shaders(&mut context, shadow_shader_source);
+    let shadow_maps = ShadowMaps::new(SHADOW_MAP_SIZE, MAX_SHADOW_MAPS as u32);
+    let point_shadow_shader_source = create_point_shadow_shader_source();
+    let point_shadow_shader = send_to_gpu_shaders(&mut context, point_shadow_shader_source);
+    let point_shadow_maps = ShadowMaps::new(POINT_SHADOW_MAP_SIZE, (MAX_POINT_SHADOW_MAPS * CUBE_FACES) as u32);
+    send_to_gpu_shadow_maps(mesh_shader, &shadow_maps, &point_shadow_maps);
+    let shadow_debug_shader_source = create_shadow_debug_shader_source();
+    let shadow_debug_shader = send_to_gpu_shaders(&mut context, shadow_debug_shader_source);
+    let (
+        shadow_debug_vao,
+        shadow_debug_v_pos_vbo) = send_to_gpu_debug_quad(shadow_debug_shader);
+
+    // Load the label font.
+    let text_shader_source = create_text_shader_source();
+    let text_shader = send_to_gpu_shaders(&mut context, text_shader_source);
+    let (text_vao, text_vbo) = send_to_gpu_text_buffer(text_shader);
+    let font_atlas_tex = send_to_gpu_font_atlas(text_shader, &FontAtlas::new());
+
+    // Load the skybox.
+    let skybox_shader_source = create_skybox_shader_source();
+    let skybox_shader = send_to_gpu_shaders(&mut context, skybox_shader_source);
+    let (
+        skybox_vao,
+        skybox_v_pos_vbo) = send_to_gpu_light_mesh(skybox_shader, &skybox_mesh);
+    let skybox_tex = send_to_gpu_skybox(skybox_shader, &skybox);
 
     // Load the lighting cube model.
     let light_shader_source = create_light_shader_source();
@@ -675,6 +2534,7 @@ fn main() {
     unsafe {
         gl::Enable(gl::DEPTH_TEST);
         gl::DepthFunc(gl::LESS);
+        gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
         gl::ClearBufferfv(gl::COLOR, 0, &CLEAR_COLOR[0] as *const GLfloat);
         gl::ClearBufferfv(gl::DEPTH, 0, &CLEAR_DEPTH[0] as *const GLfloat);
         gl::Viewport(0, 0, context.width as GLint, context.height as GLint);
@@ -690,51 +2550,190 @@ fn main() {
             framebuffer_size_callback(&mut context, width as u32, height as u32);
         }
 
-        lights[0].kinematics.update(elapsed_seconds as f32);
-        lights[1].kinematics.update(elapsed_seconds as f32);
-        lights[2].kinematics.update(elapsed_seconds as f32);
-        let delta_movement = process_input(&mut context);
-        camera.update_movement(delta_movement, elapsed_seconds as f32);
-        send_to_gpu_uniforms_camera(mesh_shader, &camera);
-        send_to_gpu_uniforms_camera(light_shader, &camera);
-        send_to_gpu_uniforms_light(mesh_shader, &lights);
-        // Illuminate the cube.
+        if rig_settings.changed {
+            // The new lights pick up their motion at the current simulation time, so 
+            // virtual time and replays place them the same way every run.
+            lights = create_lights(rig_settings.current());
+            let user_camera_frame = CameraFrame::from_view_mat(&camera.view_matrix());
+            let camera_frame = timeline_settings.camera_frame(&user_camera_frame).unwrap_or(user_camera_frame);
+            for light in lights.iter_mut() {
+                light.seek(clock.time(), &camera_frame);
+            }
+            shadow_settings.debug_cube = None;
+            rig_settings.changed = false;
+        }
+        let keyboard_input = process_input(&mut context);
+        let tick = clock.advance(elapsed_seconds);
+        let first_tick = clock.ticks() - tick.steps as u64;
+        for step_tick in first_tick..clock.ticks() {
+            // The recording drives the camera until it runs out, then the keyboard takes over.
+            let replayed_input = replay.as_ref().and_then(|replay| {
+                replay.input_at(replay.start_tick() + (step_tick - first_replayed_tick))
+            });
+            if replay.is_some() && replayed_input.is_none() {
+                info!("Finished replaying the input recording at tick {}", step_tick);
+                replay = None;
+            }
+            let input = replayed_input.unwrap_or(keyboard_input);
+            if let Some(recording) = recording.as_mut() {
+                recording.record(step_tick, input);
+            }
+            step_simulation(&mut camera, &mut lights, &mut timeline_settings, input.to_camera_movement(), clock.timestep());
+        }
+        for light in lights.iter_mut() {
+            light.interpolate(tick.alpha);
+        }
+        if clock.mode() == ClockMode::Virtual {
+            // Log the state of every frame, so that the logs of two runs can be compared.
+            for (i, light) in lights.iter().enumerate() {
+                let position = light.position();
+                info!(
+                    "Tick {}: light {} at ({}, {}, {})", 
+                    clock.ticks(), i, position[0], position[1], position[2]
+                );
+            }
+        }
+        let user_camera_frame = CameraFrame::from_view_mat(&camera.view_matrix());
+        let (camera_frame, view_mat) = match timeline_settings.camera_frame(&user_camera_frame) {
+            Some(frame) => (frame, frame.view_mat()),
+            None => (user_camera_frame, camera.view_matrix()),
+        };
+        timeline_settings.apply_to_lights(&mut lights);
+        let shadow_casters = create_shadow_casters(&lights, &scene_center_world);
+        let point_shadow_casters = create_point_shadow_casters(&lights);
+        process_events(
+            &mut context, 
+            &mut shadow_settings, 
+            &mut ev100, 
+            point_shadow_casters.len(), 
+            &mut timeline_settings, 
+            &camera_frame,
+            &mut clock,
+            &mut rig_settings,
+            &mut environment_settings,
+            &mut show_transparent,
+            &mut preview_shape,
+        );
+        if options.gallery {
+            place_gallery_objects(&mut objects, &preview_meshes[preview_shape.index()]);
+        } else if loaded_meshes.is_empty() {
+            place_preview_objects(&mut objects, &preview_meshes[preview_shape.index()]);
+        }
+        send_to_gpu_uniforms_camera(mesh_shader, &camera, &view_mat);
+        send_to_gpu_uniforms_camera(light_shader, &camera, &view_mat);
+        send_to_gpu_uniforms_light(mesh_shader, &lights, &shadow_casters, &point_shadow_casters);
+        send_to_gpu_uniforms_shadow_settings(mesh_shader, &shadow_settings);
+        send_to_gpu_uniforms_exposure(mesh_shader, ev100);
+        send_to_gpu_uniforms_environment(mesh_shader, &environment_settings);
+        send_to_gpu_uniforms_ambient_lighting(mesh_shader, environment_settings.ambient);
+        send_to_gpu_uniforms_camera(skybox_shader, &camera, &view_mat);
+        send_to_gpu_uniforms_environment(skybox_shader, &environment_settings);
+        send_to_gpu_uniforms_exposure(skybox_shader, ev100);
+
+        // Render the scene depth from each shadow casting light.
+        for (layer, caster) in shadow_casters.iter().enumerate() {
+            if let Err(e) = shadow_maps.bind_layer(layer as u32) {
+                panic!("Could not render shadow map {}. Got error: {}", layer, e);
+            }
+            send_to_gpu_uniforms_shadow(shadow_shader, &caster.projection.light_space_mat);
+            for object in objects.iter() {
+                send_to_gpu_uniforms_mesh(shadow_shader, &object.model_mat);
+                unsafe {
+                    gl::UseProgram(shadow_shader);
+                    gl::BindVertexArray(object.vao);
+                    gl::DrawElements(gl::TRIANGLES, object.index_count as i32, gl::UNSIGNED_INT, ptr::null());
+                }
+            }
+        }
+        for (cube, caster) in point_shadow_casters.iter().enumerate() {
+            for (face, light_space_mat) in caster.light_space_mats.iter().enumerate() {
+                let layer = cube * CUBE_FACES + face;
+                if let Err(e) = point_shadow_maps.bind_layer(layer as u32) {
+                    panic!("Could not render cube shadow map {} face {}. Got error: {}", cube, face, e);
+                }
+                send_to_gpu_uniforms_point_shadow(point_shadow_shader, light_space_mat, &caster.position);
+                for object in objects.iter() {
+                    send_to_gpu_uniforms_mesh(point_shadow_shader, &object.model_mat);
+                    unsafe {
+                        gl::UseProgram(point_shadow_shader);
+                        gl::BindVertexArray(object.vao);
+                        gl::DrawElements(gl::TRIANGLES, object.index_count as i32, gl::UNSIGNED_INT, ptr::null());
+                    }
+                }
+            }
+        }
+        backend::bind_default_framebuffer(&context);
+
+        // Illuminate the scene.
         unsafe {
             gl::ClearBufferfv(gl::COLOR, 0, &CLEAR_COLOR[0] as *const GLfloat);
             gl::ClearBufferfv(gl::DEPTH, 0, &CLEAR_DEPTH[0] as *const GLfloat);
-            gl::Viewport(0, 0, context.width as GLint, context.height as GLint);
-            gl::UseProgram(mesh_shader);
-            gl::BindVertexArray(mesh_vao);
-            gl::DrawArrays(gl::TRIANGLES, 0, mesh.len() as i32);
         }
-        
-        // Render the lights.
-        let light_model_mat = lights[0].kinematics.model_mat() * Matrix4::from_affine_scale(0.2);
-        send_to_gpu_uniforms_mesh(light_shader, &light_model_mat);
-        unsafe {
-            gl::UseProgram(light_shader);
-            gl::BindVertexArray(light_vao);
-            gl::DrawArrays(gl::TRIANGLES, 0, light_mesh.len() as i32);
+        ltc_1_tex.bind(LTC_1_TEXTURE_UNIT);
+        ltc_2_tex.bind(LTC_2_TEXTURE_UNIT);
+        shadow_maps.texture.bind(SHADOW_MAPS_TEXTURE_UNIT);
+        point_shadow_maps.texture.bind(POINT_SHADOW_MAPS_TEXTURE_UNIT);
+        irradiance_tex.bind(IRRADIANCE_MAP_TEXTURE_UNIT);
+        specular_tex.bind(SPECULAR_MAP_TEXTURE_UNIT);
+        for (i, object) in objects.iter().enumerate() {
+            let material = match object.transparent_material {
+                Some(transparent_material) if show_transparent => transparent_material,
+                _ => object.material,
+            };
+            let material = timeline_settings.material(i, &material);
+            send_to_gpu_uniforms_mesh(mesh_shader, &object.model_mat);
+            send_to_gpu_uniforms_material(mesh_shader, &material);
+            unsafe {
+                gl::UseProgram(mesh_shader);
+                gl::BindVertexArray(object.vao);
+                gl::DrawElements(gl::TRIANGLES, object.index_count as i32, gl::UNSIGNED_INT, ptr::null());
+            }
         }
-    
-        let light_model_mat = lights[1].kinematics.model_mat() * Matrix4::from_affine_scale(0.2);
-        send_to_gpu_uniforms_mesh(light_shader, &light_model_mat);
+
+
+        // Fill the background with the environment. The skybox lies on the far plane, so
+        // it only covers the pixels the scene leaves empty.
+        skybox_tex.bind(SKYBOX_TEXTURE_UNIT);
         unsafe {
-            gl::UseProgram(light_shader);
-            gl::BindVertexArray(light_vao);
-            gl::DrawArrays(gl::TRIANGLES, 0, light_mesh.len() as i32);
+            gl::DepthFunc(gl::LEQUAL);
+            gl::UseProgram(skybox_shader);
+            gl::BindVertexArray(skybox_vao);
+            gl::DrawArrays(gl::TRIANGLES, 0, skybox_mesh.len() as i32);
+            gl::DepthFunc(gl::LESS);
         }
         
-        let light_model_mat = lights[2].kinematics.model_mat() * Matrix4::from_affine_scale(0.2);
-        send_to_gpu_uniforms_mesh(light_shader, &light_model_mat);
-        unsafe {
-            gl::UseProgram(light_shader);
-            gl::BindVertexArray(light_vao);
-            gl::DrawArrays(gl::TRIANGLES, 0, light_mesh.len() as i32);
+        // Render the lights. Lights riding along with the camera would block its view.
+        for light in lights.iter().filter(|light| !light.kinematics.follows_camera()) {
+            let light_model_mat = light_model_mat(light);
+            send_to_gpu_uniforms_mesh(light_shader, &light_model_mat);
+            unsafe {
+                gl::UseProgram(light_shader);
+                gl::BindVertexArray(light_vao);
+                gl::DrawArrays(gl::TRIANGLES, 0, light_mesh.len() as i32);
+            }
+        }
+
+        if options.gallery {
+            let label_vertices = gallery_label_vertices(
+                &context, &camera.projection(), &view_mat, &objects, show_transparent
+            );
+            font_atlas_tex.bind(FONT_ATLAS_TEXTURE_UNIT);
+            draw_text(&context, text_shader, text_vao, text_vbo, &label_vertices);
+        }
+
+        if let Some(cube) = shadow_settings.debug_cube {
+            draw_shadow_debug_view(&context, shadow_debug_shader, shadow_debug_vao, &point_shadow_maps, cube);
         }
 
         context.window.swap_buffers();
     }
 
+    if let (Some(recording), Some(path)) = (recording, options.record_path) {
+        match recording.save(&path) {
+            Ok(()) => info!("Saved {} s of camera input to {}", recording.duration(), path),
+            Err(e) => info!("Could not save the input recording to {}. Got error: {}", path, e),
+        }
+    }
+
     info!("END LOG");
 }
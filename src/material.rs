@@ -5,12 +5,52 @@ use cglinalg::{
 use std::collections::hash_map::HashMap;
 
 
+/// The largest specular exponent in the material table. The raw table stores the specular
+/// exponents as fractions of it.
+pub const MAX_SPECULAR_EXPONENT: f32 = 128.0;
+
+/// The optical properties of a transparent material that bends the light passing
+/// through it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Refraction<S> {
+    /// The index of refraction for green light.
+    pub index_of_refraction: S,
+    /// The index of refraction for blue light minus the index of refraction for red 
+    /// light. The larger the dispersion, the further the material splits white light 
+    /// into colors.
+    pub dispersion: S,
+    /// The fraction of the light of each color that passes through the material.
+    pub transmittance: Vector3<S>,
+}
+
+impl<S> Refraction<S> where S: ScalarFloat {
+    pub fn new(index_of_refraction: S, dispersion: S, transmittance: Vector3<S>) -> Refraction<S> {
+        Refraction {
+            index_of_refraction: index_of_refraction,
+            dispersion: dispersion,
+            transmittance: transmittance,
+        }
+    }
+
+    /// The indices of refraction for red, green, and blue light.
+    pub fn indices_of_refraction(&self) -> Vector3<S> {
+        let half_dispersion = self.dispersion / (S::one() + S::one());
+        Vector3::new(
+            self.index_of_refraction - half_dispersion,
+            self.index_of_refraction,
+            self.index_of_refraction + half_dispersion
+        )
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Material<S> {
     pub ambient: Vector3<S>,
     pub diffuse: Vector3<S>,
     pub specular: Vector3<S>,
     pub specular_exponent: S,
+    /// How light passes through the material, or `None` for an opaque material.
+    pub refraction: Option<Refraction<S>>,
 }
 
 impl<S> Material<S> where S: ScalarFloat {
@@ -20,10 +60,46 @@ impl<S> Material<S> where S: ScalarFloat {
             diffuse: diffuse,
             specular: specular,
             specular_exponent: specular_exponent,
+            refraction: None,
+        }
+    }
+
+    /// A transparent version of the material. Light passes through the material instead
+    /// of scattering off of it, so it keeps only its specular highlights.
+    fn transparent(&self, refraction: Refraction<S>) -> Material<S> {
+        Material {
+            ambient: Vector3::zero(),
+            diffuse: Vector3::zero(),
+            specular: self.specular,
+            specular_exponent: self.specular_exponent,
+            refraction: Some(refraction),
         }
     }
 }
 
+impl Material<f32> {
+    /// The fraction of the light arriving from the direction of mirror reflection that the
+    /// material reflects, for each color channel. The Blinn-Phong model has no such term,
+    /// so this scales the specular color by the glossiness of the material. Polished 
+    /// metals such as chrome reflect their surroundings clearly, while rubber barely 
+    /// reflects at all.
+    pub fn reflectivity(&self) -> Vector3<f32> {
+        let glossiness = (self.specular_exponent / MAX_SPECULAR_EXPONENT).max(0.0).min(1.0);
+
+        self.specular * glossiness
+    }
+}
+
+/// The names of the materials of the `teapots.c` demo in the order it lays them out, 
+/// column by column from the top left. The columns hold the gems, the metals, the 
+/// plastics, and the rubbers.
+pub const TEAPOTS_MATERIALS: [&str; 24] = [
+    "emerald", "jade", "obsidian", "pearl", "ruby", "turquoise",
+    "brass", "bronze", "chrome", "copper", "gold", "silver",
+    "black plastic", "cyan plastic", "green plastic", "red plastic", "white plastic", "yellow plastic",
+    "black rubber", "cyan rubber", "green rubber", "red rubber", "white rubber", "yellow rubber",
+];
+
 /// A table of materials for the Blinn-Phong shading model.
 /// There material parameters are derived from the OpenGL `teapots.c` demo, 
 /// c.f. `Silicon Graphics, Inc., 1994, Mark J. Kilgard` and the table found
@@ -179,16 +255,45 @@ fn raw_material_table() -> HashMap<&'static str, Material<f32>> {
     materials
 }
 
+/// The color of the light passing through a stone with the diffuse color `color`. The
+/// brightest channel passes through unhindered.
+fn tint(color: Vector3<f32>) -> Vector3<f32> {
+    let brightest = color[0].max(color[1]).max(color[2]);
+    color * (1.0 / brightest)
+}
+
+/// Add transparent versions of the gem materials to the table, along with clear glass and
+/// diamond. The indices of refraction and dispersions are those of the real stones. 
+/// Turquoise is opaque in nature, so its dispersion is an estimate.
+fn add_transparent_materials(materials: &mut HashMap<&'static str, Material<f32>>) {
+    let gems = [
+        ("clear emerald", "emerald", 1.577, 0.014),
+        ("clear ruby", "ruby", 1.766, 0.018),
+        ("clear turquoise", "turquoise", 1.62, 0.012),
+    ];
+    for &(name, opaque_name, index_of_refraction, dispersion) in gems.iter() {
+        let opaque = materials[opaque_name];
+        let refraction = Refraction::new(index_of_refraction, dispersion, tint(opaque.diffuse));
+        materials.insert(name, opaque.transparent(refraction));
+    }
+
+    let clear = Material::new(Vector3::zero(), Vector3::zero(), Vector3::new(1.0, 1.0, 1.0), 1.0);
+    let white = Vector3::new(1.0, 1.0, 1.0);
+    materials.insert("glass", clear.transparent(Refraction::new(1.52, 0.013, white)));
+    materials.insert("diamond", clear.transparent(Refraction::new(2.417, 0.044, white)));
+}
+
 /// Create a table of materials for the Blinn-Phong shading model that can be sent to the 
 /// GPU directly.
 pub fn material_table() -> HashMap<&'static str, Material<f32>> {
-    raw_material_table()
+    let mut materials = raw_material_table();
+    add_transparent_materials(&mut materials);
+
+    materials
         .iter()
-        .map(|(name, material)| { (*name, Material::new(
-            material.ambient, 
-            material.diffuse, 
-            material.specular, 
-            128.0 * material.specular_exponent
-        ))})
+        .map(|(name, material)| { (*name, Material {
+            specular_exponent: MAX_SPECULAR_EXPONENT * material.specular_exponent,
+            ..*material
+        })})
         .collect::<HashMap<&'static str, Material<f32>>>()
 }
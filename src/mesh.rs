@@ -0,0 +1,303 @@
+use crate::vector::{
+    cross,
+    dot,
+    length,
+    normalize,
+    sub,
+};
+use mini_obj::ObjMesh;
+use std::collections::hash_map::HashMap;
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::mem;
+use std::path::Path;
+
+
+/// The crease angle in degrees of the normals generated for meshes without normals.
+pub const DEFAULT_CREASE_ANGLE_DEGREES: f32 = 45.0;
+
+
+/// Load a Wavefront `.obj` mesh from a file as a list of triangles. Meshes without
+/// normals get smooth normals with the default crease angle.
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<ObjMesh, MeshError> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|e| {
+        MeshError::new(path, format!("Could not open the file: {}", e))
+    })?;
+    let mut reader = io::BufReader::new(file);
+    let mesh = mini_obj::load(&mut reader).map_err(|e| {
+        MeshError::new(path, format!("Could not parse the file: {}", e))
+    })?;
+
+    if mesh.points.len() == 0 {
+        return Err(MeshError::new(path, String::from("The mesh has no triangles.")));
+    }
+    if mesh.points.len() % 3 != 0 {
+        return Err(MeshError::new(path, format!(
+            "The mesh has {} vertices, which is not a whole number of triangles.", mesh.points.len()
+        )));
+    }
+    let points = points(&mesh);
+    if points.iter().any(|point| point.iter().any(|coordinate| !coordinate.is_finite())) {
+        return Err(MeshError::new(path, String::from("The mesh has a vertex that is not a finite number.")));
+    }
+
+    if has_normals(&mesh) {
+        Ok(mesh)
+    } else {
+        Ok(with_smooth_normals(&mesh, DEFAULT_CREASE_ANGLE_DEGREES))
+    }
+}
+
+/// The positions of the vertices of a mesh.
+pub fn points(mesh: &ObjMesh) -> Vec<[f32; 3]> {
+    (0..mesh.points.len()).map(|i| mesh.points[i]).collect()
+}
+
+/// The texture coordinates of the vertices of a mesh, or nothing if the mesh does not
+/// have texture coordinates for every vertex.
+pub fn tex_coords(mesh: &ObjMesh) -> Vec<[f32; 2]> {
+    if mesh.tex_coords.len() != mesh.points.len() {
+        return vec![];
+    }
+
+    (0..mesh.tex_coords.len()).map(|i| mesh.tex_coords[i]).collect()
+}
+
+/// The normals of the vertices of a mesh.
+pub fn normals(mesh: &ObjMesh) -> Vec<[f32; 3]> {
+    (0..mesh.normals.len()).map(|i| mesh.normals[i]).collect()
+}
+
+/// Whether every vertex of a mesh has a normal. Files without normals leave them out
+/// or fill them with zeros, depending on the exporter.
+fn has_normals(mesh: &ObjMesh) -> bool {
+    mesh.normals.len() == mesh.points.len() &&
+        (0..mesh.normals.len()).all(|i| mesh.normals[i] != [0.0, 0.0, 0.0])
+}
+
+/// Give a mesh new normals that are smooth across the edges where its faces meet at 
+/// less than `crease_angle_degrees` degrees, and sharp across the other edges.
+pub fn with_smooth_normals(mesh: &ObjMesh, crease_angle_degrees: f32) -> ObjMesh {
+    let points = points(mesh);
+    let normals = smooth_normals(&points, crease_angle_degrees);
+
+    ObjMesh::new(points, tex_coords(mesh), normals)
+}
+
+/// The normal at each vertex of a list of triangles, averaged over the triangles sharing
+/// the vertex's position. Each triangle counts in proportion to its area and its angle 
+/// at the vertex, so that neither long thin triangles nor fans of many small triangles 
+/// pull the normal towards themselves. A triangle only counts towards the normals of 
+/// another triangle's vertices when the two meet at less than the crease angle, which
+/// splits the vertices along the crease into a vertex for each side.
+pub fn smooth_normals(points: &[[f32; 3]], crease_angle_degrees: f32) -> Vec<[f32; 3]> {
+    let cos_crease_angle = crease_angle_degrees.to_radians().cos();
+    let triangle_count = points.len() / 3;
+    let mut face_normals = Vec::with_capacity(triangle_count);
+    let mut weights = Vec::with_capacity(points.len());
+    for triangle in points.chunks(3) {
+        let normal = face_normal(triangle[0], triangle[1], triangle[2]);
+        let area = 0.5 * length(normal);
+        face_normals.push(normalize(normal).unwrap_or([0.0, 0.0, 0.0]));
+        for corner in 0..3 {
+            let angle = corner_angle(triangle[corner], triangle[(corner + 1) % 3], triangle[(corner + 2) % 3]);
+            weights.push(area * angle);
+        }
+    }
+
+    // The vertices of every triangle sharing each position.
+    let mut corners_at: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+    for (vertex, point) in points.iter().enumerate() {
+        let key = [bits(point[0]), bits(point[1]), bits(point[2])];
+        corners_at.entry(key).or_default().push(vertex);
+    }
+
+    let mut normals = vec![[0.0, 0.0, 0.0]; points.len()];
+    for corners in corners_at.values() {
+        for &vertex in corners.iter() {
+            let face_normal = face_normals[vertex / 3];
+            let mut normal = [0.0, 0.0, 0.0];
+            for &other in corners.iter() {
+                let other_normal = face_normals[other / 3];
+                if other == vertex || dot(face_normal, other_normal) >= cos_crease_angle {
+                    for axis in 0..3 {
+                        normal[axis] += weights[other] * other_normal[axis];
+                    }
+                }
+            }
+            normals[vertex] = normalize(normal)
+                .or_else(|| normalize(face_normal))
+                .unwrap_or([0.0, 1.0, 0.0]);
+        }
+    }
+
+    normals
+}
+
+/// The interior angle in radians of a triangle at `corner`, whose neighbors are `next`
+/// and `previous`.
+fn corner_angle(corner: [f32; 3], next: [f32; 3], previous: [f32; 3]) -> f32 {
+    let edge1 = sub(next, corner);
+    let edge2 = sub(previous, corner);
+
+    length(cross(edge1, edge2)).atan2(dot(edge1, edge2))
+}
+
+/// The normal of a triangle, whose length is twice the area of the triangle.
+pub fn face_normal(p0: [f32; 3], p1: [f32; 3], p2: [f32; 3]) -> [f32; 3] {
+    let edge1 = sub(p1, p0);
+    let edge2 = sub(p2, p0);
+
+    cross(edge1, edge2)
+}
+
+/// The smallest and largest coordinates of the vertices of a mesh along each axis.
+pub fn bounds(mesh: &ObjMesh) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for i in 0..mesh.points.len() {
+        let point = mesh.points[i];
+        for axis in 0..3 {
+            min[axis] = f32::min(min[axis], point[axis]);
+            max[axis] = f32::max(max[axis], point[axis]);
+        }
+    }
+
+    (min, max)
+}
+
+/// Move a mesh so that its bounding box is centered on the origin, and scale it so
+/// that it fits in the same unit cube as the box mesh.
+pub fn fit_to_unit_cube(mesh: &ObjMesh) -> ObjMesh {
+    let (min, max) = bounds(mesh);
+    let center = [0.5 * (min[0] + max[0]), 0.5 * (min[1] + max[1]), 0.5 * (min[2] + max[2])];
+    let extent = f32::max(max[0] - min[0], f32::max(max[1] - min[1], max[2] - min[2]));
+    let scale = if extent > 0.0 { 1.0 / extent } else { 1.0 };
+    let points = points(mesh).iter()
+        .map(|point| [
+            scale * (point[0] - center[0]),
+            scale * (point[1] - center[1]),
+            scale * (point[2] - center[2]),
+        ])
+        .collect();
+
+    // A uniform scale and a translation leave the normals unchanged.
+    ObjMesh::new(points, tex_coords(mesh), normals(mesh))
+}
+
+/// A mesh whose triangles share their vertices through a list of indices, three per 
+/// triangle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexedMesh {
+    pub points: Vec<[f32; 3]>,
+    /// The texture coordinates of the vertices, or nothing if the mesh has none.
+    pub tex_coords: Vec<[f32; 2]>,
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+}
+
+impl IndexedMesh {
+    /// Merge the vertices of a list of triangles that have the same position, normal, 
+    /// and texture coordinates. Vertices are only merged when they are exactly equal, so 
+    /// the mesh looks the same as before. Meshes without normals get smooth normals with 
+    /// the default crease angle first.
+    pub fn from_obj_mesh(mesh: &ObjMesh) -> IndexedMesh {
+        let points = points(mesh);
+        let tex_coords = tex_coords(mesh);
+        let normals = if has_normals(mesh) {
+            normals(mesh)
+        } else {
+            smooth_normals(&points, DEFAULT_CREASE_ANGLE_DEGREES)
+        };
+        let mut indexed_mesh = IndexedMesh {
+            points: vec![],
+            tex_coords: vec![],
+            normals: vec![],
+            indices: Vec::with_capacity(points.len()),
+        };
+        let mut vertex_indices: HashMap<[u32; 8], u32> = HashMap::new();
+        for i in 0..points.len() {
+            let tex_coord = if tex_coords.is_empty() { [0.0, 0.0] } else { tex_coords[i] };
+            let key = vertex_key(points[i], normals[i], tex_coord);
+            let index = *vertex_indices.entry(key).or_insert_with(|| {
+                indexed_mesh.points.push(points[i]);
+                indexed_mesh.normals.push(normals[i]);
+                if !tex_coords.is_empty() {
+                    indexed_mesh.tex_coords.push(tex_coord);
+                }
+
+                (indexed_mesh.points.len() - 1) as u32
+            });
+            indexed_mesh.indices.push(index);
+        }
+
+        indexed_mesh
+    }
+
+    /// The number of indices of the mesh, which is three times the number of triangles.
+    #[inline]
+    pub fn index_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// The number of distinct vertices of the mesh.
+    #[inline]
+    pub fn vertex_count(&self) -> usize {
+        self.points.len()
+    }
+
+    /// The number of bytes of the positions, normals, and indices of the mesh, which is
+    /// what the demo sends to the GPU.
+    pub fn len_bytes(&self) -> usize {
+        self.vertex_count() * mem::size_of::<[f32; 6]>() + self.index_count() * mem::size_of::<u32>()
+    }
+
+    /// The number of bytes of the positions and normals of the same mesh as a list of 
+    /// triangles without indices.
+    pub fn unindexed_len_bytes(&self) -> usize {
+        self.index_count() * mem::size_of::<[f32; 6]>()
+    }
+}
+
+/// The bit patterns of the attributes of a vertex. Both zeros compare equal as floating 
+/// point numbers, so they are merged too.
+fn vertex_key(point: [f32; 3], normal: [f32; 3], tex_coord: [f32; 2]) -> [u32; 8] {
+    [
+        bits(point[0]), bits(point[1]), bits(point[2]),
+        bits(normal[0]), bits(normal[1]), bits(normal[2]),
+        bits(tex_coord[0]), bits(tex_coord[1]),
+    ]
+}
+
+/// The bit pattern of a coordinate, with both zeros the same.
+#[inline]
+pub(crate) fn bits(value: f32) -> u32 {
+    if value == 0.0 { 0 } else { value.to_bits() }
+}
+
+/// An error from loading a mesh.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MeshError {
+    path: String,
+    message: String,
+}
+
+impl MeshError {
+    fn new(path: &Path, message: String) -> MeshError {
+        MeshError {
+            path: format!("{}", path.display()),
+            message: message,
+        }
+    }
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Could not load the mesh `{}`. {}", self.path, self.message)
+    }
+}
+
+impl error::Error for MeshError {}
@@ -0,0 +1,323 @@
+use crate::mesh::IndexedMesh;
+use crate::vector::{
+    add,
+    cross,
+    dot,
+    scale,
+    sub,
+};
+use std::cmp::Ordering;
+
+
+/// The number of vertices in the first in, first out post-transform vertex cache the
+/// average cache miss ratio is measured with, which is typical of current GPUs.
+pub const VERTEX_CACHE_SIZE: usize = 16;
+/// The number of vertices in the least recently used cache Forsyth's algorithm scores
+/// vertices with.
+const FORSYTH_CACHE_SIZE: usize = 32;
+// The weights of Forsyth's vertex score.
+const FORSYTH_CACHE_DECAY_POWER: f32 = 1.5;
+const FORSYTH_LAST_TRIANGLE_SCORE: f32 = 0.75;
+const FORSYTH_VALENCE_BOOST_SCALE: f32 = 2.0;
+const FORSYTH_VALENCE_BOOST_POWER: f32 = 0.5;
+/// How much worse than the mesh as a whole the average cache miss ratio of a cluster of
+/// triangles can be when splitting the mesh into clusters to sort for less overdraw.
+const OVERDRAW_CACHE_THRESHOLD: f32 = 1.05;
+
+
+/// Reorder a mesh for drawing: the triangles for the post-transform vertex cache first,
+/// then clusters of triangles to draw the ones facing out from the mesh before the ones
+/// they cover, then the vertices in the order the triangles use them. The mesh looks the
+/// same as before.
+pub fn optimize(mesh: &IndexedMesh) -> IndexedMesh {
+    let indices = optimize_vertex_cache(&mesh.indices, mesh.vertex_count());
+    let indices = optimize_overdraw(&indices, &mesh.points, OVERDRAW_CACHE_THRESHOLD);
+
+    optimize_vertex_fetch(&IndexedMesh {
+        points: mesh.points.clone(),
+        tex_coords: mesh.tex_coords.clone(),
+        normals: mesh.normals.clone(),
+        indices: indices,
+    })
+}
+
+/// The average number of vertices per triangle that miss a first in, first out vertex
+/// cache of `cache_size` vertices. It ranges from 3 when the triangles share no vertices
+/// down to about 0.5 for the best orders of a large regular mesh.
+pub fn average_cache_miss_ratio(indices: &[u32], vertex_count: usize, cache_size: usize) -> f32 {
+    if indices.is_empty() {
+        return 0.0;
+    }
+    let mut cache = FifoCache::new(vertex_count, cache_size);
+    let misses: usize = indices.chunks(3).map(|triangle| cache.insert_triangle(triangle)).sum();
+
+    misses as f32 / (indices.len() / 3) as f32
+}
+
+/// Reorder the triangles of a mesh so that they reuse the vertices the GPU transformed
+/// recently, following Tom Forsyth's linear speed vertex cache optimization.
+pub fn optimize_vertex_cache(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    let triangle_count = indices.len() / 3;
+    let mut vertex_triangles = vec![vec![]; vertex_count];
+    for (triangle, vertices) in indices.chunks(3).enumerate() {
+        for &vertex in vertices.iter() {
+            vertex_triangles[vertex as usize].push(triangle);
+        }
+    }
+    let mut cache_positions: Vec<Option<usize>> = vec![None; vertex_count];
+    let mut vertex_scores: Vec<f32> = vertex_triangles.iter()
+        .map(|triangles| forsyth_vertex_score(None, triangles.len()))
+        .collect();
+    let mut triangle_scores: Vec<f32> = indices.chunks(3)
+        .map(|vertices| vertices.iter().map(|&vertex| vertex_scores[vertex as usize]).sum())
+        .collect();
+    let mut emitted = vec![false; triangle_count];
+
+    let mut optimized_indices = Vec::with_capacity(indices.len());
+    let mut cache: Vec<u32> = Vec::with_capacity(FORSYTH_CACHE_SIZE + 3);
+    let mut best_triangle = (0..triangle_count)
+        .max_by(|&t1, &t2| triangle_scores[t1].partial_cmp(&triangle_scores[t2]).unwrap_or(Ordering::Equal));
+    // Where to look for a triangle to start over from when the cache runs out of them.
+    let mut next_unemitted = 0;
+    while let Some(triangle) = best_triangle {
+        let vertices = &indices[(3 * triangle)..(3 * triangle + 3)];
+        optimized_indices.extend_from_slice(vertices);
+        emitted[triangle] = true;
+        for &vertex in vertices.iter() {
+            let triangles = &mut vertex_triangles[vertex as usize];
+            if let Some(position) = triangles.iter().position(|&other| other == triangle) {
+                triangles.swap_remove(position);
+            }
+        }
+
+        // Move the vertices of the triangle to the front of the cache.
+        let mut new_cache: Vec<u32> = vertices.to_vec();
+        new_cache.extend(cache.iter().cloned().filter(|vertex| !vertices.contains(vertex)));
+        for &evicted in new_cache.iter().skip(FORSYTH_CACHE_SIZE) {
+            cache_positions[evicted as usize] = None;
+        }
+        new_cache.truncate(FORSYTH_CACHE_SIZE);
+        for (position, &vertex) in new_cache.iter().enumerate() {
+            cache_positions[vertex as usize] = Some(position);
+        }
+        let touched_vertices: Vec<u32> = cache.iter()
+            .chain(vertices.iter())
+            .cloned()
+            .collect();
+        cache = new_cache;
+
+        // Only the scores of the vertices that moved in or out of the cache change.
+        for &vertex in touched_vertices.iter() {
+            let vertex = vertex as usize;
+            vertex_scores[vertex] = forsyth_vertex_score(cache_positions[vertex], vertex_triangles[vertex].len());
+        }
+        best_triangle = None;
+        let mut best_score = f32::MIN;
+        for &vertex in touched_vertices.iter() {
+            for &other in vertex_triangles[vertex as usize].iter() {
+                let score = indices[(3 * other)..(3 * other + 3)].iter()
+                    .map(|&other_vertex| vertex_scores[other_vertex as usize])
+                    .sum();
+                triangle_scores[other] = score;
+                if cache_positions[vertex as usize].is_some() && score > best_score {
+                    best_score = score;
+                    best_triangle = Some(other);
+                }
+            }
+        }
+        if best_triangle.is_none() {
+            while next_unemitted < triangle_count && emitted[next_unemitted] {
+                next_unemitted += 1;
+            }
+            if next_unemitted < triangle_count {
+                best_triangle = Some(next_unemitted);
+            }
+        }
+    }
+
+    optimized_indices
+}
+
+/// How much Forsyth's algorithm wants to draw a triangle using a vertex next. Vertices
+/// high in the cache score highly, and so do vertices with few triangles left to draw,
+/// so that the algorithm does not leave lone triangles behind.
+fn forsyth_vertex_score(cache_position: Option<usize>, remaining_triangles: usize) -> f32 {
+    if remaining_triangles == 0 {
+        return -1.0;
+    }
+    let cache_score = match cache_position {
+        Some(position) if position < 3 => FORSYTH_LAST_TRIANGLE_SCORE,
+        Some(position) => {
+            let scale = 1.0 / (FORSYTH_CACHE_SIZE - 3) as f32;
+            (1.0 - (position - 3) as f32 * scale).powf(FORSYTH_CACHE_DECAY_POWER)
+        }
+        None => 0.0,
+    };
+    let valence_boost = FORSYTH_VALENCE_BOOST_SCALE * (remaining_triangles as f32).powf(-FORSYTH_VALENCE_BOOST_POWER);
+
+    cache_score + valence_boost
+}
+
+/// Reorder clusters of triangles so that the clusters facing out from the center of the
+/// mesh come first, following Sander et al. Those clusters tend to cover the others,
+/// so the GPU shades fewer fragments it later overwrites. The clusters are cut where
+/// the average cache miss ratio of the cluster so far is within `threshold` of the
+/// mesh's, so the order stays almost as good for the vertex cache.
+pub fn optimize_overdraw(indices: &[u32], points: &[[f32; 3]], threshold: f32) -> Vec<u32> {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return vec![];
+    }
+    let target_ratio = average_cache_miss_ratio(indices, points.len(), VERTEX_CACHE_SIZE);
+
+    let mut cluster_starts = vec![0];
+    let mut cache = FifoCache::new(points.len(), VERTEX_CACHE_SIZE);
+    let mut misses = 0;
+    let mut triangles = 0;
+    for (triangle, vertices) in indices.chunks(3).enumerate() {
+        let triangle_misses = cache.insert_triangle(vertices);
+        // The cache starts over anyway where a triangle shares no vertices with it.
+        if triangle_misses == 3 && triangles > 0 {
+            cluster_starts.push(triangle);
+            misses = 0;
+            triangles = 0;
+        }
+        misses += triangle_misses;
+        triangles += 1;
+        if misses as f32 <= threshold * target_ratio * triangles as f32 && triangle + 1 < triangle_count {
+            cluster_starts.push(triangle + 1);
+            cache.clear();
+            misses = 0;
+            triangles = 0;
+        }
+    }
+    cluster_starts.push(triangle_count);
+
+    let centroid = mesh_centroid(indices, points);
+    let mut clusters: Vec<(f32, &[u32])> = cluster_starts.windows(2)
+        .map(|bounds| {
+            let cluster = &indices[(3 * bounds[0])..(3 * bounds[1])];
+            (cluster_facing(cluster, points, centroid), cluster)
+        })
+        .collect();
+    // The sort is stable, so clusters facing the same way keep their order.
+    clusters.sort_by(|cluster1, cluster2| cluster2.0.partial_cmp(&cluster1.0).unwrap_or(Ordering::Equal));
+
+    clusters.iter().flat_map(|cluster| cluster.1.iter().cloned()).collect()
+}
+
+/// The area weighted centroid of the triangles of a mesh.
+fn mesh_centroid(indices: &[u32], points: &[[f32; 3]]) -> [f32; 3] {
+    let mut weighted_sum = [0.0, 0.0, 0.0];
+    let mut total_area = 0.0;
+    for vertices in indices.chunks(3) {
+        let (area, centroid) = triangle_area_centroid(vertices, points);
+        weighted_sum = add(weighted_sum, scale(centroid, area));
+        total_area += area;
+    }
+
+    if total_area > 0.0 { scale(weighted_sum, 1.0 / total_area) } else { [0.0, 0.0, 0.0] }
+}
+
+/// How far out from the center of the mesh a cluster of triangles lies along the
+/// direction it faces.
+fn cluster_facing(cluster: &[u32], points: &[[f32; 3]], mesh_centroid: [f32; 3]) -> f32 {
+    let mut weighted_sum = [0.0, 0.0, 0.0];
+    let mut total_area = 0.0;
+    let mut normal = [0.0, 0.0, 0.0];
+    for vertices in cluster.chunks(3) {
+        let (area, centroid) = triangle_area_centroid(vertices, points);
+        weighted_sum = add(weighted_sum, scale(centroid, area));
+        total_area += area;
+        let p0 = points[vertices[0] as usize];
+        normal = add(normal, cross(sub(points[vertices[1] as usize], p0), sub(points[vertices[2] as usize], p0)));
+    }
+    let normal_length = dot(normal, normal).sqrt();
+    if total_area <= 0.0 || normal_length <= 0.0 {
+        return 0.0;
+    }
+    let centroid = scale(weighted_sum, 1.0 / total_area);
+
+    dot(sub(centroid, mesh_centroid), normal) / normal_length
+}
+
+/// The area and centroid of a triangle.
+fn triangle_area_centroid(vertices: &[u32], points: &[[f32; 3]]) -> (f32, [f32; 3]) {
+    let p0 = points[vertices[0] as usize];
+    let p1 = points[vertices[1] as usize];
+    let p2 = points[vertices[2] as usize];
+    let normal = cross(sub(p1, p0), sub(p2, p0));
+    let area = 0.5 * dot(normal, normal).sqrt();
+
+    (area, scale(add(add(p0, p1), p2), 1.0 / 3.0))
+}
+
+/// Renumber the vertices of a mesh in the order its triangles first use them, so the GPU
+/// reads the vertex buffers front to back. Vertices no triangle uses are dropped.
+pub fn optimize_vertex_fetch(mesh: &IndexedMesh) -> IndexedMesh {
+    let mut new_indices: Vec<Option<u32>> = vec![None; mesh.vertex_count()];
+    let mut optimized_mesh = IndexedMesh {
+        points: vec![],
+        tex_coords: vec![],
+        normals: vec![],
+        indices: Vec::with_capacity(mesh.index_count()),
+    };
+    for &index in mesh.indices.iter() {
+        let vertex = index as usize;
+        let new_index = match new_indices[vertex] {
+            Some(new_index) => new_index,
+            None => {
+                optimized_mesh.points.push(mesh.points[vertex]);
+                optimized_mesh.normals.push(mesh.normals[vertex]);
+                if !mesh.tex_coords.is_empty() {
+                    optimized_mesh.tex_coords.push(mesh.tex_coords[vertex]);
+                }
+                let new_index = (optimized_mesh.points.len() - 1) as u32;
+                new_indices[vertex] = Some(new_index);
+                new_index
+            }
+        };
+        optimized_mesh.indices.push(new_index);
+    }
+
+    optimized_mesh
+}
+
+/// A first in, first out vertex cache, the way GPUs cache transformed vertices.
+struct FifoCache {
+    /// The time each vertex entered the cache.
+    timestamps: Vec<usize>,
+    time: usize,
+    size: usize,
+}
+
+impl FifoCache {
+    fn new(vertex_count: usize, size: usize) -> FifoCache {
+        FifoCache {
+            timestamps: vec![0; vertex_count],
+            time: size + 1,
+            size: size,
+        }
+    }
+
+    /// Draw a triangle, returning the number of its vertices that missed the cache.
+    fn insert_triangle(&mut self, vertices: &[u32]) -> usize {
+        let mut misses = 0;
+        for &vertex in vertices.iter() {
+            let vertex = vertex as usize;
+            if self.time - self.timestamps[vertex] > self.size {
+                self.timestamps[vertex] = self.time;
+                self.time += 1;
+                misses += 1;
+            }
+        }
+
+        misses
+    }
+
+    /// Empty the cache.
+    fn clear(&mut self) {
+        self.time += self.size + 1;
+    }
+}
+
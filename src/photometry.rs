@@ -0,0 +1,91 @@
+use cglinalg::{
+    Vector3,
+};
+
+
+/// The contributions of the red, green, and blue components of a linear sRGB color
+/// to its luminance.
+const LUMINANCE_WEIGHTS: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// The amount of light a point or spot light emits.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LuminousPower {
+    /// The total luminous flux the light emits in lumens.
+    Lumens(f32),
+    /// The luminous intensity of the light in candela, which is the luminous flux
+    /// emitted per steradian.
+    Candela(f32),
+}
+
+/// Compute the linear sRGB color of a black body radiator at the temperature `kelvin`.
+/// The color is normalized to unit luminance, so scaling it by a photometric quantity
+/// yields a color with that luminous quantity. The chromaticity follows the cubic spline
+/// fit of the Planckian locus, c.f. `Kim, Kim, Lee, Jung, Design of Advanced Color
+/// Temperature Control System for HDTV Applications, 2002`, which is valid between
+/// 1667 K and 25000 K. Temperatures outside of that range are clamped to it.
+pub fn color_temperature_to_rgb(kelvin: f32) -> Vector3<f32> {
+    let t = kelvin.max(1667.0).min(25000.0);
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let x = if t <= 4000.0 {
+        -0.2661239e9 / t3 - 0.2343589e6 / t2 + 0.8776956e3 / t + 0.17991
+    } else {
+        -3.0258468e9 / t3 + 2.107038e6 / t2 + 0.2226347e3 / t + 0.24039
+    };
+    let x2 = x * x;
+    let x3 = x2 * x;
+    let y = if t <= 2222.0 {
+        -1.1063814 * x3 - 1.3481102 * x2 + 2.1855583 * x - 0.20219684
+    } else if t <= 4000.0 {
+        -0.9549476 * x3 - 1.3741859 * x2 + 2.09137 * x - 0.16748866
+    } else {
+        3.081758 * x3 - 5.873387 * x2 + 3.7511299 * x - 0.37001482
+    };
+
+    // Convert the chromaticity to CIE XYZ with unit luminance, then to linear sRGB.
+    // Warm temperatures lie outside of the sRGB gamut, so their negative components
+    // are clipped.
+    let cie_x = x / y;
+    let cie_y = 1.0;
+    let cie_z = (1.0 - x - y) / y;
+    let r = 3.2404542 * cie_x - 1.5371385 * cie_y - 0.4985314 * cie_z;
+    let g = -0.969266 * cie_x + 1.8760108 * cie_y + 0.041556 * cie_z;
+    let b = 0.0556434 * cie_x - 0.2040259 * cie_y + 1.0572252 * cie_z;
+    let rgb = Vector3::new(r.max(0.0), g.max(0.0), b.max(0.0));
+
+    rgb * (1.0 / luminance(&rgb))
+}
+
+/// Compute the luminance of a linear sRGB color.
+pub fn luminance(rgb: &Vector3<f32>) -> f32 {
+    LUMINANCE_WEIGHTS[0] * rgb[0] + LUMINANCE_WEIGHTS[1] * rgb[1] + LUMINANCE_WEIGHTS[2] * rgb[2]
+}
+
+/// Compute the luminous intensity in candela of a light emitting `power` uniformly
+/// over a cone with the half-angle `half_angle` radians. A point light emits over
+/// the whole sphere, whose half-angle is `pi`.
+pub fn luminous_intensity(power: LuminousPower, half_angle: f32) -> f32 {
+    match power {
+        LuminousPower::Lumens(lumens) => {
+            let solid_angle = 2.0 * std::f32::consts::PI * (1.0 - half_angle.cos());
+            lumens / solid_angle
+        }
+        LuminousPower::Candela(candela) => candela,
+    }
+}
+
+/// Compute the exposure value at ISO 100 of a physical camera with the f-number
+/// `aperture`, the shutter time `shutter_time` in seconds, and the sensitivity `iso`.
+pub fn ev100_from_camera(aperture: f32, shutter_time: f32, iso: f32) -> f32 {
+    f32::log2((aperture * aperture) / shutter_time * (100.0 / iso))
+}
+
+/// Compute the factor scaling luminance in nits to display values for a camera with the
+/// exposure value `ev100`. The factor maps the luminance that saturates the camera's
+/// sensor to one, c.f. `Lagarde, de Rousiers, Moving Frostbite to Physically Based
+/// Rendering, 2014`.
+pub fn exposure_from_ev100(ev100: f32) -> f32 {
+    let max_luminance = 1.2 * f32::powf(2.0, ev100);
+
+    1.0 / max_luminance
+}
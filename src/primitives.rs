@@ -0,0 +1,273 @@
+use crate::mesh;
+use crate::vector::{
+    add,
+    dot,
+    length,
+    normalize,
+    sub,
+};
+use mini_obj::ObjMesh;
+use std::collections::hash_map::HashMap;
+use std::f32::consts::PI;
+
+
+/// Create a sphere from rings of constant latitude. There are `slices` segments around
+/// the vertical axis and `stacks` segments from the south pole to the north pole.
+pub fn uv_sphere(radius: f32, slices: usize, stacks: usize) -> ObjMesh {
+    assert!(slices >= 3 && stacks >= 2, "A sphere needs at least 3 slices and 2 stacks.");
+    let mut builder = MeshBuilder::new();
+    builder.surface(slices, stacks, |u, v| {
+        let longitude = 2.0 * PI * u;
+        let latitude = PI * (v - 0.5);
+        let normal = [latitude.cos() * longitude.cos(), latitude.sin(), -latitude.cos() * longitude.sin()];
+        let point = [radius * normal[0], radius * normal[1], radius * normal[2]];
+
+        (point, normal)
+    });
+
+    builder.build()
+}
+
+/// Create a sphere by splitting each triangle of an icosahedron into four `subdivisions`
+/// times. The triangles are spread far more evenly over the sphere than those of a UV
+/// sphere with the same number of triangles.
+pub fn icosphere(radius: f32, subdivisions: usize) -> ObjMesh {
+    let t = 0.5 * (1.0 + 5_f32.sqrt());
+    let mut vertices: Vec<[f32; 3]> = vec![
+        [-1.0,  t,  0.0], [ 1.0,  t,  0.0], [-1.0, -t,  0.0], [ 1.0, -t,  0.0],
+        [ 0.0, -1.0,  t], [ 0.0,  1.0,  t], [ 0.0, -1.0, -t], [ 0.0,  1.0, -t],
+        [ t,  0.0, -1.0], [ t,  0.0,  1.0], [-t,  0.0, -1.0], [-t,  0.0,  1.0],
+    ];
+    for vertex in vertices.iter_mut() {
+        *vertex = normalize(*vertex).unwrap();
+    }
+    let mut faces: Vec<[usize; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // Each edge is shared by two triangles, which must share its midpoint too.
+        let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut midpoint = |vertices: &mut Vec<[f32; 3]>, a: usize, b: usize| {
+            let edge = (usize::min(a, b), usize::max(a, b));
+            *midpoints.entry(edge).or_insert_with(|| {
+                let (p, q) = (vertices[a], vertices[b]);
+                vertices.push(normalize(add(p, q)).unwrap());
+
+                vertices.len() - 1
+            })
+        };
+        let mut subdivided = Vec::with_capacity(4 * faces.len());
+        for face in faces.iter() {
+            let ab = midpoint(&mut vertices, face[0], face[1]);
+            let bc = midpoint(&mut vertices, face[1], face[2]);
+            let ca = midpoint(&mut vertices, face[2], face[0]);
+            subdivided.push([face[0], ab, ca]);
+            subdivided.push([face[1], bc, ab]);
+            subdivided.push([face[2], ca, bc]);
+            subdivided.push([ab, bc, ca]);
+        }
+        faces = subdivided;
+    }
+
+    let mut builder = MeshBuilder::new();
+    for face in faces.iter() {
+        let normals = [vertices[face[0]], vertices[face[1]], vertices[face[2]]];
+        let mut tex_coords = [
+            spherical_tex_coords(normals[0]),
+            spherical_tex_coords(normals[1]),
+            spherical_tex_coords(normals[2])
+        ];
+        // Triangles crossing the seam at the back of the sphere would otherwise stretch
+        // the whole texture across themselves.
+        let max_u = f32::max(tex_coords[0][0], f32::max(tex_coords[1][0], tex_coords[2][0]));
+        for tex_coord in tex_coords.iter_mut() {
+            if max_u - tex_coord[0] > 0.5 {
+                tex_coord[0] += 1.0;
+            }
+        }
+        let points = [
+            [radius * normals[0][0], radius * normals[0][1], radius * normals[0][2]],
+            [radius * normals[1][0], radius * normals[1][1], radius * normals[1][2]],
+            [radius * normals[2][0], radius * normals[2][1], radius * normals[2][2]],
+        ];
+        builder.triangle(points, tex_coords, normals);
+    }
+
+    builder.build()
+}
+
+/// Create a torus lying flat around the vertical axis. The tube of radius `minor_radius`
+/// circles the axis at a distance of `major_radius`, with `major_segments` segments
+/// around the axis and `minor_segments` segments around the tube.
+pub fn torus(major_radius: f32, minor_radius: f32, major_segments: usize, minor_segments: usize) -> ObjMesh {
+    assert!(major_segments >= 3 && minor_segments >= 3, "A torus needs at least 3 segments each way.");
+    let mut builder = MeshBuilder::new();
+    builder.surface(major_segments, minor_segments, |u, v| {
+        let major_angle = 2.0 * PI * u;
+        let minor_angle = 2.0 * PI * v;
+        let normal = [
+            minor_angle.cos() * major_angle.cos(),
+            minor_angle.sin(),
+            -minor_angle.cos() * major_angle.sin()
+        ];
+        let distance = major_radius + minor_radius * minor_angle.cos();
+        let point = [distance * major_angle.cos(), minor_radius * minor_angle.sin(), -distance * major_angle.sin()];
+
+        (point, normal)
+    });
+
+    builder.build()
+}
+
+/// Create a capped cylinder standing on the vertical axis and centered on the origin,
+/// with `slices` segments around the axis and `stacks` segments along it.
+pub fn cylinder(radius: f32, height: f32, slices: usize, stacks: usize) -> ObjMesh {
+    assert!(slices >= 3 && stacks >= 1, "A cylinder needs at least 3 slices and 1 stack.");
+    let mut builder = MeshBuilder::new();
+    builder.surface(slices, stacks, |u, v| {
+        let angle = 2.0 * PI * u;
+        let normal = [angle.cos(), 0.0, -angle.sin()];
+        let point = [radius * normal[0], height * (v - 0.5), radius * normal[2]];
+
+        (point, normal)
+    });
+    builder.disc(radius, 0.5 * height, slices, [0.0, 1.0, 0.0]);
+    builder.disc(radius, -0.5 * height, slices, [0.0, -1.0, 0.0]);
+
+    builder.build()
+}
+
+/// Create a cone with its base capped, standing on the vertical axis and centered on
+/// the origin, with `slices` segments around the axis and `stacks` segments along it.
+pub fn cone(radius: f32, height: f32, slices: usize, stacks: usize) -> ObjMesh {
+    assert!(slices >= 3 && stacks >= 1, "A cone needs at least 3 slices and 1 stack.");
+    let slant = (radius * radius + height * height).sqrt();
+    let mut builder = MeshBuilder::new();
+    builder.surface(slices, stacks, |u, v| {
+        let angle = 2.0 * PI * u;
+        let normal = [height * angle.cos() / slant, radius / slant, -height * angle.sin() / slant];
+        let distance = radius * (1.0 - v);
+        let point = [distance * angle.cos(), height * (v - 0.5), -distance * angle.sin()];
+
+        (point, normal)
+    });
+    builder.disc(radius, -0.5 * height, slices, [0.0, -1.0, 0.0]);
+
+    builder.build()
+}
+
+/// Create a square in the horizontal plane facing up, centered on the origin, and split
+/// into `subdivisions` segments along each side.
+pub fn plane(size: f32, subdivisions: usize) -> ObjMesh {
+    assert!(subdivisions >= 1, "A plane needs at least 1 subdivision.");
+    let mut builder = MeshBuilder::new();
+    builder.surface(subdivisions, subdivisions, |u, v| {
+        ([size * (u - 0.5), 0.0, size * (0.5 - v)], [0.0, 1.0, 0.0])
+    });
+
+    builder.build()
+}
+
+/// The texture coordinates of a point on the unit sphere, wrapping the texture around
+/// the vertical axis like the UV sphere does.
+fn spherical_tex_coords(direction: [f32; 3]) -> [f32; 2] {
+    let longitude = (-direction[2]).atan2(direction[0]);
+    let latitude = direction[1].max(-1.0).min(1.0).asin();
+    let u = if longitude < 0.0 { longitude / (2.0 * PI) + 1.0 } else { longitude / (2.0 * PI) };
+
+    [u, latitude / PI + 0.5]
+}
+
+/// Collects the vertices of a list of triangles.
+pub struct MeshBuilder {
+    points: Vec<[f32; 3]>,
+    tex_coords: Vec<[f32; 2]>,
+    normals: Vec<[f32; 3]>,
+}
+
+impl MeshBuilder {
+    pub fn new() -> MeshBuilder {
+        MeshBuilder {
+            points: vec![],
+            tex_coords: vec![],
+            normals: vec![],
+        }
+    }
+
+    /// Add a triangle, wound counterclockwise when seen from the side its normals
+    /// face. Triangles collapsed to a line or a point, like the ones touching the poles
+    /// of a sphere, are left out.
+    pub fn triangle(&mut self, points: [[f32; 3]; 3], tex_coords: [[f32; 2]; 3], normals: [[f32; 3]; 3]) {
+        let face_normal = mesh::face_normal(points[0], points[1], points[2]);
+        let longest_edge = (0..3)
+            .map(|i| {
+                let edge = sub(points[(i + 1) % 3], points[i]);
+                dot(edge, edge)
+            })
+            .fold(0.0, f32::max);
+        if length(face_normal) <= 1e-5 * longest_edge {
+            return;
+        }
+
+        let vertex_normal = [
+            normals[0][0] + normals[1][0] + normals[2][0],
+            normals[0][1] + normals[1][1] + normals[2][1],
+            normals[0][2] + normals[1][2] + normals[2][2],
+        ];
+        let order = if dot(face_normal, vertex_normal) >= 0.0 { [0, 1, 2] } else { [0, 2, 1] };
+        for &i in order.iter() {
+            self.points.push(points[i]);
+            self.tex_coords.push(tex_coords[i]);
+            self.normals.push(normals[i]);
+        }
+    }
+
+    /// Add a parametric surface sampled on a grid of `u_segments` by `v_segments` quads
+    /// over the unit square. The texture coordinates are the parameters.
+    pub fn surface<F>(&mut self, u_segments: usize, v_segments: usize, f: F)
+        where F: Fn(f32, f32) -> ([f32; 3], [f32; 3])
+    {
+        let sample = |i: usize, j: usize| {
+            let u = i as f32 / u_segments as f32;
+            let v = j as f32 / v_segments as f32;
+            let (point, normal) = f(u, v);
+
+            (point, [u, v], normal)
+        };
+        for j in 0..v_segments {
+            for i in 0..u_segments {
+                let a = sample(i, j);
+                let b = sample(i + 1, j);
+                let c = sample(i + 1, j + 1);
+                let d = sample(i, j + 1);
+                self.triangle([a.0, b.0, c.0], [a.1, b.1, c.1], [a.2, b.2, c.2]);
+                self.triangle([a.0, c.0, d.0], [a.1, c.1, d.1], [a.2, c.2, d.2]);
+            }
+        }
+    }
+
+    /// Add a horizontal disc at height `y` facing up or down, as a fan of `slices`
+    /// triangles.
+    fn disc(&mut self, radius: f32, y: f32, slices: usize, normal: [f32; 3]) {
+        let sample = |i: usize| {
+            let angle = 2.0 * PI * i as f32 / slices as f32;
+            let point = [radius * angle.cos(), y, -radius * angle.sin()];
+            let tex_coord = [0.5 + 0.5 * angle.cos(), 0.5 + 0.5 * angle.sin()];
+
+            (point, tex_coord)
+        };
+        for i in 0..slices {
+            let a = sample(i);
+            let b = sample(i + 1);
+            self.triangle([[0.0, y, 0.0], a.0, b.0], [[0.5, 0.5], a.1, b.1], [normal, normal, normal]);
+        }
+    }
+
+    pub fn build(self) -> ObjMesh {
+        ObjMesh::new(self.points, self.tex_coords, self.normals)
+    }
+}
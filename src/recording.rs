@@ -0,0 +1,390 @@
+use crate::line_format;
+use crate::line_format::FormatError;
+use cgperspective::{
+    CameraMovement,
+    SimpleCameraMovement,
+};
+use std::fs::File;
+use std::io;
+use std::io::{
+    BufRead,
+    BufReader,
+    Write,
+};
+use std::ops;
+use std::path::Path;
+
+
+/// The kind of file the errors from reading and writing recordings name.
+const FILE_KIND: &str = "input recording";
+
+/// The names of the camera movements in recording files, in the order of their bits
+/// in a `MovementInput`.
+const MOVEMENT_NAMES: [&str; 12] = [
+    "move_left",
+    "move_right",
+    "move_up",
+    "move_down",
+    "move_forward",
+    "move_backward",
+    "yaw_left",
+    "yaw_right",
+    "pitch_up",
+    "pitch_down",
+    "roll_counter_clockwise",
+    "roll_clockwise",
+];
+
+fn movement_bit(movement: &SimpleCameraMovement) -> usize {
+    match *movement {
+        SimpleCameraMovement::MoveLeft => 0,
+        SimpleCameraMovement::MoveRight => 1,
+        SimpleCameraMovement::MoveUp => 2,
+        SimpleCameraMovement::MoveDown => 3,
+        SimpleCameraMovement::MoveForward => 4,
+        SimpleCameraMovement::MoveBackward => 5,
+        SimpleCameraMovement::YawLeft => 6,
+        SimpleCameraMovement::YawRight => 7,
+        SimpleCameraMovement::PitchUp => 8,
+        SimpleCameraMovement::PitchDown => 9,
+        SimpleCameraMovement::RollCounterClockwise => 10,
+        SimpleCameraMovement::RollClockwise => 11,
+    }
+}
+
+fn movement_from_bit(bit: usize) -> SimpleCameraMovement {
+    match bit {
+        0 => SimpleCameraMovement::MoveLeft,
+        1 => SimpleCameraMovement::MoveRight,
+        2 => SimpleCameraMovement::MoveUp,
+        3 => SimpleCameraMovement::MoveDown,
+        4 => SimpleCameraMovement::MoveForward,
+        5 => SimpleCameraMovement::MoveBackward,
+        6 => SimpleCameraMovement::YawLeft,
+        7 => SimpleCameraMovement::YawRight,
+        8 => SimpleCameraMovement::PitchUp,
+        9 => SimpleCameraMovement::PitchDown,
+        10 => SimpleCameraMovement::RollCounterClockwise,
+        11 => SimpleCameraMovement::RollClockwise,
+        _ => panic!("There is no camera movement with the bit {}.", bit),
+    }
+}
+
+/// The camera movements requested during one simulation step, one bit per movement.
+/// Unlike a `CameraMovement`, this can be compared, stored, and written to a file.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MovementInput {
+    bits: u16,
+}
+
+impl MovementInput {
+    pub fn new() -> MovementInput {
+        MovementInput {
+            bits: 0,
+        }
+    }
+
+    /// The camera movement the input requests.
+    pub fn to_camera_movement(self) -> CameraMovement {
+        let mut movement = CameraMovement::new();
+        for bit in 0..MOVEMENT_NAMES.len() {
+            if self.bits & (1 << bit) != 0 {
+                movement += movement_from_bit(bit);
+            }
+        }
+
+        movement
+    }
+
+    fn names(self) -> impl Iterator<Item = &'static str> {
+        (0..MOVEMENT_NAMES.len())
+            .filter(move |bit| self.bits & (1 << bit) != 0)
+            .map(|bit| MOVEMENT_NAMES[bit])
+    }
+}
+
+impl ops::AddAssign<SimpleCameraMovement> for MovementInput {
+    fn add_assign(&mut self, other: SimpleCameraMovement) {
+        self.bits |= 1 << movement_bit(&other);
+    }
+}
+
+/// A change of the camera movement input at a simulation step.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct InputEvent {
+    /// The simulation step the input takes effect at, counted from the start of the
+    /// recording.
+    tick: u64,
+    input: MovementInput,
+}
+
+/// The camera movement input of every simulation step of a session. The recording only
+/// stores the steps where the input changes, and each input holds until the next change.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputRecording {
+    /// The length in seconds of each simulation step. The recording only replays the
+    /// same camera path with the same timestep.
+    timestep: f32,
+    /// The simulation step the recording started at.
+    start_tick: u64,
+    /// The number of simulation steps in the recording.
+    length: u64,
+    events: Vec<InputEvent>,
+}
+
+impl InputRecording {
+    pub fn new(timestep: f32, start_tick: u64) -> InputRecording {
+        InputRecording {
+            timestep: timestep,
+            start_tick: start_tick,
+            length: 0,
+            events: vec![],
+        }
+    }
+
+    #[inline]
+    pub fn timestep(&self) -> f32 {
+        self.timestep
+    }
+
+    #[inline]
+    pub fn start_tick(&self) -> u64 {
+        self.start_tick
+    }
+
+    /// The length of the recording in seconds.
+    pub fn duration(&self) -> f32 {
+        self.length as f32 * self.timestep
+    }
+
+    /// Append the input of the simulation step `tick` to the recording. The steps must be
+    /// recorded in order.
+    pub fn record(&mut self, tick: u64, input: MovementInput) {
+        debug_assert!(tick >= self.start_tick + self.length);
+        let tick = tick - self.start_tick;
+        let changed = self.events.last().map_or(true, |event| event.input != input);
+        if changed {
+            self.events.push(InputEvent { tick: tick, input: input });
+        }
+        self.length = tick + 1;
+    }
+
+    /// The input of the simulation step `tick`, or `None` if the step lies outside of
+    /// the recording. No movement is held down before the first input change.
+    pub fn input_at(&self, tick: u64) -> Option<MovementInput> {
+        if tick < self.start_tick || tick >= self.start_tick + self.length {
+            return None;
+        }
+
+        let tick = tick - self.start_tick;
+        let input = match self.events.iter().rposition(|event| event.tick <= tick) {
+            Some(index) => self.events[index].input,
+            None => MovementInput::new(),
+        };
+
+        Some(input)
+    }
+
+    /// Load a recording from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<InputRecording, FormatError> {
+        let file = File::open(&path).map_err(|e| FormatError::io(FILE_KIND, &path, e))?;
+        InputRecording::parse(BufReader::new(file))
+    }
+
+    /// Save the recording to a file in the format `InputRecording::parse` reads.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), FormatError> {
+        let mut file = File::create(&path).map_err(|e| FormatError::io(FILE_KIND, &path, e))?;
+        self.write(&mut file).map_err(|e| FormatError::io(FILE_KIND, &path, e))
+    }
+
+    /// Parse a recording from the statements `line_format::read_statements` reads. A header
+    /// gives the simulation timestep, the simulation step the recording starts at, and the
+    /// number of steps in the recording. Each input change
+    /// follows as the step it takes effect at, its simulation time in seconds, and the
+    /// names of the movements held down from then on.
+    /// ```text
+    /// timestep 0.008333334
+    /// start_tick 0
+    /// length 360
+    /// input 0 0
+    /// input 120 1 move_forward yaw_left
+    /// input 240 2
+    /// ```
+    pub fn parse<R: BufRead>(reader: R) -> Result<InputRecording, FormatError> {
+        let mut timestep = None;
+        let mut start_tick = None;
+        let mut length = None;
+        let mut events: Vec<InputEvent> = vec![];
+        line_format::read_statements(reader, FILE_KIND, |line_number, words| {
+            let error = |message: &str| FormatError::new(FILE_KIND, line_number, String::from(message));
+            let parse_u64 = |word: &str| {
+                word.parse::<u64>().map_err(|_| error(&format!("Expected a step number but got `{}`.", word)))
+            };
+            match words[0] {
+                "timestep" if words.len() == 2 => {
+                    let value = words[1].parse::<f32>()
+                        .map_err(|_| error(&format!("Expected a number but got `{}`.", words[1])))?;
+                    if value <= 0.0 || !value.is_finite() {
+                        return Err(error("The timestep must be positive."));
+                    }
+                    timestep = Some(value);
+                }
+                "start_tick" if words.len() == 2 => {
+                    start_tick = Some(parse_u64(words[1])?);
+                }
+                "length" if words.len() == 2 => {
+                    length = Some(parse_u64(words[1])?);
+                }
+                "input" if words.len() >= 3 => {
+                    let tick = parse_u64(words[1])?;
+                    if let Some(last) = events.last() {
+                        if tick <= last.tick {
+                            return Err(error("The inputs must be in increasing order of their steps."));
+                        }
+                    }
+                    // The simulation time only makes the file easier to read. The step
+                    // number determines when the input takes effect.
+                    let mut input = MovementInput::new();
+                    for &name in words[3..].iter() {
+                        let bit = MOVEMENT_NAMES.iter().position(|&movement| movement == name)
+                            .ok_or_else(|| error(&format!("Unknown camera movement `{}`.", name)))?;
+                        input.bits |= 1 << bit;
+                    }
+                    events.push(InputEvent { tick: tick, input: input });
+                }
+                statement => {
+                    return Err(error(&format!("Unknown or malformed statement `{}`.", statement)));
+                }
+            }
+
+            Ok(())
+        })?;
+
+        let missing = |name: &str| FormatError::new(FILE_KIND, 0, format!("The recording has no `{}`.", name));
+        let timestep = timestep.ok_or_else(|| missing("timestep"))?;
+        let start_tick = start_tick.ok_or_else(|| missing("start_tick"))?;
+        let length = length.ok_or_else(|| missing("length"))?;
+        if events.last().map_or(false, |event| event.tick >= length) {
+            return Err(FormatError::new(FILE_KIND, 0, String::from("The recording has inputs past its end.")));
+        }
+
+        Ok(InputRecording {
+            timestep: timestep,
+            start_tick: start_tick,
+            length: length,
+            events: events,
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "timestep {}", self.timestep)?;
+        writeln!(writer, "start_tick {}", self.start_tick)?;
+        writeln!(writer, "length {}", self.length)?;
+        for event in self.events.iter() {
+            write!(writer, "input {} {}", event.tick, event.tick as f32 * self.timestep)?;
+            for name in event.input.names() {
+                write!(writer, " {}", name)?;
+            }
+            writeln!(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMESTEP: f32 = 0.25;
+
+    fn input(movements: &[SimpleCameraMovement]) -> MovementInput {
+        let mut input = MovementInput::new();
+        for &movement in movements.iter() {
+            input += movement;
+        }
+
+        input
+    }
+
+    fn sample_recording() -> InputRecording {
+        let mut recording = InputRecording::new(TIMESTEP, 10);
+        let forward = input(&[SimpleCameraMovement::MoveForward]);
+        let turning = input(&[SimpleCameraMovement::MoveForward, SimpleCameraMovement::YawLeft]);
+        for tick in 10..13 {
+            recording.record(tick, MovementInput::new());
+        }
+        for tick in 13..15 {
+            recording.record(tick, forward);
+        }
+        recording.record(15, turning);
+        recording.record(16, MovementInput::new());
+
+        recording
+    }
+
+    /// Each input holds from the step it changes at until the next change.
+    #[test]
+    fn test_input_at_holds_each_input_until_the_next_change() {
+        let recording = sample_recording();
+        let forward = input(&[SimpleCameraMovement::MoveForward]);
+        let turning = input(&[SimpleCameraMovement::MoveForward, SimpleCameraMovement::YawLeft]);
+        let expected = [
+            MovementInput::new(),
+            MovementInput::new(),
+            MovementInput::new(),
+            forward,
+            forward,
+            turning,
+            MovementInput::new(),
+        ];
+
+        assert_eq!(recording.events.len(), 4);
+        for (i, &expected) in expected.iter().enumerate() {
+            assert_eq!(recording.input_at(10 + i as u64), Some(expected));
+        }
+    }
+
+    /// The steps before the start and from the end of the recording on have no input.
+    #[test]
+    fn test_input_at_outside_the_recording_is_none() {
+        let recording = sample_recording();
+
+        assert_eq!(recording.input_at(9), None);
+        assert_eq!(recording.input_at(17), None);
+        assert_eq!(InputRecording::new(TIMESTEP, 0).input_at(0), None);
+    }
+
+    /// A recording whose first input change comes after its first step holds no movement
+    /// down until then.
+    #[test]
+    fn test_input_at_before_the_first_input_is_empty() {
+        let text = "timestep 0.25\nstart_tick 0\nlength 4\ninput 2 0.5 move_up\n";
+        let recording = InputRecording::parse(text.as_bytes()).unwrap();
+
+        assert_eq!(recording.input_at(0), Some(MovementInput::new()));
+        assert_eq!(recording.input_at(1), Some(MovementInput::new()));
+        assert_eq!(recording.input_at(2), Some(input(&[SimpleCameraMovement::MoveUp])));
+    }
+
+    /// Saving a recording and parsing it back recovers the same recording.
+    #[test]
+    fn test_write_then_parse_round_trips() {
+        let recording = sample_recording();
+        let mut text = vec![];
+        recording.write(&mut text).unwrap();
+        let parsed = InputRecording::parse(&text[..]).unwrap();
+
+        assert_eq!(parsed, recording);
+    }
+
+    /// Inputs out of order or past the end of the recording are errors.
+    #[test]
+    fn test_parse_rejects_misplaced_inputs() {
+        let out_of_order = "timestep 0.25\nstart_tick 0\nlength 4\ninput 2 0.5\ninput 1 0.25\n";
+        let past_the_end = "timestep 0.25\nstart_tick 0\nlength 4\ninput 4 1\n";
+
+        assert!(InputRecording::parse(out_of_order.as_bytes()).is_err());
+        assert!(InputRecording::parse(past_the_end.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,876 @@
+use crate::kinematics::{
+    Keyframe,
+    KinematicsConfig,
+};
+use crate::light::{
+    AreaLight,
+    AreaLightShape,
+    Attenuation,
+    DirectionalLight,
+    LightSource,
+    PointLight,
+    SpotLight,
+};
+use crate::line_format;
+use crate::line_format::FormatError;
+use crate::photometry::LuminousPower;
+use cglinalg::{
+    Radians,
+    Vector3,
+};
+use std::fs::File;
+use std::io;
+use std::io::{
+    BufRead,
+    BufReader,
+    Write,
+};
+use std::path::Path;
+
+
+/// The kind of file the errors from reading and writing rigs name.
+const FILE_KIND: &str = "light rig";
+
+/// The kind of light a rig places, together with the properties specific to that kind.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Emitter {
+    Point {
+        power: LuminousPower,
+        attenuation: Attenuation<f32>,
+        radius: f32,
+    },
+    Directional {
+        /// The illuminance in lux the light casts onto a surface facing it.
+        illuminance: f32,
+        angular_radius: Radians<f32>,
+    },
+    Spot {
+        power: LuminousPower,
+        inner_cutoff: Radians<f32>,
+        outer_cutoff: Radians<f32>,
+        falloff: f32,
+        attenuation: Attenuation<f32>,
+        radius: f32,
+    },
+    Area {
+        lumens: f32,
+        shape: AreaLightShape<f32>,
+    },
+}
+
+/// The description of a light in a rig, in the photometric units the lights are
+/// constructed from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightSpec {
+    pub emitter: Emitter,
+    /// The color temperature of the light in Kelvin.
+    pub temperature: f32,
+    /// The fraction of the light's diffuse component it contributes as ambient light.
+    pub ambient_fraction: f32,
+    /// The direction the light points in world space. Point lights ignore it.
+    pub direction: Vector3<f32>,
+    pub kinematics: KinematicsConfig,
+    pub casts_shadows: bool,
+}
+
+impl LightSpec {
+    /// Construct the light source the description describes.
+    pub fn light_source(&self) -> LightSource<f32> {
+        let temperature = self.temperature;
+        let ambient_fraction = self.ambient_fraction;
+        let direction = self.direction;
+        match self.emitter {
+            Emitter::Point { power, attenuation, radius } => {
+                LightSource::Point(PointLight::from_photometric(
+                    temperature, power, ambient_fraction, attenuation, radius
+                ))
+            }
+            Emitter::Directional { illuminance, angular_radius } => {
+                LightSource::Directional(DirectionalLight::from_photometric(
+                    temperature, illuminance, ambient_fraction, direction, angular_radius
+                ))
+            }
+            Emitter::Spot { power, inner_cutoff, outer_cutoff, falloff, attenuation, radius } => {
+                LightSource::Spot(SpotLight::from_photometric(
+                    temperature, power, ambient_fraction, direction,
+                    inner_cutoff, outer_cutoff, falloff, attenuation, radius
+                ))
+            }
+            Emitter::Area { lumens, shape } => {
+                LightSource::Area(AreaLight::from_photometric(
+                    temperature, lumens, ambient_fraction, direction, shape
+                ))
+            }
+        }
+    }
+}
+
+/// A named arrangement of lights for illuminating the scene.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightRig {
+    pub name: String,
+    pub lights: Vec<LightSpec>,
+}
+
+impl LightRig {
+    /// Load a rig from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<LightRig, FormatError> {
+        let file = File::open(&path).map_err(|e| FormatError::io(FILE_KIND, &path, e))?;
+        LightRig::parse(BufReader::new(file))
+    }
+
+    /// Save the rig to a file in the format `LightRig::parse` reads.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), FormatError> {
+        let mut file = File::create(&path).map_err(|e| FormatError::io(FILE_KIND, &path, e))?;
+        self.write(&mut file).map_err(|e| FormatError::io(FILE_KIND, &path, e))
+    }
+
+    /// Parse a rig from the statements `line_format::read_statements` reads. The rig starts
+    /// with its name, and each light follows as a line naming its kind and a line for each
+    /// of its properties. Angles are in radians.
+    /// ```text
+    /// rig key_fill_rim
+    /// light spot
+    /// temperature 3200
+    /// ambient_fraction 0.02
+    /// candela 1200                       # or `lumens`; directional lights use `lux`
+    /// direction -2.5 -3.0 -3.0
+    /// cone 0.3 0.45 1.0                  # inner cutoff, outer cutoff, falloff
+    /// attenuation inverse_square 20.0    # or `none`
+    /// radius 0.15
+    /// shadows
+    /// static 2.5 3.0 3.0                 # the light's motion
+    /// ```
+    /// A directional light has an `angular_radius`, and an area light has a `rectangle`
+    /// with its half width and half height or a `disk` with its radius. The motion is one
+    /// of `static <position>`, `orbit <center> <start> <axis> <angular speed>`,
+    /// `kepler <focus> <periapsis direction> <normal> <semi-major axis> <eccentricity> <period>`,
+    /// `spline <period or none>` followed by `keyframe <time> <position>` lines, or
+    /// `headlamp <offset>`.
+    pub fn parse<R: BufRead>(reader: R) -> Result<LightRig, FormatError> {
+        let mut name = None;
+        let mut lights = vec![];
+        let mut light: Option<LightBuilder> = None;
+        line_format::read_statements(reader, FILE_KIND, |line_number, words| {
+            let error = |message: &str| FormatError::new(FILE_KIND, line_number, String::from(message));
+            match words[0] {
+                "rig" => {
+                    if name.is_some() {
+                        return Err(error("The rig has more than one name."));
+                    }
+                    if words.len() != 2 {
+                        return Err(error("Expected `rig <name>`."));
+                    }
+                    name = Some(String::from(words[1]));
+                }
+                _ if name.is_none() => {
+                    return Err(error("The rig must start with its name."));
+                }
+                "light" => {
+                    if let Some(builder) = light.take() {
+                        lights.push(builder.build()?);
+                    }
+                    let kind = match words.get(1) {
+                        Some(&"point") => LightKind::Point,
+                        Some(&"directional") => LightKind::Directional,
+                        Some(&"spot") => LightKind::Spot,
+                        Some(&"area") => LightKind::Area,
+                        _ => return Err(error("Expected `light <point | directional | spot | area>`.")),
+                    };
+                    light = Some(LightBuilder::new(kind, line_number));
+                }
+                _ => {
+                    let builder = light.as_mut().ok_or_else(|| error("Found a property before the first light."))?;
+                    builder.set(words).map_err(|message| FormatError::new(FILE_KIND, line_number, message))?;
+                }
+            }
+
+            Ok(())
+        })?;
+        if let Some(builder) = light.take() {
+            lights.push(builder.build()?);
+        }
+        let name = name.ok_or_else(|| FormatError::new(FILE_KIND, 0, String::from("The rig is empty.")))?;
+
+        Ok(LightRig {
+            name: name,
+            lights: lights,
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "rig {}", self.name)?;
+        for light in self.lights.iter() {
+            writeln!(writer)?;
+            let kind = match light.emitter {
+                Emitter::Point { .. } => "point",
+                Emitter::Directional { .. } => "directional",
+                Emitter::Spot { .. } => "spot",
+                Emitter::Area { .. } => "area",
+            };
+            writeln!(writer, "light {}", kind)?;
+            writeln!(writer, "temperature {}", light.temperature)?;
+            writeln!(writer, "ambient_fraction {}", light.ambient_fraction)?;
+            match light.emitter {
+                Emitter::Point { power, attenuation, radius } => {
+                    write_power(writer, power)?;
+                    write_attenuation(writer, attenuation)?;
+                    writeln!(writer, "radius {}", radius)?;
+                }
+                Emitter::Directional { illuminance, angular_radius } => {
+                    writeln!(writer, "lux {}", illuminance)?;
+                    write_vector(writer, "direction", &light.direction)?;
+                    writeln!(writer, "angular_radius {}", angular_radius.0)?;
+                }
+                Emitter::Spot { power, inner_cutoff, outer_cutoff, falloff, attenuation, radius } => {
+                    write_power(writer, power)?;
+                    write_vector(writer, "direction", &light.direction)?;
+                    writeln!(writer, "cone {} {} {}", inner_cutoff.0, outer_cutoff.0, falloff)?;
+                    write_attenuation(writer, attenuation)?;
+                    writeln!(writer, "radius {}", radius)?;
+                }
+                Emitter::Area { lumens, shape } => {
+                    writeln!(writer, "lumens {}", lumens)?;
+                    write_vector(writer, "direction", &light.direction)?;
+                    match shape {
+                        AreaLightShape::Rectangle { half_width, half_height } => {
+                            writeln!(writer, "rectangle {} {}", half_width, half_height)?;
+                        }
+                        AreaLightShape::Disk { radius } => {
+                            writeln!(writer, "disk {}", radius)?;
+                        }
+                    }
+                }
+            }
+            if light.casts_shadows {
+                writeln!(writer, "shadows")?;
+            }
+            match light.kinematics {
+                KinematicsConfig::Static { position } => {
+                    write_vector(writer, "static", &position)?;
+                }
+                KinematicsConfig::CircularOrbit { center, start, axis, angular_speed } => {
+                    writeln!(writer, "orbit {} {} {} {}",
+                        vector_words(&center), vector_words(&start), vector_words(&axis), angular_speed
+                    )?;
+                }
+                KinematicsConfig::KeplerOrbit {
+                    focus, periapsis_direction, normal, semi_major_axis, eccentricity, period
+                } => {
+                    writeln!(writer, "kepler {} {} {} {} {} {}",
+                        vector_words(&focus), vector_words(&periapsis_direction), vector_words(&normal),
+                        semi_major_axis, eccentricity, period
+                    )?;
+                }
+                KinematicsConfig::SplinePath { ref keyframes, period } => {
+                    match period {
+                        Some(period) => writeln!(writer, "spline {}", period)?,
+                        None => writeln!(writer, "spline none")?,
+                    }
+                    for keyframe in keyframes.iter() {
+                        writeln!(writer, "keyframe {} {}", keyframe.time, vector_words(&keyframe.position))?;
+                    }
+                }
+                KinematicsConfig::Headlamp { offset } => {
+                    write_vector(writer, "headlamp", &offset)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn vector_words(vector: &Vector3<f32>) -> String {
+    format!("{} {} {}", vector[0], vector[1], vector[2])
+}
+
+fn write_vector<W: Write>(writer: &mut W, statement: &str, vector: &Vector3<f32>) -> io::Result<()> {
+    writeln!(writer, "{} {}", statement, vector_words(vector))
+}
+
+fn write_power<W: Write>(writer: &mut W, power: LuminousPower) -> io::Result<()> {
+    match power {
+        LuminousPower::Lumens(lumens) => writeln!(writer, "lumens {}", lumens),
+        LuminousPower::Candela(candela) => writeln!(writer, "candela {}", candela),
+    }
+}
+
+fn write_attenuation<W: Write>(writer: &mut W, attenuation: Attenuation<f32>) -> io::Result<()> {
+    match attenuation {
+        Attenuation::None => writeln!(writer, "attenuation none"),
+        Attenuation::Polynomial { .. } => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Polynomial attenuation does not apply to photometric lights.",
+        )),
+        Attenuation::InverseSquare { radius } => writeln!(writer, "attenuation inverse_square {}", radius),
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum LightKind {
+    Point,
+    Directional,
+    Spot,
+    Area,
+}
+
+/// The properties of a light read from a rig file so far.
+struct LightBuilder {
+    kind: LightKind,
+    /// The line the light starts on, for reporting missing properties.
+    line: usize,
+    temperature: Option<f32>,
+    ambient_fraction: f32,
+    power: Option<LuminousPower>,
+    illuminance: Option<f32>,
+    direction: Option<Vector3<f32>>,
+    angular_radius: f32,
+    cone: Option<(f32, f32, f32)>,
+    attenuation: Attenuation<f32>,
+    radius: f32,
+    shape: Option<AreaLightShape<f32>>,
+    casts_shadows: bool,
+    kinematics: Option<KinematicsConfig>,
+}
+
+impl LightBuilder {
+    fn new(kind: LightKind, line: usize) -> LightBuilder {
+        LightBuilder {
+            kind: kind,
+            line: line,
+            temperature: None,
+            ambient_fraction: 0.0,
+            power: None,
+            illuminance: None,
+            direction: None,
+            angular_radius: 0.0,
+            cone: None,
+            attenuation: Attenuation::None,
+            radius: 0.0,
+            shape: None,
+            casts_shadows: false,
+            kinematics: None,
+        }
+    }
+
+    /// Set the property on one line of a rig file.
+    fn set(&mut self, words: &[&str]) -> Result<(), String> {
+        // Parse the `count` numbers following the first `skip` words of the line.
+        let numbers_after = |skip: usize, count: usize| -> Result<Vec<f32>, String> {
+            if words.len() != skip + count {
+                let expected = if count == 1 { String::from("a number") } else { format!("{} numbers", count) };
+                return Err(format!("Expected {} after `{}`.", expected, words[..skip].join(" ")));
+            }
+            // Rust parses `nan` and `inf` as numbers, but no property of a light can be either.
+            words[skip..].iter()
+                .map(|word| match word.parse::<f32>() {
+                    Ok(value) if value.is_finite() => Ok(value),
+                    Ok(_) => Err(format!("Expected a finite number but got `{}`.", word)),
+                    Err(_) => Err(format!("Expected a number but got `{}`.", word)),
+                })
+                .collect()
+        };
+        let numbers = |count: usize| numbers_after(1, count);
+        let non_negative = |value: f32, name: &str| -> Result<f32, String> {
+            if value < 0.0 {
+                return Err(format!("The {} must not be negative.", name));
+            }
+
+            Ok(value)
+        };
+        let positive = |value: f32, name: &str| -> Result<f32, String> {
+            if value <= 0.0 {
+                return Err(format!("The {} must be positive.", name));
+            }
+
+            Ok(value)
+        };
+        let vector = |values: &[f32]| Vector3::new(values[0], values[1], values[2]);
+        // A direction has no length to normalize when it is zero.
+        let direction = |values: &[f32], name: &str| -> Result<Vector3<f32>, String> {
+            if values.iter().all(|&value| value == 0.0) {
+                return Err(format!("The {} must not be zero.", name));
+            }
+
+            Ok(vector(values))
+        };
+        match words[0] {
+            "temperature" => self.temperature = Some(positive(numbers(1)?[0], "temperature")?),
+            "ambient_fraction" => self.ambient_fraction = non_negative(numbers(1)?[0], "ambient fraction")?,
+            "lumens" => self.power = Some(LuminousPower::Lumens(non_negative(numbers(1)?[0], "luminous power")?)),
+            "candela" => self.power = Some(LuminousPower::Candela(non_negative(numbers(1)?[0], "luminous intensity")?)),
+            "lux" => self.illuminance = Some(non_negative(numbers(1)?[0], "illuminance")?),
+            "direction" => self.direction = Some(direction(&numbers(3)?, "direction")?),
+            "angular_radius" => self.angular_radius = non_negative(numbers(1)?[0], "angular radius")?,
+            "cone" => {
+                let values = numbers(3)?;
+                let inner_cutoff = non_negative(values[0], "inner cutoff of a cone")?;
+                let outer_cutoff = non_negative(values[1], "outer cutoff of a cone")?;
+                let falloff = non_negative(values[2], "falloff of a cone")?;
+                if outer_cutoff > std::f32::consts::FRAC_PI_2 {
+                    return Err(String::from("The outer cutoff of a cone must not exceed pi / 2."));
+                }
+                if inner_cutoff > outer_cutoff {
+                    return Err(String::from("The inner cutoff of a cone must not exceed its outer cutoff."));
+                }
+                self.cone = Some((inner_cutoff, outer_cutoff, falloff));
+            }
+            "attenuation" => {
+                self.attenuation = match words.get(1) {
+                    Some(&"none") if words.len() == 2 => Attenuation::None,
+                    // The polynomial coefficients are unitless fudge factors that do not preserve
+                    // the photometric intensity of the light.
+                    Some(&"polynomial") => {
+                        return Err(String::from(
+                            "Polynomial attenuation does not apply to photometric lights; use `inverse_square`."
+                        ));
+                    }
+                    Some(&"inverse_square") => {
+                        Attenuation::InverseSquare { radius: positive(numbers_after(2, 1)?[0], "attenuation radius")? }
+                    }
+                    _ => return Err(String::from("Expected `none` or `inverse_square` attenuation.")),
+                };
+            }
+            "radius" => self.radius = non_negative(numbers(1)?[0], "radius")?,
+            "rectangle" => {
+                let values = numbers(2)?;
+                self.shape = Some(AreaLightShape::Rectangle {
+                    half_width: positive(values[0], "half width of a rectangle")?,
+                    half_height: positive(values[1], "half height of a rectangle")?,
+                });
+            }
+            "disk" => self.shape = Some(AreaLightShape::Disk { radius: positive(numbers(1)?[0], "radius of a disk")? }),
+            "shadows" if words.len() == 1 => self.casts_shadows = true,
+            "static" => self.set_kinematics(KinematicsConfig::Static { position: vector(&numbers(3)?) })?,
+            "orbit" => {
+                let values = numbers(10)?;
+                self.set_kinematics(KinematicsConfig::CircularOrbit {
+                    center: vector(&values[0..3]),
+                    start: vector(&values[3..6]),
+                    axis: direction(&values[6..9], "axis of an orbit")?,
+                    angular_speed: values[9],
+                })?;
+            }
+            "kepler" => {
+                let values = numbers(12)?;
+                if !(0.0..1.0).contains(&values[10]) {
+                    return Err(String::from("The eccentricity of an orbit must lie in [0, 1)."));
+                }
+                if values[11] <= 0.0 {
+                    return Err(String::from("The period of an orbit must be positive."));
+                }
+                if values[9] <= 0.0 {
+                    return Err(String::from("The semi-major axis of an orbit must be positive."));
+                }
+                let periapsis_direction = direction(&values[3..6], "periapsis direction of an orbit")?;
+                let normal = direction(&values[6..9], "normal of an orbit")?;
+                if periapsis_direction.cross(&normal) == Vector3::zero() {
+                    return Err(String::from("The periapsis direction of an orbit must not be parallel to its normal."));
+                }
+                self.set_kinematics(KinematicsConfig::KeplerOrbit {
+                    focus: vector(&values[0..3]),
+                    periapsis_direction: periapsis_direction,
+                    normal: normal,
+                    semi_major_axis: values[9],
+                    eccentricity: values[10],
+                    period: values[11],
+                })?;
+            }
+            "spline" => {
+                let period = match words.get(1) {
+                    Some(&"none") if words.len() == 2 => None,
+                    _ => Some(numbers(1)?[0]),
+                };
+                if let Some(period) = period {
+                    if period <= 0.0 {
+                        return Err(String::from("The period of a spline must be positive."));
+                    }
+                }
+                self.set_kinematics(KinematicsConfig::SplinePath { keyframes: vec![], period: period })?;
+            }
+            "keyframe" => {
+                let values = numbers(4)?;
+                match self.kinematics {
+                    Some(KinematicsConfig::SplinePath { ref mut keyframes, period }) => {
+                        let time = values[0];
+                        if let Some(previous) = keyframes.last() {
+                            if time <= previous.time {
+                                return Err(format!(
+                                    "The keyframe times of a spline must increase, but {} follows {}.", time, previous.time
+                                ));
+                            }
+                        }
+                        // A looping spline also runs from its final keyframe back to its first.
+                        if let (Some(first), Some(period)) = (keyframes.first(), period) {
+                            if time - first.time >= period {
+                                return Err(format!(
+                                    "The keyframes of a spline with a period of {} s must lie within {} s of the first.",
+                                    period, period
+                                ));
+                            }
+                        }
+                        keyframes.push(Keyframe::new(time, vector(&values[1..4])));
+                    }
+                    _ => return Err(String::from("Found a keyframe outside of a spline.")),
+                }
+            }
+            "headlamp" => self.set_kinematics(KinematicsConfig::Headlamp { offset: vector(&numbers(3)?) })?,
+            statement => return Err(format!("Unknown or malformed property `{}`.", statement)),
+        }
+
+        Ok(())
+    }
+
+    fn set_kinematics(&mut self, kinematics: KinematicsConfig) -> Result<(), String> {
+        if self.kinematics.is_some() {
+            return Err(String::from("The light has more than one motion."));
+        }
+        self.kinematics = Some(kinematics);
+
+        Ok(())
+    }
+
+    /// Check that the light has every property its kind needs, and construct it.
+    fn build(self) -> Result<LightSpec, FormatError> {
+        let line = self.line;
+        let missing = |property: &str| FormatError::new(FILE_KIND, line, format!("The light has no `{}`.", property));
+        let temperature = self.temperature.ok_or_else(|| missing("temperature"))?;
+        let kinematics = self.kinematics.ok_or_else(|| missing("motion"))?;
+        if let KinematicsConfig::SplinePath { ref keyframes, .. } = kinematics {
+            if keyframes.len() < 2 {
+                return Err(FormatError::new(FILE_KIND, line, String::from("A spline needs at least two keyframes.")));
+            }
+        }
+        let emitter = match self.kind {
+            LightKind::Point => Emitter::Point {
+                power: self.power.ok_or_else(|| missing("lumens` or `candela"))?,
+                attenuation: self.attenuation,
+                radius: self.radius,
+            },
+            LightKind::Directional => Emitter::Directional {
+                illuminance: self.illuminance.ok_or_else(|| missing("lux"))?,
+                angular_radius: Radians(self.angular_radius),
+            },
+            LightKind::Spot => {
+                let (inner_cutoff, outer_cutoff, falloff) = self.cone.ok_or_else(|| missing("cone"))?;
+                Emitter::Spot {
+                    power: self.power.ok_or_else(|| missing("lumens` or `candela"))?,
+                    inner_cutoff: Radians(inner_cutoff),
+                    outer_cutoff: Radians(outer_cutoff),
+                    falloff: falloff,
+                    attenuation: self.attenuation,
+                    radius: self.radius,
+                }
+            }
+            LightKind::Area => {
+                let lumens = match self.power {
+                    Some(LuminousPower::Lumens(lumens)) => lumens,
+                    _ => return Err(missing("lumens")),
+                };
+                Emitter::Area {
+                    lumens: lumens,
+                    shape: self.shape.ok_or_else(|| missing("rectangle` or `disk"))?,
+                }
+            }
+        };
+        let direction = match self.kind {
+            LightKind::Point => self.direction.unwrap_or_else(Vector3::zero),
+            _ => self.direction.ok_or_else(|| missing("direction"))?,
+        };
+
+        Ok(LightSpec {
+            emitter: emitter,
+            temperature: temperature,
+            ambient_fraction: self.ambient_fraction,
+            direction: direction,
+            kinematics: kinematics,
+            casts_shadows: self.casts_shadows,
+        })
+    }
+}
+
+
+/// The light rigs built into the demo, with the showcase rig first.
+pub fn presets(scene_center_world: Vector3<f32>) -> Vec<LightRig> {
+    vec![
+        showcase_rig(scene_center_world),
+        key_fill_rim_rig(scene_center_world),
+        studio_softbox_rig(scene_center_world),
+        outdoor_sun_sky_rig(),
+        headlamp_rig(),
+    ]
+}
+
+/// Every kind of light and motion the demo supports at once.
+fn showcase_rig(scene_center_world: Vector3<f32>) -> LightRig {
+    // The orbiting lights are warm household bulbs.
+    let bulb = Emitter::Point {
+        power: LuminousPower::Lumens(480.0),
+        attenuation: Attenuation::InverseSquare { radius: 15.0 },
+        radius: 0.1,
+    };
+    let light_0 = LightSpec {
+        emitter: bulb,
+        temperature: 2700.0,
+        ambient_fraction: 0.4,
+        direction: Vector3::zero(),
+        kinematics: KinematicsConfig::CircularOrbit {
+            center: scene_center_world,
+            start: Vector3::new(3.0, 0.0, 0.0),
+            axis: Vector3::new(0.0, 1.0, 1.0),
+            angular_speed: std::f32::consts::PI,
+        },
+        casts_shadows: true,
+    };
+    // The second light swings around the scene on an eccentric orbit, passing close
+    // by the scene and then drifting away.
+    let light_1 = LightSpec {
+        emitter: bulb,
+        temperature: 2700.0,
+        ambient_fraction: 0.4,
+        direction: Vector3::zero(),
+        kinematics: KinematicsConfig::KeplerOrbit {
+            focus: scene_center_world,
+            periapsis_direction: Vector3::new(0.0, 1.0, 0.0),
+            normal: Vector3::new(0.0, 0.0, 1.0),
+            semi_major_axis: 5.0,
+            eccentricity: 0.4,
+            period: 6.0,
+        },
+        casts_shadows: true,
+    };
+    // The third light follows a looping path weaving around the scene.
+    let light_2 = LightSpec {
+        emitter: bulb,
+        temperature: 2700.0,
+        ambient_fraction: 0.4,
+        direction: Vector3::zero(),
+        kinematics: KinematicsConfig::SplinePath {
+            keyframes: vec![
+                Keyframe::new(0.0, Vector3::new( 0.0, 5.0,  5.0)),
+                Keyframe::new(3.0, Vector3::new( 4.0, 2.0,  2.0)),
+                Keyframe::new(6.0, Vector3::new( 0.0, 4.0, -4.0)),
+                Keyframe::new(9.0, Vector3::new(-4.0, 2.0,  2.0)),
+            ],
+            period: Some(12.0),
+        },
+        casts_shadows: true,
+    };
+    // The sun sits far away from the scene and shines down on it at an angle.
+    // Its position only places the marker cube; it does not affect the shading.
+    // The sun is drawn a few times larger than it appears in the sky to soften its shadows.
+    let position_3 = Vector3::new(-4.0, 8.0, 4.0);
+    let light_3 = LightSpec {
+        emitter: Emitter::Directional { illuminance: 25.0, angular_radius: Radians(0.02) },
+        temperature: 5500.0,
+        ambient_fraction: 0.15,
+        direction: scene_center_world - position_3,
+        kinematics: KinematicsConfig::Static { position: position_3 },
+        casts_shadows: true,
+    };
+    // The stage spot is a tungsten lamp hanging still above the scene. It points at the
+    // center of the scene and casts shadows onto the ground.
+    let position_4 = Vector3::new(2.0, 4.0, 2.0);
+    let light_4 = LightSpec {
+        emitter: Emitter::Spot {
+            power: LuminousPower::Candela(1500.0),
+            inner_cutoff: Radians(0.15),
+            outer_cutoff: Radians(0.25),
+            falloff: 1.0,
+            attenuation: Attenuation::InverseSquare { radius: 20.0 },
+            radius: 0.2,
+        },
+        temperature: 3200.0,
+        ambient_fraction: 0.0,
+        direction: scene_center_world - position_4,
+        kinematics: KinematicsConfig::Static { position: position_4 },
+        casts_shadows: true,
+    };
+    // The softbox is a daylight balanced studio light hanging in front of and above the
+    // scene, facing its center.
+    let position_5 = Vector3::new(0.0, 3.0, 3.0);
+    let light_5 = LightSpec {
+        emitter: Emitter::Area {
+            lumens: 5800.0,
+            shape: AreaLightShape::Rectangle { half_width: 1.0, half_height: 0.75 },
+        },
+        temperature: 5600.0,
+        ambient_fraction: 0.0,
+        direction: scene_center_world - position_5,
+        kinematics: KinematicsConfig::Static { position: position_5 },
+        casts_shadows: false,
+    };
+    // The headlamp rides along with the camera and lights up whatever the camera looks at.
+    let light_6 = LightSpec {
+        emitter: headlamp_emitter(150.0),
+        temperature: 4000.0,
+        ambient_fraction: 0.0,
+        direction: Vector3::new(0.0, 0.0, -1.0),
+        kinematics: KinematicsConfig::Headlamp { offset: Vector3::new(0.0, 0.2, 0.0) },
+        casts_shadows: false,
+    };
+
+    LightRig {
+        name: String::from("showcase"),
+        lights: vec![light_0, light_1, light_2, light_3, light_4, light_5, light_6],
+    }
+}
+
+/// Classic three point lighting. A strong key light above and to the side models the
+/// shape of the object, a soft fill light on the opposite side lifts the shadows, and a
+/// rim light behind the object separates its silhouette from the background.
+fn key_fill_rim_rig(scene_center_world: Vector3<f32>) -> LightRig {
+    let key_position = Vector3::new(2.5, 3.0, 3.0);
+    let key = LightSpec {
+        emitter: Emitter::Spot {
+            power: LuminousPower::Candela(1200.0),
+            inner_cutoff: Radians(0.3),
+            outer_cutoff: Radians(0.45),
+            falloff: 1.0,
+            attenuation: Attenuation::InverseSquare { radius: 20.0 },
+            radius: 0.15,
+        },
+        temperature: 3200.0,
+        ambient_fraction: 0.02,
+        direction: scene_center_world - key_position,
+        kinematics: KinematicsConfig::Static { position: key_position },
+        casts_shadows: true,
+    };
+    // The fill light is a broad panel, so it casts no hard shadows of its own.
+    let fill_position = Vector3::new(-3.0, 1.5, 2.5);
+    let fill = LightSpec {
+        emitter: Emitter::Area {
+            lumens: 1500.0,
+            shape: AreaLightShape::Rectangle { half_width: 0.6, half_height: 0.6 },
+        },
+        temperature: 3200.0,
+        ambient_fraction: 0.02,
+        direction: scene_center_world - fill_position,
+        kinematics: KinematicsConfig::Static { position: fill_position },
+        casts_shadows: false,
+    };
+    let rim_position = Vector3::new(0.0, 2.5, -3.5);
+    let rim = LightSpec {
+        emitter: Emitter::Spot {
+            power: LuminousPower::Candela(900.0),
+            inner_cutoff: Radians(0.2),
+            outer_cutoff: Radians(0.3),
+            falloff: 1.0,
+            attenuation: Attenuation::InverseSquare { radius: 20.0 },
+            radius: 0.1,
+        },
+        temperature: 3200.0,
+        ambient_fraction: 0.0,
+        direction: scene_center_world - rim_position,
+        kinematics: KinematicsConfig::Static { position: rim_position },
+        casts_shadows: true,
+    };
+
+    LightRig {
+        name: String::from("key_fill_rim"),
+        lights: vec![key, fill, rim],
+    }
+}
+
+/// A product photography setup. A large softbox above and in front of the scene wraps
+/// the objects in soft light, a strip light to the side draws long highlights along
+/// their edges, and a dim overhead bulb grounds the objects with soft contact shadows.
+fn studio_softbox_rig(scene_center_world: Vector3<f32>) -> LightRig {
+    let softbox_position = Vector3::new(0.0, 3.0, 2.5);
+    let softbox = LightSpec {
+        emitter: Emitter::Area {
+            lumens: 6000.0,
+            shape: AreaLightShape::Rectangle { half_width: 1.2, half_height: 0.8 },
+        },
+        temperature: 5600.0,
+        ambient_fraction: 0.02,
+        direction: scene_center_world - softbox_position,
+        kinematics: KinematicsConfig::Static { position: softbox_position },
+        casts_shadows: false,
+    };
+    let strip_position = Vector3::new(-3.0, 1.0, 0.0);
+    let strip = LightSpec {
+        emitter: Emitter::Area {
+            lumens: 2000.0,
+            shape: AreaLightShape::Rectangle { half_width: 0.25, half_height: 1.0 },
+        },
+        temperature: 5600.0,
+        ambient_fraction: 0.0,
+        direction: scene_center_world - strip_position,
+        kinematics: KinematicsConfig::Static { position: strip_position },
+        casts_shadows: false,
+    };
+    let overhead = LightSpec {
+        emitter: Emitter::Point {
+            power: LuminousPower::Lumens(300.0),
+            attenuation: Attenuation::InverseSquare { radius: 10.0 },
+            radius: 0.5,
+        },
+        temperature: 5600.0,
+        ambient_fraction: 0.1,
+        direction: Vector3::zero(),
+        kinematics: KinematicsConfig::Static { position: Vector3::new(0.0, 3.5, 0.0) },
+        casts_shadows: true,
+    };
+
+    LightRig {
+        name: String::from("studio_softbox"),
+        lights: vec![softbox, strip, overhead],
+    }
+}
+
+/// Daylight. A warm sun casts crisp shadows while a dim blue sky fills them in from above.
+fn outdoor_sun_sky_rig() -> LightRig {
+    let sun = LightSpec {
+        emitter: Emitter::Directional { illuminance: 40.0, angular_radius: Radians(0.01) },
+        temperature: 5500.0,
+        ambient_fraction: 0.1,
+        direction: Vector3::new(1.0, -2.0, -1.0),
+        kinematics: KinematicsConfig::Static { position: Vector3::new(-4.0, 8.0, 4.0) },
+        casts_shadows: true,
+    };
+    // The sky lights the scene from every direction above the horizon. A directional light
+    // from straight above with a large ambient fraction stands in for the hemisphere.
+    let sky = LightSpec {
+        emitter: Emitter::Directional { illuminance: 8.0, angular_radius: Radians(0.0) },
+        temperature: 12000.0,
+        ambient_fraction: 0.6,
+        direction: Vector3::new(0.0, -1.0, 0.0),
+        kinematics: KinematicsConfig::Static { position: Vector3::new(0.0, 9.0, 0.0) },
+        casts_shadows: false,
+    };
+
+    LightRig {
+        name: String::from("outdoor_sun_sky"),
+        lights: vec![sun, sky],
+    }
+}
+
+/// A single light riding along with the camera, like exploring a cave.
+fn headlamp_rig() -> LightRig {
+    let headlamp = LightSpec {
+        emitter: headlamp_emitter(200.0),
+        temperature: 4000.0,
+        ambient_fraction: 0.0,
+        direction: Vector3::new(0.0, 0.0, -1.0),
+        kinematics: KinematicsConfig::Headlamp { offset: Vector3::new(0.0, 0.2, 0.0) },
+        casts_shadows: false,
+    };
+
+    LightRig {
+        name: String::from("headlamp"),
+        lights: vec![headlamp],
+    }
+}
+
+fn headlamp_emitter(candela: f32) -> Emitter {
+    Emitter::Spot {
+        power: LuminousPower::Candela(candela),
+        inner_cutoff: Radians(0.2),
+        outer_cutoff: Radians(0.35),
+        falloff: 1.0,
+        attenuation: Attenuation::InverseSquare { radius: 15.0 },
+        radius: 0.05,
+    }
+}
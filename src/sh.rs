@@ -0,0 +1,220 @@
+use crate::environment::{
+    self,
+    CubeMap,
+};
+
+
+/// The number of coefficients of a function projected onto the spherical harmonics up to
+/// and including the second band.
+pub const SH_COEFFICIENTS: usize = 9;
+/// The width and height in texels of the cube map faces `SphericalHarmonics::from_fn`
+/// integrates over.
+const PROJECTION_SIZE: u32 = 32;
+
+
+/// The real spherical harmonics of the first three bands in the direction `direction`,
+/// in the order `Y(0, 0)`, `Y(1, -1)`, `Y(1, 0)`, `Y(1, 1)`, `Y(2, -2)`, ..., `Y(2, 2)`.
+pub fn basis(direction: [f32; 3]) -> [f32; SH_COEFFICIENTS] {
+    let [x, y, z] = direction;
+    [
+        0.282_095,
+        0.488_603 * y,
+        0.488_603 * z,
+        0.488_603 * x,
+        1.092_548 * x * y,
+        1.092_548 * y * z,
+        0.315_392 * (3.0 * z * z - 1.0),
+        1.092_548 * x * z,
+        0.546_274 * (x * x - y * y),
+    ]
+}
+
+/// The band each coefficient belongs to.
+const BANDS: [usize; SH_COEFFICIENTS] = [0, 1, 1, 1, 2, 2, 2, 2, 2];
+
+/// A function on the sphere with red, green, and blue channels, projected onto the first
+/// nine spherical harmonics. Nine coefficients capture the low frequency part of an
+/// environment, which is all that diffuse reflection depends on.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SphericalHarmonics {
+    pub coefficients: [[f32; 3]; SH_COEFFICIENTS],
+}
+
+impl SphericalHarmonics {
+    /// The projection of a function that is zero everywhere.
+    pub fn zero() -> SphericalHarmonics {
+        SphericalHarmonics {
+            coefficients: [[0.0; 3]; SH_COEFFICIENTS],
+        }
+    }
+
+    /// Project an environment onto the spherical harmonics, integrating over the texels 
+    /// of the cube map weighted by their solid angles.
+    pub fn from_cube_map(cube_map: &CubeMap) -> SphericalHarmonics {
+        let mut projection = SphericalHarmonics::zero();
+        for (face, texels) in cube_map.faces.iter().enumerate() {
+            for y in 0..cube_map.size {
+                for x in 0..cube_map.size {
+                    let direction = environment::texel_direction(face, x, y, cube_map.size);
+                    let solid_angle = environment::texel_solid_angle(x, y, cube_map.size);
+                    let index = 3 * (y * cube_map.size + x) as usize;
+                    let basis = basis(direction);
+                    for i in 0..SH_COEFFICIENTS {
+                        for channel in 0..3 {
+                            projection.coefficients[i][channel] += 
+                                texels[index + channel] * basis[i] * solid_angle;
+                        }
+                    }
+                }
+            }
+        }
+
+        projection
+    }
+
+    /// Project an analytic model of the environment, such as a sky model, onto the
+    /// spherical harmonics. `radiance` gives the radiance arriving from each direction.
+    pub fn from_fn<F>(radiance: F) -> SphericalHarmonics where F: Fn([f32; 3]) -> [f32; 3] {
+        SphericalHarmonics::from_cube_map(&CubeMap::from_fn(PROJECTION_SIZE, radiance))
+    }
+
+    /// Reconstruct the projected function in the direction `direction`.
+    pub fn evaluate(&self, direction: [f32; 3]) -> [f32; 3] {
+        let basis = basis(direction);
+        let mut value = [0.0; 3];
+        for i in 0..SH_COEFFICIENTS {
+            for channel in 0..3 {
+                value[channel] += self.coefficients[i][channel] * basis[i];
+            }
+        }
+
+        value
+    }
+
+    /// Convolve the projected radiance with a clamped cosine lobe, c.f. "An Efficient 
+    /// Representation for Irradiance Environment Maps" by Ramamoorthi and Hanrahan. 
+    /// Evaluating the result in the direction of a surface normal gives the irradiance 
+    /// arriving at the surface divided by pi, so that multiplying it by the diffuse 
+    /// reflectance of the surface gives the radiance the surface reflects.
+    pub fn irradiance(&self) -> SphericalHarmonics {
+        // The projections of the clamped cosine onto each band, divided by pi.
+        const BAND_FACTORS: [f32; 3] = [1.0, 2.0 / 3.0, 1.0 / 4.0];
+
+        let mut irradiance = *self;
+        for i in 0..SH_COEFFICIENTS {
+            for channel in 0..3 {
+                irradiance.coefficients[i][channel] *= BAND_FACTORS[BANDS[i]];
+            }
+        }
+
+        irradiance
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector;
+    use std::f32::consts::PI;
+
+    const EPSILON: f32 = 1e-3;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() <= EPSILON * expected.abs().max(1.0),
+            "Expected {} but got {}.", expected, actual
+        );
+    }
+
+    fn assert_projection(projection: &SphericalHarmonics, expected: [f32; SH_COEFFICIENTS]) {
+        for i in 0..SH_COEFFICIENTS {
+            for channel in 0..3 {
+                assert_close(projection.coefficients[i][channel], expected[i]);
+            }
+        }
+    }
+
+    /// The basis functions are orthonormal, so projecting one of them recovers it exactly.
+    #[test]
+    fn test_basis_functions_project_onto_themselves() {
+        for i in 0..SH_COEFFICIENTS {
+            let projection = SphericalHarmonics::from_fn(|direction| [basis(direction)[i]; 3]);
+            let mut expected = [0.0; SH_COEFFICIENTS];
+            expected[i] = 1.0;
+
+            assert_projection(&projection, expected);
+        }
+    }
+
+    /// A constant environment of radiance `c` projects onto `c * sqrt(4 * pi)` in the 
+    /// first band, and irradiance of `pi * c` everywhere.
+    #[test]
+    fn test_constant_environment() {
+        let projection = SphericalHarmonics::from_fn(|_| [2.0; 3]);
+        let mut expected = [0.0; SH_COEFFICIENTS];
+        expected[0] = 2.0 * (4.0 * PI).sqrt();
+
+        assert_projection(&projection, expected);
+        let irradiance = projection.irradiance();
+        for &direction in [[1.0, 0.0, 0.0], [0.0, -1.0, 0.0], vector::normalize([1.0, 2.0, -3.0]).unwrap()].iter() {
+            for &value in irradiance.evaluate(direction).iter() {
+                assert_close(value, 2.0);
+            }
+        }
+    }
+
+    /// A sky of unit radiance above the horizon and no light below projects onto
+    /// `sqrt(pi)` in the first band and `sqrt(3 * pi) / 2` along the zenith in the second
+    /// band. The irradiance divided by pi is one at the zenith, one half on the horizon, 
+    /// and zero at the nadir.
+    #[test]
+    fn test_hemisphere_sky() {
+        let projection = SphericalHarmonics::from_fn(|direction| {
+            if direction[1] > 0.0 { [1.0; 3] } else { [0.0; 3] }
+        });
+        let mut expected = [0.0; SH_COEFFICIENTS];
+        expected[0] = PI.sqrt();
+        expected[1] = (3.0 * PI).sqrt() / 2.0;
+
+        assert_projection(&projection, expected);
+        let irradiance = projection.irradiance();
+        assert_close(irradiance.evaluate([0.0, 1.0, 0.0])[0], 1.0);
+        assert_close(irradiance.evaluate([1.0, 0.0, 0.0])[0], 0.5);
+        assert_close(irradiance.evaluate([0.0, -1.0, 0.0])[0], 0.0);
+    }
+
+    /// The radiance `max(0, cos(theta))` around the zenith projects onto the clamped
+    /// cosine coefficients of Ramamoorthi and Hanrahan. The exact irradiance divided by
+    /// pi at the zenith is `2 / 3`, and the first three bands recover all but one percent
+    /// of it.
+    #[test]
+    fn test_clamped_cosine_lobe() {
+        let projection = SphericalHarmonics::from_fn(|direction| [direction[2].max(0.0); 3]);
+        let mut expected = [0.0; SH_COEFFICIENTS];
+        expected[0] = PI.sqrt() / 2.0;
+        expected[2] = (PI / 3.0).sqrt();
+        expected[6] = (5.0 * PI).sqrt() / 8.0;
+
+        assert_projection(&projection, expected);
+        let irradiance = projection.irradiance().evaluate([0.0, 0.0, 1.0])[0];
+        assert_close(irradiance, 1.0 / 4.0 + 1.0 / 3.0 + 5.0 / 64.0);
+        assert!((irradiance - 2.0 / 3.0).abs() < 0.01 * 2.0 / 3.0);
+    }
+
+    /// Irradiance is linear in the radiance, so each channel is projected independently.
+    #[test]
+    fn test_channels_are_independent() {
+        let projection = SphericalHarmonics::from_fn(|direction| [1.0, direction[0], 0.0]);
+        let mut red = [0.0; SH_COEFFICIENTS];
+        red[0] = (4.0 * PI).sqrt();
+        let mut green = [0.0; SH_COEFFICIENTS];
+        green[3] = (4.0 * PI / 3.0).sqrt();
+
+        for i in 0..SH_COEFFICIENTS {
+            assert_close(projection.coefficients[i][0], red[i]);
+            assert_close(projection.coefficients[i][1], green[i]);
+            assert_close(projection.coefficients[i][2], 0.0);
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use crate::backend::{
+    Framebuffer,
+    FramebufferError,
+    Texture,
+    TextureFormat,
+};
+use crate::gl;
+use crate::light::{
+    DirectionalLight,
+    SpotLight,
+};
+use cglinalg::{
+    Magnitude,
+    Matrix4,
+    Vector3,
+};
+
+
+/// The width and height in texels of each shadow map.
+pub const SHADOW_MAP_SIZE: u32 = 2048;
+/// The maximum number of shadow maps the mesh shader can sample from. This must agree
+/// with `MAX_SHADOW_MAPS` in `mesh.frag.glsl`.
+pub const MAX_SHADOW_MAPS: usize = 4;
+/// The width and height in texels of each face of a point light's cube shadow map.
+pub const POINT_SHADOW_MAP_SIZE: u32 = 512;
+/// The maximum number of point lights that cast shadows. This must agree with 
+/// `MAX_POINT_SHADOW_MAPS` in `mesh.frag.glsl`.
+pub const MAX_POINT_SHADOW_MAPS: usize = 4;
+/// The number of faces of a cube shadow map. Each face occupies one layer of the 
+/// point light shadow maps.
+pub const CUBE_FACES: usize = 6;
+/// The distance from a point light inside of which nothing casts shadows.
+pub const POINT_SHADOW_NEAR_PLANE: f32 = 0.05;
+/// The distance from a point light beyond which nothing casts shadows.
+pub const POINT_SHADOW_FAR_PLANE: f32 = 20.0;
+
+
+/// The techniques for filtering shadow maps that the mesh shader knows how to evaluate.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ShadowFilter {
+    /// Percentage closer filtering over a fixed size kernel. Every shadow edge is 
+    /// equally soft.
+    Pcf,
+    /// Percentage closer soft shadows. The kernel grows with the size of the light and 
+    /// the distance between the receiver and its blocker, so shadows are sharp where
+    /// objects touch and soften as they fall away.
+    Pcss,
+}
+
+impl ShadowFilter {
+    /// The integer tag identifying the filter in the shader. These must agree with the
+    /// `SHADOW_FILTER_*` constants in `mesh.frag.glsl`.
+    pub fn type_id(&self) -> i32 {
+        match *self {
+            ShadowFilter::Pcf => 0,
+            ShadowFilter::Pcss => 1,
+        }
+    }
+
+    /// The other filter.
+    pub fn toggle(&self) -> ShadowFilter {
+        match *self {
+            ShadowFilter::Pcf => ShadowFilter::Pcss,
+            ShadowFilter::Pcss => ShadowFilter::Pcf,
+        }
+    }
+}
+
+/// The projection a directional or spot light renders its shadow map with. 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShadowProjection {
+    /// The transformation from world space to the clip space of the light.
+    pub light_space_mat: Matrix4<f32>,
+    /// Whether the projection is a perspective projection, as opposed to an
+    /// orthographic one.
+    pub perspective: bool,
+    pub near: f32,
+    pub far: f32,
+    /// The size of the light in shadow map texture coordinates. For an orthographic 
+    /// projection, this is the radius of the penumbra a blocker casts onto a receiver a unit
+    /// distance behind it. For a perspective projection, it is the radius of the light 
+    /// measured at a unit distance from the light.
+    pub light_size_uv: f32,
+}
+
+
+/// The offsets applied to the depth of a fragment before comparing it to the depth in a 
+/// shadow map. Without them, surfaces shadow themselves in a pattern of stripes called 
+/// shadow acne.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DepthBias {
+    /// The smallest offset applied to every fragment.
+    pub constant: f32,
+    /// The offset applied to a surface at a grazing angle to the light.
+    pub slope: f32,
+}
+
+impl DepthBias {
+    pub fn new(constant: f32, slope: f32) -> DepthBias {
+        DepthBias {
+            constant: constant,
+            slope: slope,
+        }
+    }
+
+    /// Scale both offsets by `factor`.
+    pub fn scale(&mut self, factor: f32) {
+        self.constant *= factor;
+        self.slope *= factor;
+    }
+}
+
+
+/// Compute the view matrix of a camera at `eye` looking at `target`.
+pub fn look_at(eye: &Vector3<f32>, target: &Vector3<f32>) -> Matrix4<f32> {
+    let forward = (*target - *eye).normalize();
+    // Pick an up direction that is not parallel to the view direction.
+    let up = if forward[1].abs() < 0.999 {
+        Vector3::unit_y()
+    } else {
+        Vector3::unit_z()
+    };
+
+    look_at_with_up(eye, target, &up)
+}
+
+/// Compute the view matrix of a camera at `eye` looking at `target`, whose up 
+/// direction is as close to `up` as possible.
+pub fn look_at_with_up(eye: &Vector3<f32>, target: &Vector3<f32>, up: &Vector3<f32>) -> Matrix4<f32> {
+    let forward = (*target - *eye).normalize();
+    let side = forward.cross(up).normalize();
+    let up = side.cross(&forward);
+
+    Matrix4::new(
+        side[0],          up[0],          -forward[0],       0.0,
+        side[1],          up[1],          -forward[1],       0.0,
+        side[2],          up[2],          -forward[2],       0.0,
+        -side.dot(eye),   -up.dot(eye),   forward.dot(eye),  1.0,
+    )
+}
+
+/// Compute an orthographic projection matrix mapping the box
+/// `[left, right] x [bottom, top] x [-near, -far]` to the canonical view volume.
+pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Matrix4<f32> {
+    let sx = 2.0 / (right - left);
+    let sy = 2.0 / (top - bottom);
+    let sz = -2.0 / (far - near);
+    let tx = -(right + left) / (right - left);
+    let ty = -(top + bottom) / (top - bottom);
+    let tz = -(far + near) / (far - near);
+
+    Matrix4::new(
+        sx,  0.0, 0.0, 0.0,
+        0.0, sy,  0.0, 0.0,
+        0.0, 0.0, sz,  0.0,
+        tx,  ty,  tz,  1.0,
+    )
+}
+
+/// Compute a perspective projection matrix with a vertical field of view of `fovy` radians.
+pub fn perspective(fovy: f32, aspect: f32, near: f32, far: f32) -> Matrix4<f32> {
+    let focal_length = 1.0 / (fovy / 2.0).tan();
+    let c2r2 = (far + near) / (near - far);
+    let c3r2 = (2.0 * far * near) / (near - far);
+
+    Matrix4::new(
+        focal_length / aspect, 0.0,          0.0,  0.0,
+        0.0,                   focal_length, 0.0,  0.0,
+        0.0,                   0.0,          c2r2, -1.0,
+        0.0,                   0.0,          c3r2, 0.0,
+    )
+}
+
+/// Compute the shadow map projection of a directional light. The light's view volume is a 
+/// box enclosing the sphere of radius `scene_radius` around the center of the scene.
+pub fn directional_shadow_projection(
+    light: &DirectionalLight<f32>, scene_center: &Vector3<f32>, scene_radius: f32) -> ShadowProjection {
+
+    let eye = *scene_center - light.direction * (2.0 * scene_radius);
+    let view_mat = look_at(&eye, scene_center);
+    let near = scene_radius;
+    let far = 3.0 * scene_radius;
+    let proj_mat = orthographic(-scene_radius, scene_radius, -scene_radius, scene_radius, near, far);
+    let light_size_uv = light.angular_radius.0.tan() / (2.0 * scene_radius);
+
+    ShadowProjection {
+        light_space_mat: proj_mat * view_mat,
+        perspective: false,
+        near: near,
+        far: far,
+        light_size_uv: light_size_uv,
+    }
+}
+
+/// Compute the shadow map projection of a spot light at `position`. The light's view
+/// volume is the frustum enclosing its outer cone.
+pub fn spot_shadow_projection(light: &SpotLight<f32>, position: &Vector3<f32>, far: f32) -> ShadowProjection {
+    let target = *position + light.direction;
+    let view_mat = look_at(position, &target);
+    let fovy = f32::min(2.0 * light.outer_cutoff.0, 3.0);
+    let near = 0.1;
+    let proj_mat = perspective(fovy, 1.0, near, far);
+    let light_size_uv = light.radius / (2.0 * (fovy / 2.0).tan());
+
+    ShadowProjection {
+        light_space_mat: proj_mat * view_mat,
+        perspective: true,
+        near: near,
+        far: far,
+        light_size_uv: light_size_uv,
+    }
+}
+
+/// Compute the transformations from world space to the clip space of each face of the 
+/// cube shadow map of a point light at `position`. The faces are ordered `+x, -x, +y, -y, 
+/// +z, -z`, and oriented the way OpenGL orients the faces of a cube map, so the shader
+/// can look up the texel of each face with the cube map face selection rules.
+pub fn point_light_space_mats(position: &Vector3<f32>, far: f32) -> [Matrix4<f32>; CUBE_FACES] {
+    let proj_mat = perspective(std::f32::consts::FRAC_PI_2, 1.0, POINT_SHADOW_NEAR_PLANE, far);
+    let faces = [
+        (Vector3::new( 1.0,  0.0,  0.0), Vector3::new(0.0, -1.0,  0.0)),
+        (Vector3::new(-1.0,  0.0,  0.0), Vector3::new(0.0, -1.0,  0.0)),
+        (Vector3::new( 0.0,  1.0,  0.0), Vector3::new(0.0,  0.0,  1.0)),
+        (Vector3::new( 0.0, -1.0,  0.0), Vector3::new(0.0,  0.0, -1.0)),
+        (Vector3::new( 0.0,  0.0,  1.0), Vector3::new(0.0, -1.0,  0.0)),
+        (Vector3::new( 0.0,  0.0, -1.0), Vector3::new(0.0, -1.0,  0.0)),
+    ];
+    let mut mats = [Matrix4::identity(); CUBE_FACES];
+    for (mat, (forward, up)) in mats.iter_mut().zip(faces.iter()) {
+        let target = *position + *forward;
+        *mat = proj_mat * look_at_with_up(position, &target, up);
+    }
+
+    mats
+}
+
+/// The shadow maps for the shadow casting lights in a scene. Each light renders the depth
+/// of the scene as seen from the light into its own layer of a depth texture array.
+pub struct ShadowMaps {
+    pub texture: Texture,
+    framebuffer: Framebuffer,
+}
+
+impl ShadowMaps {
+    pub fn new(size: u32, layers: u32) -> ShadowMaps {
+        let texture = Texture::new_2d_array(size, size, layers, TextureFormat::Depth32F);
+        // Percentage closer filtering compares individual depth samples, so they must not be
+        // blended. Everything outside of the light's view volume is lit.
+        texture.set_filter(gl::NEAREST);
+        texture.set_wrap(gl::CLAMP_TO_BORDER);
+        texture.set_border_color([1.0, 1.0, 1.0, 1.0]);
+        let framebuffer = Framebuffer::new(size, size);
+
+        ShadowMaps {
+            texture: texture,
+            framebuffer: framebuffer,
+        }
+    }
+
+    /// Bind the layer `layer` of the shadow maps as the render target and clear it.
+    pub fn bind_layer(&self, layer: u32) -> Result<(), FramebufferError> {
+        self.framebuffer.attach_depth_layer(&self.texture, layer)?;
+        self.framebuffer.bind();
+        unsafe {
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+
+        Ok(())
+    }
+}
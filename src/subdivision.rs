@@ -0,0 +1,420 @@
+use crate::mesh;
+use crate::vector::{
+    add,
+    dot,
+    scale,
+    sub,
+};
+use mini_obj::ObjMesh;
+use std::collections::hash_map::HashMap;
+use std::collections::HashSet;
+
+
+/// The schemes that smooth a mesh by subdividing it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SubdivisionScheme {
+    /// Loop subdivision, which splits each triangle into four triangles.
+    Loop,
+    /// Catmull-Clark subdivision, which splits each polygon into quads. Pairs of
+    /// triangles that form a flat quad are merged into the quad first, so that meshes
+    /// made of quads, like the box, subdivide the way they were modelled.
+    CatmullClark,
+}
+
+impl SubdivisionScheme {
+    /// Parse the name of a scheme as it appears on the command line.
+    pub fn parse(name: &str) -> Option<SubdivisionScheme> {
+        match name {
+            "loop" => Some(SubdivisionScheme::Loop),
+            "catmull-clark" => Some(SubdivisionScheme::CatmullClark),
+            _ => None,
+        }
+    }
+}
+
+/// Refine a mesh by `levels` levels of subdivision. The edges where faces meet at more
+/// than `crease_angle_degrees` degrees stay sharp, as do the edges on the boundary of
+/// the mesh. The subdivided mesh gets new smooth normals split along the creases, and
+/// loses its texture coordinates.
+pub fn subdivide(mesh: &ObjMesh, scheme: SubdivisionScheme, levels: usize, crease_angle_degrees: f32) -> ObjMesh {
+    let mut polygon_mesh = PolygonMesh::from_obj_mesh(mesh, crease_angle_degrees);
+    if scheme == SubdivisionScheme::CatmullClark {
+        polygon_mesh = polygon_mesh.merge_quads();
+    }
+    for _ in 0..levels {
+        polygon_mesh = match scheme {
+            SubdivisionScheme::Loop => polygon_mesh.loop_step(),
+            SubdivisionScheme::CatmullClark => polygon_mesh.catmull_clark_step(),
+        };
+    }
+
+    polygon_mesh.to_obj_mesh(crease_angle_degrees)
+}
+
+/// The cosine of the largest angle at which two faces of a flat quad can meet.
+const COS_FLAT_ANGLE: f32 = 0.9999;
+
+/// A mesh of polygons sharing their vertices, with some of its edges marked as creases.
+struct PolygonMesh {
+    points: Vec<[f32; 3]>,
+    /// The vertices of each face, counterclockwise when seen from the front.
+    faces: Vec<Vec<usize>>,
+    /// The creases, with the smaller vertex first.
+    creases: HashSet<(usize, usize)>,
+}
+
+impl PolygonMesh {
+    /// Join the triangles of a mesh at their shared positions, and mark the edges where
+    /// they meet at more than the crease angle as creases. When the mesh has normals,
+    /// each triangle is wound to face the same way as its normals, since the rules only
+    /// work on a consistently wound surface.
+    fn from_obj_mesh(mesh: &ObjMesh, crease_angle_degrees: f32) -> PolygonMesh {
+        let mesh_points = mesh::points(mesh);
+        let mesh_normals = mesh::normals(mesh);
+        let mut points = vec![];
+        let mut vertex_indices: HashMap<[u32; 3], usize> = HashMap::new();
+        let mut faces = vec![];
+        for (i, triangle) in mesh_points.chunks(3).enumerate() {
+            let mut face: Vec<usize> = triangle.iter()
+                .map(|point| {
+                    let key = [mesh::bits(point[0]), mesh::bits(point[1]), mesh::bits(point[2])];
+                    *vertex_indices.entry(key).or_insert_with(|| {
+                        points.push(*point);
+                        points.len() - 1
+                    })
+                })
+                .collect();
+            if mesh_normals.len() == mesh_points.len() {
+                let normal = mesh::face_normal(triangle[0], triangle[1], triangle[2]);
+                let vertex_normals = mesh_normals[(3 * i)..(3 * i + 3)].iter()
+                    .fold([0.0, 0.0, 0.0], |sum, &vertex_normal| add(sum, vertex_normal));
+                if dot(normal, vertex_normals) < 0.0 {
+                    face.swap(1, 2);
+                }
+            }
+            // Triangles collapsed onto an edge have no place in the surface.
+            if face[0] != face[1] && face[1] != face[2] && face[2] != face[0] {
+                faces.push(face);
+            }
+        }
+
+        let mut polygon_mesh = PolygonMesh {
+            points: points,
+            faces: faces,
+            creases: HashSet::new(),
+        };
+        let cos_crease_angle = crease_angle_degrees.to_radians().cos();
+        for (edge, edge_faces) in polygon_mesh.edge_faces().iter() {
+            let is_crease = match edge_faces.as_slice() {
+                &[face1, face2] => {
+                    let normal1 = polygon_mesh.face_normal(face1);
+                    let normal2 = polygon_mesh.face_normal(face2);
+                    dot(normal1, normal2) < cos_crease_angle
+                }
+                // Edges on the boundary, and edges shared by more than two faces.
+                _ => true,
+            };
+            if is_crease {
+                polygon_mesh.creases.insert(*edge);
+            }
+        }
+
+        polygon_mesh
+    }
+
+    /// The faces on each side of each edge.
+    fn edge_faces(&self) -> HashMap<(usize, usize), Vec<usize>> {
+        let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (i, face) in self.faces.iter().enumerate() {
+            for j in 0..face.len() {
+                edge_faces.entry(edge(face[j], face[(j + 1) % face.len()])).or_default().push(i);
+            }
+        }
+
+        edge_faces
+    }
+
+    /// The unit normal of a face, using Newell's method so that faces that are not quite
+    /// flat get an average normal.
+    fn face_normal(&self, face: usize) -> [f32; 3] {
+        let face = &self.faces[face];
+        let mut normal = [0.0, 0.0, 0.0];
+        for j in 0..face.len() {
+            let current = self.points[face[j]];
+            let next = self.points[face[(j + 1) % face.len()]];
+            normal[0] += (current[1] - next[1]) * (current[2] + next[2]);
+            normal[1] += (current[2] - next[2]) * (current[0] + next[0]);
+            normal[2] += (current[0] - next[0]) * (current[1] + next[1]);
+        }
+        let length = dot(normal, normal).sqrt();
+        if length > 0.0 {
+            [normal[0] / length, normal[1] / length, normal[2] / length]
+        } else {
+            normal
+        }
+    }
+
+    /// Merge each pair of triangles that forms a flat quad with its diagonal as the
+    /// longest edge of both triangles.
+    fn merge_quads(self) -> PolygonMesh {
+        let edge_faces = self.edge_faces();
+        let mut merged = vec![false; self.faces.len()];
+        let mut faces = vec![];
+        for (i, face) in self.faces.iter().enumerate() {
+            if merged[i] || face.len() != 3 {
+                continue;
+            }
+            // Rotate the triangle so that its longest edge runs from its second vertex
+            // to its third.
+            let longest = self.longest_edge(face);
+            let triangle = [face[(longest + 2) % 3], face[longest], face[(longest + 1) % 3]];
+            let diagonal = edge(triangle[1], triangle[2]);
+            let partner = edge_faces[&diagonal].iter()
+                .cloned()
+                .find(|&other| {
+                    let other_face = &self.faces[other];
+                    if other == i || merged[other] || other_face.len() != 3 {
+                        return false;
+                    }
+                    let other_longest = self.longest_edge(other_face);
+                    let other_diagonal = edge(other_face[other_longest], other_face[(other_longest + 1) % 3]);
+
+                    other_diagonal == diagonal && dot(self.face_normal(i), self.face_normal(other)) >= COS_FLAT_ANGLE
+                });
+            match partner {
+                Some(other) => {
+                    let opposite = self.faces[other].iter()
+                        .cloned()
+                        .find(|&vertex| vertex != triangle[1] && vertex != triangle[2])
+                        .unwrap();
+                    faces.push(vec![triangle[0], triangle[1], opposite, triangle[2]]);
+                    merged[i] = true;
+                    merged[other] = true;
+                }
+                None => {
+                    faces.push(face.clone());
+                    merged[i] = true;
+                }
+            }
+        }
+        // The diagonals of the quads are gone, so they are no longer creases.
+        let mut polygon_mesh = PolygonMesh {
+            points: self.points,
+            faces: faces,
+            creases: HashSet::new(),
+        };
+        let edges = polygon_mesh.edge_faces();
+        polygon_mesh.creases = self.creases.into_iter()
+            .filter(|crease| edges.contains_key(crease))
+            .collect();
+
+        polygon_mesh
+    }
+
+    /// The index of the first vertex of the longest edge of a triangle.
+    fn longest_edge(&self, triangle: &[usize]) -> usize {
+        let length_squared = |j: usize| {
+            let edge = sub(self.points[triangle[(j + 1) % 3]], self.points[triangle[j]]);
+            dot(edge, edge)
+        };
+
+        (0..3).fold(0, |longest, j| if length_squared(j) > length_squared(longest) { j } else { longest })
+    }
+
+    /// The neighbors of each vertex along its creases, and along all of its edges.
+    fn neighbors(&self, edge_faces: &HashMap<(usize, usize), Vec<usize>>) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+        let mut crease_neighbors = vec![vec![]; self.points.len()];
+        let mut neighbors = vec![vec![]; self.points.len()];
+        for &(a, b) in edge_faces.keys() {
+            neighbors[a].push(b);
+            neighbors[b].push(a);
+            if self.creases.contains(&(a, b)) {
+                crease_neighbors[a].push(b);
+                crease_neighbors[b].push(a);
+            }
+        }
+
+        (crease_neighbors, neighbors)
+    }
+
+    /// Split each triangle into four, following Loop's rules with the crease rules of
+    /// Hoppe et al.
+    fn loop_step(&self) -> PolygonMesh {
+        let edge_faces = self.edge_faces();
+        let (crease_neighbors, neighbors) = self.neighbors(&edge_faces);
+
+        // Move the existing vertices first.
+        let mut points: Vec<[f32; 3]> = (0..self.points.len())
+            .map(|vertex| {
+                let point = self.points[vertex];
+                match crease_neighbors[vertex].len() {
+                    0 | 1 => {
+                        let n = neighbors[vertex].len();
+                        let beta = if n == 3 { 3.0 / 16.0 } else { 3.0 / (8.0 * n as f32) };
+                        let mut sum = scale(point, 1.0 - n as f32 * beta);
+                        for &neighbor in neighbors[vertex].iter() {
+                            sum = add(sum, scale(self.points[neighbor], beta));
+                        }
+                        sum
+                    }
+                    2 => self.crease_vertex(vertex, &crease_neighbors[vertex]),
+                    _ => point,
+                }
+            })
+            .collect();
+
+        let mut edge_points = HashMap::new();
+        let mut creases = HashSet::new();
+        for (&(a, b), faces) in edge_faces.iter() {
+            let midpoint = scale(add(self.points[a], self.points[b]), 0.5);
+            let point = if self.creases.contains(&(a, b)) {
+                midpoint
+            } else {
+                let opposite = |face: usize| {
+                    self.faces[face].iter().cloned().find(|&vertex| vertex != a && vertex != b).unwrap()
+                };
+                let wings = add(self.points[opposite(faces[0])], self.points[opposite(faces[1])]);
+                add(scale(midpoint, 0.75), scale(wings, 0.125))
+            };
+            points.push(point);
+            let edge_point = points.len() - 1;
+            edge_points.insert((a, b), edge_point);
+            if self.creases.contains(&(a, b)) {
+                creases.insert(edge(a, edge_point));
+                creases.insert(edge(edge_point, b));
+            }
+        }
+
+        let mut faces = Vec::with_capacity(4 * self.faces.len());
+        for face in self.faces.iter() {
+            let (v0, v1, v2) = (face[0], face[1], face[2]);
+            let e01 = edge_points[&edge(v0, v1)];
+            let e12 = edge_points[&edge(v1, v2)];
+            let e20 = edge_points[&edge(v2, v0)];
+            faces.push(vec![v0, e01, e20]);
+            faces.push(vec![v1, e12, e01]);
+            faces.push(vec![v2, e20, e12]);
+            faces.push(vec![e01, e12, e20]);
+        }
+
+        PolygonMesh {
+            points: points,
+            faces: faces,
+            creases: creases,
+        }
+    }
+
+    /// Split each face into a quad for each of its corners, following the rules of
+    /// Catmull and Clark with the crease rules of DeRose et al.
+    fn catmull_clark_step(&self) -> PolygonMesh {
+        let edge_faces = self.edge_faces();
+        let (crease_neighbors, neighbors) = self.neighbors(&edge_faces);
+        let face_points: Vec<[f32; 3]> = self.faces.iter()
+            .map(|face| {
+                let sum = face.iter().fold([0.0, 0.0, 0.0], |sum, &vertex| add(sum, self.points[vertex]));
+                scale(sum, 1.0 / face.len() as f32)
+            })
+            .collect();
+        let mut vertex_faces = vec![vec![]; self.points.len()];
+        for (i, face) in self.faces.iter().enumerate() {
+            for &vertex in face.iter() {
+                vertex_faces[vertex].push(i);
+            }
+        }
+
+        let mut points: Vec<[f32; 3]> = (0..self.points.len())
+            .map(|vertex| {
+                let point = self.points[vertex];
+                match crease_neighbors[vertex].len() {
+                    0 | 1 => {
+                        let n = neighbors[vertex].len() as f32;
+                        let faces = &vertex_faces[vertex];
+                        let face_average = faces.iter()
+                            .fold([0.0, 0.0, 0.0], |sum, &face| add(sum, face_points[face]));
+                        let face_average = scale(face_average, 1.0 / faces.len() as f32);
+                        let edge_average = neighbors[vertex].iter()
+                            .fold([0.0, 0.0, 0.0], |sum, &neighbor| add(sum, scale(add(point, self.points[neighbor]), 0.5)));
+                        let edge_average = scale(edge_average, 1.0 / n);
+
+                        scale(add(add(face_average, scale(edge_average, 2.0)), scale(point, n - 3.0)), 1.0 / n)
+                    }
+                    2 => self.crease_vertex(vertex, &crease_neighbors[vertex]),
+                    _ => point,
+                }
+            })
+            .collect();
+
+        let first_face_point = points.len();
+        points.extend_from_slice(&face_points);
+
+        let mut edge_points = HashMap::new();
+        let mut creases = HashSet::new();
+        for (&(a, b), faces) in edge_faces.iter() {
+            let midpoint = scale(add(self.points[a], self.points[b]), 0.5);
+            let point = if self.creases.contains(&(a, b)) {
+                midpoint
+            } else {
+                let wings = add(face_points[faces[0]], face_points[faces[1]]);
+                scale(add(midpoint, scale(wings, 0.5)), 0.5)
+            };
+            points.push(point);
+            let edge_point = points.len() - 1;
+            edge_points.insert((a, b), edge_point);
+            if self.creases.contains(&(a, b)) {
+                creases.insert(edge(a, edge_point));
+                creases.insert(edge(edge_point, b));
+            }
+        }
+
+        let mut faces = vec![];
+        for (i, face) in self.faces.iter().enumerate() {
+            let face_point = first_face_point + i;
+            for j in 0..face.len() {
+                let previous = face[(j + face.len() - 1) % face.len()];
+                let next = face[(j + 1) % face.len()];
+                faces.push(vec![
+                    face[j],
+                    edge_points[&edge(face[j], next)],
+                    face_point,
+                    edge_points[&edge(previous, face[j])],
+                ]);
+            }
+        }
+
+        PolygonMesh {
+            points: points,
+            faces: faces,
+            creases: creases,
+        }
+    }
+
+    /// The new position of a vertex on a crease, which follows the subdivision rules of
+    /// a curve along the crease.
+    fn crease_vertex(&self, vertex: usize, crease_neighbors: &[usize]) -> [f32; 3] {
+        let ends = add(self.points[crease_neighbors[0]], self.points[crease_neighbors[1]]);
+
+        add(scale(self.points[vertex], 0.75), scale(ends, 0.125))
+    }
+
+    /// Split the faces into triangles for drawing, with new normals.
+    fn to_obj_mesh(&self, crease_angle_degrees: f32) -> ObjMesh {
+        let mut points = vec![];
+        for face in self.faces.iter() {
+            for j in 1..(face.len() - 1) {
+                points.push(self.points[face[0]]);
+                points.push(self.points[face[j]]);
+                points.push(self.points[face[j + 1]]);
+            }
+        }
+        let normals = mesh::smooth_normals(&points, crease_angle_degrees);
+
+        ObjMesh::new(points, vec![], normals)
+    }
+}
+
+/// An edge of a mesh, with the smaller vertex first.
+#[inline]
+fn edge(a: usize, b: usize) -> (usize, usize) {
+    if a < b { (a, b) } else { (b, a) }
+}
+
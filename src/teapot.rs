@@ -0,0 +1,242 @@
+use crate::primitives::MeshBuilder;
+use crate::vector::{
+    cross,
+    length,
+    normalize,
+};
+use mini_obj::ObjMesh;
+
+
+/// The control points of Martin Newell's teapot, with the z axis pointing up.
+const TEAPOT_VERTICES: [[f32; 3]; 306] = [
+    [    1.4,     0.0,     2.4], [    1.4,  -0.784,     2.4], [  0.784,    -1.4,     2.4],
+    [    0.0,    -1.4,     2.4], [ 1.3375,     0.0, 2.53125], [ 1.3375,  -0.749, 2.53125],
+    [  0.749, -1.3375, 2.53125], [    0.0, -1.3375, 2.53125], [ 1.4375,     0.0, 2.53125],
+    [ 1.4375,  -0.805, 2.53125], [  0.805, -1.4375, 2.53125], [    0.0, -1.4375, 2.53125],
+    [    1.5,     0.0,     2.4], [    1.5,   -0.84,     2.4], [   0.84,    -1.5,     2.4],
+    [    0.0,    -1.5,     2.4], [ -0.784,    -1.4,     2.4], [   -1.4,  -0.784,     2.4],
+    [   -1.4,     0.0,     2.4], [ -0.749, -1.3375, 2.53125], [-1.3375,  -0.749, 2.53125],
+    [-1.3375,     0.0, 2.53125], [ -0.805, -1.4375, 2.53125], [-1.4375,  -0.805, 2.53125],
+    [-1.4375,     0.0, 2.53125], [  -0.84,    -1.5,     2.4], [   -1.5,   -0.84,     2.4],
+    [   -1.5,     0.0,     2.4], [   -1.4,   0.784,     2.4], [ -0.784,     1.4,     2.4],
+    [    0.0,     1.4,     2.4], [-1.3375,   0.749, 2.53125], [ -0.749,  1.3375, 2.53125],
+    [    0.0,  1.3375, 2.53125], [-1.4375,   0.805, 2.53125], [ -0.805,  1.4375, 2.53125],
+    [    0.0,  1.4375, 2.53125], [   -1.5,    0.84,     2.4], [  -0.84,     1.5,     2.4],
+    [    0.0,     1.5,     2.4], [  0.784,     1.4,     2.4], [    1.4,   0.784,     2.4],
+    [  0.749,  1.3375, 2.53125], [ 1.3375,   0.749, 2.53125], [  0.805,  1.4375, 2.53125],
+    [ 1.4375,   0.805, 2.53125], [   0.84,     1.5,     2.4], [    1.5,    0.84,     2.4],
+    [   1.75,     0.0,   1.875], [   1.75,   -0.98,   1.875], [   0.98,   -1.75,   1.875],
+    [    0.0,   -1.75,   1.875], [    2.0,     0.0,    1.35], [    2.0,   -1.12,    1.35],
+    [   1.12,    -2.0,    1.35], [    0.0,    -2.0,    1.35], [    2.0,     0.0,     0.9],
+    [    2.0,   -1.12,     0.9], [   1.12,    -2.0,     0.9], [    0.0,    -2.0,     0.9],
+    [  -0.98,   -1.75,   1.875], [  -1.75,   -0.98,   1.875], [  -1.75,     0.0,   1.875],
+    [  -1.12,    -2.0,    1.35], [   -2.0,   -1.12,    1.35], [   -2.0,     0.0,    1.35],
+    [  -1.12,    -2.0,     0.9], [   -2.0,   -1.12,     0.9], [   -2.0,     0.0,     0.9],
+    [  -1.75,    0.98,   1.875], [  -0.98,    1.75,   1.875], [    0.0,    1.75,   1.875],
+    [   -2.0,    1.12,    1.35], [  -1.12,     2.0,    1.35], [    0.0,     2.0,    1.35],
+    [   -2.0,    1.12,     0.9], [  -1.12,     2.0,     0.9], [    0.0,     2.0,     0.9],
+    [   0.98,    1.75,   1.875], [   1.75,    0.98,   1.875], [   1.12,     2.0,    1.35],
+    [    2.0,    1.12,    1.35], [   1.12,     2.0,     0.9], [    2.0,    1.12,     0.9],
+    [    2.0,     0.0,    0.45], [    2.0,   -1.12,    0.45], [   1.12,    -2.0,    0.45],
+    [    0.0,    -2.0,    0.45], [    1.5,     0.0,   0.225], [    1.5,   -0.84,   0.225],
+    [   0.84,    -1.5,   0.225], [    0.0,    -1.5,   0.225], [    1.5,     0.0,    0.15],
+    [    1.5,   -0.84,    0.15], [   0.84,    -1.5,    0.15], [    0.0,    -1.5,    0.15],
+    [  -1.12,    -2.0,    0.45], [   -2.0,   -1.12,    0.45], [   -2.0,     0.0,    0.45],
+    [  -0.84,    -1.5,   0.225], [   -1.5,   -0.84,   0.225], [   -1.5,     0.0,   0.225],
+    [  -0.84,    -1.5,    0.15], [   -1.5,   -0.84,    0.15], [   -1.5,     0.0,    0.15],
+    [   -2.0,    1.12,    0.45], [  -1.12,     2.0,    0.45], [    0.0,     2.0,    0.45],
+    [   -1.5,    0.84,   0.225], [  -0.84,     1.5,   0.225], [    0.0,     1.5,   0.225],
+    [   -1.5,    0.84,    0.15], [  -0.84,     1.5,    0.15], [    0.0,     1.5,    0.15],
+    [   1.12,     2.0,    0.45], [    2.0,    1.12,    0.45], [   0.84,     1.5,   0.225],
+    [    1.5,    0.84,   0.225], [   0.84,     1.5,    0.15], [    1.5,    0.84,    0.15],
+    [   -1.6,     0.0,   2.025], [   -1.6,    -0.3,   2.025], [   -1.5,    -0.3,    2.25],
+    [   -1.5,     0.0,    2.25], [   -2.3,     0.0,   2.025], [   -2.3,    -0.3,   2.025],
+    [   -2.5,    -0.3,    2.25], [   -2.5,     0.0,    2.25], [   -2.7,     0.0,   2.025],
+    [   -2.7,    -0.3,   2.025], [   -3.0,    -0.3,    2.25], [   -3.0,     0.0,    2.25],
+    [   -2.7,     0.0,     1.8], [   -2.7,    -0.3,     1.8], [   -3.0,    -0.3,     1.8],
+    [   -3.0,     0.0,     1.8], [   -1.5,     0.3,    2.25], [   -1.6,     0.3,   2.025],
+    [   -2.5,     0.3,    2.25], [   -2.3,     0.3,   2.025], [   -3.0,     0.3,    2.25],
+    [   -2.7,     0.3,   2.025], [   -3.0,     0.3,     1.8], [   -2.7,     0.3,     1.8],
+    [   -2.7,     0.0,   1.575], [   -2.7,    -0.3,   1.575], [   -3.0,    -0.3,    1.35],
+    [   -3.0,     0.0,    1.35], [   -2.5,     0.0,   1.125], [   -2.5,    -0.3,   1.125],
+    [  -2.65,    -0.3,  0.9375], [  -2.65,     0.0,  0.9375], [   -2.0,    -0.3,     0.9],
+    [   -1.9,    -0.3,     0.6], [   -1.9,     0.0,     0.6], [   -3.0,     0.3,    1.35],
+    [   -2.7,     0.3,   1.575], [  -2.65,     0.3,  0.9375], [   -2.5,     0.3,   1.125],
+    [   -1.9,     0.3,     0.6], [   -2.0,     0.3,     0.9], [    1.7,     0.0,   1.425],
+    [    1.7,   -0.66,   1.425], [    1.7,   -0.66,     0.6], [    1.7,     0.0,     0.6],
+    [    2.6,     0.0,   1.425], [    2.6,   -0.66,   1.425], [    3.1,   -0.66,   0.825],
+    [    3.1,     0.0,   0.825], [    2.3,     0.0,     2.1], [    2.3,   -0.25,     2.1],
+    [    2.4,   -0.25,   2.025], [    2.4,     0.0,   2.025], [    2.7,     0.0,     2.4],
+    [    2.7,   -0.25,     2.4], [    3.3,   -0.25,     2.4], [    3.3,     0.0,     2.4],
+    [    1.7,    0.66,     0.6], [    1.7,    0.66,   1.425], [    3.1,    0.66,   0.825],
+    [    2.6,    0.66,   1.425], [    2.4,    0.25,   2.025], [    2.3,    0.25,     2.1],
+    [    3.3,    0.25,     2.4], [    2.7,    0.25,     2.4], [    2.8,     0.0,   2.475],
+    [    2.8,   -0.25,   2.475], [  3.525,   -0.25, 2.49375], [  3.525,     0.0, 2.49375],
+    [    2.9,     0.0,   2.475], [    2.9,   -0.15,   2.475], [   3.45,   -0.15,  2.5125],
+    [   3.45,     0.0,  2.5125], [    2.8,     0.0,     2.4], [    2.8,   -0.15,     2.4],
+    [    3.2,   -0.15,     2.4], [    3.2,     0.0,     2.4], [  3.525,    0.25, 2.49375],
+    [    2.8,    0.25,   2.475], [   3.45,    0.15,  2.5125], [    2.9,    0.15,   2.475],
+    [    3.2,    0.15,     2.4], [    2.8,    0.15,     2.4], [    0.0,     0.0,    3.15],
+    [    0.0,  -0.002,    3.15], [  0.002,     0.0,    3.15], [    0.8,     0.0,    3.15],
+    [    0.8,   -0.45,    3.15], [   0.45,    -0.8,    3.15], [    0.0,    -0.8,    3.15],
+    [    0.0,     0.0,    2.85], [    0.2,     0.0,     2.7], [    0.2,  -0.112,     2.7],
+    [  0.112,    -0.2,     2.7], [    0.0,    -0.2,     2.7], [ -0.002,     0.0,    3.15],
+    [  -0.45,    -0.8,    3.15], [   -0.8,   -0.45,    3.15], [   -0.8,     0.0,    3.15],
+    [ -0.112,    -0.2,     2.7], [   -0.2,  -0.112,     2.7], [   -0.2,     0.0,     2.7],
+    [    0.0,   0.002,    3.15], [   -0.8,    0.45,    3.15], [  -0.45,     0.8,    3.15],
+    [    0.0,     0.8,    3.15], [   -0.2,   0.112,     2.7], [ -0.112,     0.2,     2.7],
+    [    0.0,     0.2,     2.7], [   0.45,     0.8,    3.15], [    0.8,    0.45,    3.15],
+    [  0.112,     0.2,     2.7], [    0.2,   0.112,     2.7], [    0.4,     0.0,    2.55],
+    [    0.4,  -0.224,    2.55], [  0.224,    -0.4,    2.55], [    0.0,    -0.4,    2.55],
+    [    1.3,     0.0,    2.55], [    1.3,  -0.728,    2.55], [  0.728,    -1.3,    2.55],
+    [    0.0,    -1.3,    2.55], [    1.3,     0.0,     2.4], [    1.3,  -0.728,     2.4],
+    [  0.728,    -1.3,     2.4], [    0.0,    -1.3,     2.4], [ -0.224,    -0.4,    2.55],
+    [   -0.4,  -0.224,    2.55], [   -0.4,     0.0,    2.55], [ -0.728,    -1.3,    2.55],
+    [   -1.3,  -0.728,    2.55], [   -1.3,     0.0,    2.55], [ -0.728,    -1.3,     2.4],
+    [   -1.3,  -0.728,     2.4], [   -1.3,     0.0,     2.4], [   -0.4,   0.224,    2.55],
+    [ -0.224,     0.4,    2.55], [    0.0,     0.4,    2.55], [   -1.3,   0.728,    2.55],
+    [ -0.728,     1.3,    2.55], [    0.0,     1.3,    2.55], [   -1.3,   0.728,     2.4],
+    [ -0.728,     1.3,     2.4], [    0.0,     1.3,     2.4], [  0.224,     0.4,    2.55],
+    [    0.4,   0.224,    2.55], [  0.728,     1.3,    2.55], [    1.3,   0.728,    2.55],
+    [  0.728,     1.3,     2.4], [    1.3,   0.728,     2.4], [    0.0,     0.0,     0.0],
+    [    1.5,     0.0,    0.15], [    1.5,    0.84,    0.15], [   0.84,     1.5,    0.15],
+    [    0.0,     1.5,    0.15], [    1.5,     0.0,   0.075], [    1.5,    0.84,   0.075],
+    [   0.84,     1.5,   0.075], [    0.0,     1.5,   0.075], [  1.425,     0.0,     0.0],
+    [  1.425,   0.798,     0.0], [  0.798,   1.425,     0.0], [    0.0,   1.425,     0.0],
+    [  -0.84,     1.5,    0.15], [   -1.5,    0.84,    0.15], [   -1.5,     0.0,    0.15],
+    [  -0.84,     1.5,   0.075], [   -1.5,    0.84,   0.075], [   -1.5,     0.0,   0.075],
+    [ -0.798,   1.425,     0.0], [ -1.425,   0.798,     0.0], [ -1.425,     0.0,     0.0],
+    [   -1.5,   -0.84,    0.15], [  -0.84,    -1.5,    0.15], [    0.0,    -1.5,    0.15],
+    [   -1.5,   -0.84,   0.075], [  -0.84,    -1.5,   0.075], [    0.0,    -1.5,   0.075],
+    [ -1.425,  -0.798,     0.0], [ -0.798,  -1.425,     0.0], [    0.0,  -1.425,     0.0],
+    [   0.84,    -1.5,    0.15], [    1.5,   -0.84,    0.15], [   0.84,    -1.5,   0.075],
+    [    1.5,   -0.84,   0.075], [  0.798,  -1.425,     0.0], [  1.425,  -0.798,     0.0],
+];
+
+/// The 32 bicubic Bezier patches of the teapot. Each patch lists its 4 by 4 control
+/// points row by row, numbered from one as in the original data set.
+const TEAPOT_PATCHES: [[usize; 16]; 32] = [
+    // The rim.
+    [  1,   2,   3,   4,   5,   6,   7,   8,   9,  10,  11,  12,  13,  14,  15,  16],
+    [  4,  17,  18,  19,   8,  20,  21,  22,  12,  23,  24,  25,  16,  26,  27,  28],
+    [ 19,  29,  30,  31,  22,  32,  33,  34,  25,  35,  36,  37,  28,  38,  39,  40],
+    [ 31,  41,  42,   1,  34,  43,  44,   5,  37,  45,  46,   9,  40,  47,  48,  13],
+    // The body.
+    [ 13,  14,  15,  16,  49,  50,  51,  52,  53,  54,  55,  56,  57,  58,  59,  60],
+    [ 16,  26,  27,  28,  52,  61,  62,  63,  56,  64,  65,  66,  60,  67,  68,  69],
+    [ 28,  38,  39,  40,  63,  70,  71,  72,  66,  73,  74,  75,  69,  76,  77,  78],
+    [ 40,  47,  48,  13,  72,  79,  80,  49,  75,  81,  82,  53,  78,  83,  84,  57],
+    [ 57,  58,  59,  60,  85,  86,  87,  88,  89,  90,  91,  92,  93,  94,  95,  96],
+    [ 60,  67,  68,  69,  88,  97,  98,  99,  92, 100, 101, 102,  96, 103, 104, 105],
+    [ 69,  76,  77,  78,  99, 106, 107, 108, 102, 109, 110, 111, 105, 112, 113, 114],
+    [ 78,  83,  84,  57, 108, 115, 116,  85, 111, 117, 118,  89, 114, 119, 120,  93],
+    // The handle.
+    [121, 122, 123, 124, 125, 126, 127, 128, 129, 130, 131, 132, 133, 134, 135, 136],
+    [124, 137, 138, 121, 128, 139, 140, 125, 132, 141, 142, 129, 136, 143, 144, 133],
+    [133, 134, 135, 136, 145, 146, 147, 148, 149, 150, 151, 152,  69, 153, 154, 155],
+    [136, 143, 144, 133, 148, 156, 157, 145, 152, 158, 159, 149, 155, 160, 161,  69],
+    // The spout.
+    [162, 163, 164, 165, 166, 167, 168, 169, 170, 171, 172, 173, 174, 175, 176, 177],
+    [165, 178, 179, 162, 169, 180, 181, 166, 173, 182, 183, 170, 177, 184, 185, 174],
+    [174, 175, 176, 177, 186, 187, 188, 189, 190, 191, 192, 193, 194, 195, 196, 197],
+    [177, 184, 185, 174, 189, 198, 199, 186, 193, 200, 201, 190, 197, 202, 203, 194],
+    // The lid.
+    [204, 204, 204, 204, 207, 208, 209, 210, 211, 211, 211, 211, 212, 213, 214, 215],
+    [204, 204, 204, 204, 210, 217, 218, 219, 211, 211, 211, 211, 215, 220, 221, 222],
+    [204, 204, 204, 204, 219, 224, 225, 226, 211, 211, 211, 211, 222, 227, 228, 229],
+    [204, 204, 204, 204, 226, 230, 231, 207, 211, 211, 211, 211, 229, 232, 233, 212],
+    [212, 213, 214, 215, 234, 235, 236, 237, 238, 239, 240, 241, 242, 243, 244, 245],
+    [215, 220, 221, 222, 237, 246, 247, 248, 241, 249, 250, 251, 245, 252, 253, 254],
+    [222, 227, 228, 229, 248, 255, 256, 257, 251, 258, 259, 260, 254, 261, 262, 263],
+    [229, 232, 233, 212, 257, 264, 265, 234, 260, 266, 267, 238, 263, 268, 269, 242],
+    // The bottom.
+    [270, 270, 270, 270, 279, 280, 281, 282, 275, 276, 277, 278, 271, 272, 273, 274],
+    [270, 270, 270, 270, 282, 289, 290, 291, 278, 286, 287, 288, 274, 283, 284, 285],
+    [270, 270, 270, 270, 291, 298, 299, 300, 288, 295, 296, 297, 285, 292, 293, 294],
+    [270, 270, 270, 270, 300, 305, 306, 279, 297, 303, 304, 275, 294, 301, 302, 271],
+];
+
+/// Create the Utah teapot, splitting each of its patches into `level_of_detail` by 
+/// `level_of_detail` quads. The teapot stands on the origin with its spout pointing 
+/// along the x axis, and is 3.15 units tall like the original.
+pub fn teapot(level_of_detail: usize) -> ObjMesh {
+    assert!(level_of_detail >= 1, "A teapot needs a level of detail of at least 1.");
+    let mut builder = MeshBuilder::new();
+    for patch in TEAPOT_PATCHES.iter() {
+        let mut control_points = [[0.0; 3]; 16];
+        for (control_point, &index) in control_points.iter_mut().zip(patch.iter()) {
+            // The demo has the y axis pointing up.
+            let vertex = TEAPOT_VERTICES[index - 1];
+            *control_point = [vertex[0], vertex[2], -vertex[1]];
+        }
+        tessellate_bezier_patch(&mut builder, &control_points, level_of_detail);
+    }
+
+    builder.build()
+}
+
+/// Add a bicubic Bezier patch to a mesh as a grid of `level_of_detail` by 
+/// `level_of_detail` quads, with the exact normals of the surface at each vertex. The
+/// control points are listed row by row.
+pub fn tessellate_bezier_patch(builder: &mut MeshBuilder, control_points: &[[f32; 3]; 16], level_of_detail: usize) {
+    builder.surface(level_of_detail, level_of_detail, |u, v| {
+        let (point, normal) = evaluate_bezier_patch(control_points, u, v);
+        match normalize(normal) {
+            Some(unit_normal) if length(normal) > MIN_NORMAL_LENGTH => (point, unit_normal),
+            _ => {
+                // Patches that collapse a whole row of control points into one point, 
+                // like the top of the lid, have no tangent plane there. The normal just 
+                // inside the patch is the limit of the normals approaching it.
+                let (_, normal) = evaluate_bezier_patch(
+                    control_points, u + NUDGE * (0.5 - u), v + NUDGE * (0.5 - v)
+                );
+                (point, normalize(normal).unwrap_or([0.0, 1.0, 0.0]))
+            }
+        }
+    })
+}
+
+/// How far towards the middle of a patch to look for the normal at a point where the 
+/// patch has none, as a fraction of the distance.
+const NUDGE: f32 = 1e-3;
+
+/// The length of a normal below which it is rounding error rather than the normal of a
+/// tangent plane.
+const MIN_NORMAL_LENGTH: f32 = 1e-6;
+
+/// The point of a bicubic Bezier patch at the parameters `u` along each row and `v` 
+/// across the rows, and the normal there, which is not normalized.
+fn evaluate_bezier_patch(control_points: &[[f32; 3]; 16], u: f32, v: f32) -> ([f32; 3], [f32; 3]) {
+    let (basis_u, derivative_u) = (bernstein(u), bernstein_derivative(u));
+    let (basis_v, derivative_v) = (bernstein(v), bernstein_derivative(v));
+    let mut point = [0.0; 3];
+    let mut tangent_u = [0.0; 3];
+    let mut tangent_v = [0.0; 3];
+    for i in 0..4 {
+        for j in 0..4 {
+            let control_point = control_points[4 * i + j];
+            for axis in 0..3 {
+                point[axis] += basis_v[i] * basis_u[j] * control_point[axis];
+                tangent_u[axis] += basis_v[i] * derivative_u[j] * control_point[axis];
+                tangent_v[axis] += derivative_v[i] * basis_u[j] * control_point[axis];
+            }
+        }
+    }
+
+    (point, cross(tangent_u, tangent_v))
+}
+
+/// The cubic Bernstein polynomials at `t`.
+#[inline]
+fn bernstein(t: f32) -> [f32; 4] {
+    let s = 1.0 - t;
+    [s * s * s, 3.0 * t * s * s, 3.0 * t * t * s, t * t * t]
+}
+
+/// The derivatives of the cubic Bernstein polynomials at `t`.
+#[inline]
+fn bernstein_derivative(t: f32) -> [f32; 4] {
+    let s = 1.0 - t;
+    [-3.0 * s * s, 3.0 * s * s - 6.0 * t * s, 6.0 * t * s - 3.0 * t * t, 3.0 * t * t]
+}
+
@@ -0,0 +1,138 @@
+/// The width and height in pixels of each glyph of the font.
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+/// The space in pixels between neighboring glyphs, both on screen and in the atlas.
+const GLYPH_SPACING: usize = 1;
+/// The first character with a glyph. The font has a glyph for each lowercase letter, 
+/// which is all that material names need. Every other character is drawn as a space.
+const FIRST_CHARACTER: u8 = b'a';
+
+
+/// The glyphs of the letters, one row of pixels per entry from the top down, with the
+/// leftmost pixel in the highest bit.
+const GLYPHS: [[u8; GLYPH_HEIGHT]; 26] = [
+    // a
+    [0b00000, 0b00000, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111],
+    // b
+    [0b10000, 0b10000, 0b11110, 0b10001, 0b10001, 0b10001, 0b11110],
+    // c
+    [0b00000, 0b00000, 0b01110, 0b10000, 0b10000, 0b10001, 0b01110],
+    // d
+    [0b00001, 0b00001, 0b01111, 0b10001, 0b10001, 0b10001, 0b01111],
+    // e
+    [0b00000, 0b00000, 0b01110, 0b10001, 0b11111, 0b10000, 0b01110],
+    // f
+    [0b00110, 0b01001, 0b01000, 0b11100, 0b01000, 0b01000, 0b01000],
+    // g
+    [0b00000, 0b01111, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110],
+    // h
+    [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001],
+    // i
+    [0b00100, 0b00000, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110],
+    // j
+    [0b00010, 0b00000, 0b00110, 0b00010, 0b00010, 0b10010, 0b01100],
+    // k
+    [0b10000, 0b10000, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010],
+    // l
+    [0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+    // m
+    [0b00000, 0b00000, 0b11010, 0b10101, 0b10101, 0b10001, 0b10001],
+    // n
+    [0b00000, 0b00000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001],
+    // o
+    [0b00000, 0b00000, 0b01110, 0b10001, 0b10001, 0b10001, 0b01110],
+    // p
+    [0b00000, 0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000],
+    // q
+    [0b00000, 0b01111, 0b10001, 0b10001, 0b01111, 0b00001, 0b00001],
+    // r
+    [0b00000, 0b00000, 0b10110, 0b11001, 0b10000, 0b10000, 0b10000],
+    // s
+    [0b00000, 0b00000, 0b01110, 0b10000, 0b01110, 0b00001, 0b11110],
+    // t
+    [0b01000, 0b01000, 0b11100, 0b01000, 0b01000, 0b01001, 0b00110],
+    // u
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b10011, 0b01101],
+    // v
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+    // w
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10101, 0b10101, 0b01010],
+    // x
+    [0b00000, 0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001],
+    // y
+    [0b00000, 0b10001, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110],
+    // z
+    [0b00000, 0b00000, 0b11111, 0b00010, 0b00100, 0b01000, 0b11111],
+];
+
+/// The glyphs of the font packed side by side into a single row, as a single channel 
+/// image for a texture.
+pub struct FontAtlas {
+    pub width: u32,
+    pub height: u32,
+    /// The coverage of each pixel in row major order, starting from the bottom row like
+    /// OpenGL textures do.
+    pub pixels: Vec<f32>,
+}
+
+impl FontAtlas {
+    pub fn new() -> FontAtlas {
+        let width = GLYPHS.len() * (GLYPH_WIDTH + GLYPH_SPACING);
+        let height = GLYPH_HEIGHT;
+        let mut pixels = vec![0.0; width * height];
+        for (i, glyph) in GLYPHS.iter().enumerate() {
+            for (row, bits) in glyph.iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                        let x = i * (GLYPH_WIDTH + GLYPH_SPACING) + column;
+                        let y = GLYPH_HEIGHT - 1 - row;
+                        pixels[y * width + x] = 1.0;
+                    }
+                }
+            }
+        }
+
+        FontAtlas {
+            width: width as u32,
+            height: height as u32,
+            pixels: pixels,
+        }
+    }
+}
+
+/// The width in pixels of a line of text drawn with each glyph pixel `scale` pixels wide.
+pub fn text_width(text: &str, scale: f32) -> f32 {
+    let characters = text.chars().count();
+    if characters == 0 {
+        return 0.0;
+    }
+
+    scale * (characters * (GLYPH_WIDTH + GLYPH_SPACING) - GLYPH_SPACING) as f32
+}
+
+/// The vertices of the quads drawing a line of text whose bottom left corner lies at 
+/// the pixel `(x, y)`, counted from the bottom left corner of the window. Each vertex 
+/// holds its position in pixels followed by its texture coordinates in the font atlas.
+pub fn text_vertices(text: &str, x: f32, y: f32, scale: f32) -> Vec<[f32; 4]> {
+    let atlas_width = (GLYPHS.len() * (GLYPH_WIDTH + GLYPH_SPACING)) as f32;
+    let width = scale * GLYPH_WIDTH as f32;
+    let height = scale * GLYPH_HEIGHT as f32;
+    let mut vertices = vec![];
+    for (i, character) in text.bytes().enumerate() {
+        if !character.is_ascii_lowercase() {
+            continue;
+        }
+        let glyph = (character - FIRST_CHARACTER) as usize;
+        let left = x + scale * (i * (GLYPH_WIDTH + GLYPH_SPACING)) as f32;
+        let right = left + width;
+        let top = y + height;
+        let u_left = (glyph * (GLYPH_WIDTH + GLYPH_SPACING)) as f32 / atlas_width;
+        let u_right = u_left + GLYPH_WIDTH as f32 / atlas_width;
+        vertices.extend_from_slice(&[
+            [left, y, u_left, 0.0], [right, y, u_right, 0.0], [right, top, u_right, 1.0],
+            [left, y, u_left, 0.0], [right, top, u_right, 1.0], [left, top, u_left, 1.0],
+        ]);
+    }
+
+    vertices
+}
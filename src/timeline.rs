@@ -0,0 +1,587 @@
+use crate::kinematics::CameraFrame;
+use crate::line_format;
+use crate::line_format::FormatError;
+use cglinalg::{
+    Vector3,
+};
+use std::fs::File;
+use std::io;
+use std::io::{
+    BufRead,
+    BufReader,
+    Write,
+};
+use std::path::Path;
+
+
+/// The kind of file the errors from reading and writing timelines name.
+const FILE_KIND: &str = "timeline";
+
+/// How a track interpolates between its keys.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Hold the value of each key until the next key.
+    Step,
+    /// Interpolate linearly between neighboring keys.
+    Linear,
+    /// Interpolate along a Catmull-Rom spline passing through the keys, so that the
+    /// motion has no sudden changes in velocity at the keys.
+    CatmullRom,
+    /// Interpolate orientations along the shortest arc between neighboring keys at a
+    /// constant angular speed.
+    Slerp,
+}
+
+impl Interpolation {
+    fn name(self) -> &'static str {
+        match self {
+            Interpolation::Step => "step",
+            Interpolation::Linear => "linear",
+            Interpolation::CatmullRom => "catmull_rom",
+            Interpolation::Slerp => "slerp",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Interpolation> {
+        match name {
+            "step" => Some(Interpolation::Step),
+            "linear" => Some(Interpolation::Linear),
+            "catmull_rom" => Some(Interpolation::CatmullRom),
+            "slerp" => Some(Interpolation::Slerp),
+            _ => None,
+        }
+    }
+}
+
+/// The quantity in the scene a track animates. Lights and materials are identified by
+/// their index in the scene.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TrackTarget {
+    /// The position of a light in world space.
+    LightPosition(usize),
+    /// The color of a light, normalized to unit luminance.
+    LightColor(usize),
+    /// The luminous intensity of a light in candela, or its illuminance in lux for
+    /// a directional light.
+    LightIntensity(usize),
+    /// The position of the camera in world space.
+    CameraPosition,
+    /// The orientation of the camera as a unit quaternion `(w, x, y, z)`.
+    CameraOrientation,
+    MaterialAmbient(usize),
+    MaterialDiffuse(usize),
+    MaterialSpecular(usize),
+    MaterialSpecularExponent(usize),
+}
+
+impl TrackTarget {
+    /// The number of components in the values of the target.
+    pub fn components(self) -> usize {
+        match self {
+            TrackTarget::LightIntensity(_) => 1,
+            TrackTarget::MaterialSpecularExponent(_) => 1,
+            TrackTarget::CameraOrientation => 4,
+            _ => 3,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            TrackTarget::LightPosition(_) => "light_position",
+            TrackTarget::LightColor(_) => "light_color",
+            TrackTarget::LightIntensity(_) => "light_intensity",
+            TrackTarget::CameraPosition => "camera_position",
+            TrackTarget::CameraOrientation => "camera_orientation",
+            TrackTarget::MaterialAmbient(_) => "material_ambient",
+            TrackTarget::MaterialDiffuse(_) => "material_diffuse",
+            TrackTarget::MaterialSpecular(_) => "material_specular",
+            TrackTarget::MaterialSpecularExponent(_) => "material_specular_exponent",
+        }
+    }
+
+    fn index(self) -> Option<usize> {
+        match self {
+            TrackTarget::LightPosition(index) => Some(index),
+            TrackTarget::LightColor(index) => Some(index),
+            TrackTarget::LightIntensity(index) => Some(index),
+            TrackTarget::CameraPosition => None,
+            TrackTarget::CameraOrientation => None,
+            TrackTarget::MaterialAmbient(index) => Some(index),
+            TrackTarget::MaterialDiffuse(index) => Some(index),
+            TrackTarget::MaterialSpecular(index) => Some(index),
+            TrackTarget::MaterialSpecularExponent(index) => Some(index),
+        }
+    }
+
+    fn from_name(name: &str, index: Option<usize>) -> Option<TrackTarget> {
+        match (name, index) {
+            ("light_position", Some(index)) => Some(TrackTarget::LightPosition(index)),
+            ("light_color", Some(index)) => Some(TrackTarget::LightColor(index)),
+            ("light_intensity", Some(index)) => Some(TrackTarget::LightIntensity(index)),
+            ("camera_position", None) => Some(TrackTarget::CameraPosition),
+            ("camera_orientation", None) => Some(TrackTarget::CameraOrientation),
+            ("material_ambient", Some(index)) => Some(TrackTarget::MaterialAmbient(index)),
+            ("material_diffuse", Some(index)) => Some(TrackTarget::MaterialDiffuse(index)),
+            ("material_specular", Some(index)) => Some(TrackTarget::MaterialSpecular(index)),
+            ("material_specular_exponent", Some(index)) => Some(TrackTarget::MaterialSpecularExponent(index)),
+            _ => None,
+        }
+    }
+
+    /// Whether the target is an orientation, which only interpolates with `Step` or `Slerp`.
+    fn is_orientation(self) -> bool {
+        self == TrackTarget::CameraOrientation
+    }
+}
+
+/// The value of a track at a point in time. Targets with fewer than four components
+/// leave the trailing components at zero.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TrackKey {
+    /// The time in seconds since the start of the timeline.
+    pub time: f32,
+    pub value: [f32; 4],
+}
+
+impl TrackKey {
+    pub fn new(time: f32, value: [f32; 4]) -> TrackKey {
+        TrackKey {
+            time: time,
+            value: value,
+        }
+    }
+}
+
+/// A sequence of keys animating one target.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Track {
+    pub target: TrackTarget,
+    pub interpolation: Interpolation,
+    keys: Vec<TrackKey>,
+}
+
+impl Track {
+    pub fn new(target: TrackTarget, interpolation: Interpolation) -> Track {
+        Track {
+            target: target,
+            interpolation: interpolation,
+            keys: vec![],
+        }
+    }
+
+    /// Insert a key into the track, replacing any key at the same time.
+    pub fn insert(&mut self, key: TrackKey) {
+        match self.keys.binary_search_by(|probe| probe.time.partial_cmp(&key.time).unwrap()) {
+            Ok(index) => self.keys[index] = key,
+            Err(index) => self.keys.insert(index, key),
+        }
+    }
+
+    /// Evaluate the track at the time `time`. Before the first key and after the last
+    /// key, the track holds the value of that key.
+    pub fn sample(&self, time: f32) -> Option<[f32; 4]> {
+        let first = self.keys.first()?;
+        let last = self.keys.last()?;
+        if time <= first.time {
+            return Some(first.value);
+        }
+        if time >= last.time {
+            return Some(last.value);
+        }
+
+        let index = self.keys.iter().rposition(|key| key.time <= time).unwrap();
+        let start = &self.keys[index];
+        let end = &self.keys[index + 1];
+        let t = (time - start.time) / (end.time - start.time);
+        let value = match self.interpolation {
+            Interpolation::Step => start.value,
+            Interpolation::Linear => lerp(&start.value, &end.value, t),
+            Interpolation::Slerp => slerp(&start.value, &end.value, t),
+            Interpolation::CatmullRom => {
+                let previous = &self.keys[index.saturating_sub(1)];
+                let next = &self.keys[usize::min(index + 2, self.keys.len() - 1)];
+                catmull_rom(previous, start, end, next, t)
+            }
+        };
+
+        Some(value)
+    }
+}
+
+fn lerp(start: &[f32; 4], end: &[f32; 4], t: f32) -> [f32; 4] {
+    let mut value = [0.0; 4];
+    for i in 0..4 {
+        value[i] = start[i] + (end[i] - start[i]) * t;
+    }
+
+    value
+}
+
+/// Interpolate between two unit quaternions along the shorter of the two arcs joining
+/// them on the unit sphere.
+fn slerp(start: &[f32; 4], end: &[f32; 4], t: f32) -> [f32; 4] {
+    let mut cos_theta = (0..4).map(|i| start[i] * end[i]).sum::<f32>();
+    let mut end = *end;
+    if cos_theta < 0.0 {
+        cos_theta = -cos_theta;
+        for component in end.iter_mut() {
+            *component = -*component;
+        }
+    }
+    // Nearly parallel quaternions are indistinguishable from a straight line between them.
+    if cos_theta > 0.9995 {
+        return normalize(&lerp(start, &end, t));
+    }
+
+    let theta = cos_theta.acos();
+    let sin_theta = theta.sin();
+    let weight_start = ((1.0 - t) * theta).sin() / sin_theta;
+    let weight_end = (t * theta).sin() / sin_theta;
+    let mut value = [0.0; 4];
+    for i in 0..4 {
+        value[i] = weight_start * start[i] + weight_end * end[i];
+    }
+
+    value
+}
+
+fn normalize(value: &[f32; 4]) -> [f32; 4] {
+    let norm = value.iter().map(|component| component * component).sum::<f32>().sqrt();
+    let mut normalized = [0.0; 4];
+    for i in 0..4 {
+        normalized[i] = value[i] / norm;
+    }
+
+    normalized
+}
+
+/// Evaluate the Catmull-Rom spline through the keys `previous`, `start`, `end`, and `next`
+/// between `start` and `end`. The tangents at the keys account for the spacing of the keys
+/// in time, so unevenly spaced keys do not overshoot.
+fn catmull_rom(previous: &TrackKey, start: &TrackKey, end: &TrackKey, next: &TrackKey, t: f32) -> [f32; 4] {
+    let duration = end.time - start.time;
+    let tangent = |before: &TrackKey, after: &TrackKey, i: usize| {
+        let span = after.time - before.time;
+        if span > 0.0 {
+            (after.value[i] - before.value[i]) / span * duration
+        } else {
+            0.0
+        }
+    };
+
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let h00 = 2.0 * t3 - 3.0 * t2 + 1.0;
+    let h10 = t3 - 2.0 * t2 + t;
+    let h01 = -2.0 * t3 + 3.0 * t2;
+    let h11 = t3 - t2;
+    let mut value = [0.0; 4];
+    for i in 0..4 {
+        let tangent_start = tangent(previous, end, i);
+        let tangent_end = tangent(start, next, i);
+        value[i] = h00 * start.value[i] + h10 * tangent_start + h01 * end.value[i] + h11 * tangent_end;
+    }
+
+    value
+}
+
+/// A unit quaternion `(w, x, y, z)` rotating the camera's local axes into world space.
+fn orientation_from_frame(frame: &CameraFrame) -> [f32; 4] {
+    // The columns of the rotation matrix are the camera's right, up, and backward axes.
+    let backward = -frame.forward;
+    let m = [
+        [frame.right[0], frame.up[0], backward[0]],
+        [frame.right[1], frame.up[1], backward[1]],
+        [frame.right[2], frame.up[2], backward[2]],
+    ];
+    let trace = m[0][0] + m[1][1] + m[2][2];
+    let q = if trace > 0.0 {
+        let s = 2.0 * f32::sqrt(trace + 1.0);
+        [0.25 * s, (m[2][1] - m[1][2]) / s, (m[0][2] - m[2][0]) / s, (m[1][0] - m[0][1]) / s]
+    } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+        let s = 2.0 * f32::sqrt(1.0 + m[0][0] - m[1][1] - m[2][2]);
+        [(m[2][1] - m[1][2]) / s, 0.25 * s, (m[0][1] + m[1][0]) / s, (m[0][2] + m[2][0]) / s]
+    } else if m[1][1] > m[2][2] {
+        let s = 2.0 * f32::sqrt(1.0 + m[1][1] - m[0][0] - m[2][2]);
+        [(m[0][2] - m[2][0]) / s, (m[0][1] + m[1][0]) / s, 0.25 * s, (m[1][2] + m[2][1]) / s]
+    } else {
+        let s = 2.0 * f32::sqrt(1.0 + m[2][2] - m[0][0] - m[1][1]);
+        [(m[1][0] - m[0][1]) / s, (m[0][2] + m[2][0]) / s, (m[1][2] + m[2][1]) / s, 0.25 * s]
+    };
+
+    normalize(&q)
+}
+
+/// Rotate the vector `v` by the unit quaternion `q = (w, x, y, z)`.
+fn rotate(q: &[f32; 4], v: &Vector3<f32>) -> Vector3<f32> {
+    let u = Vector3::new(q[1], q[2], q[3]);
+    let w = q[0];
+    let uv = u.cross(v);
+    let uuv = u.cross(&uv);
+
+    *v + uv * (2.0 * w) + uuv * 2.0
+}
+
+/// A collection of tracks animating the scene over time, with the controls of a
+/// media player.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timeline {
+    tracks: Vec<Track>,
+    /// The length of the timeline in seconds. Playback loops back to the start when
+    /// it reaches the end.
+    duration: f32,
+    time: f32,
+    playing: bool,
+}
+
+impl Timeline {
+    pub fn new(duration: f32) -> Timeline {
+        Timeline {
+            tracks: vec![],
+            duration: duration,
+            time: 0.0,
+            playing: false,
+        }
+    }
+
+    #[inline]
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    #[inline]
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn toggle_playback(&mut self) {
+        self.playing = !self.playing;
+    }
+
+    /// Move the playhead by `delta_seconds`, clamped to the extent of the timeline.
+    pub fn scrub(&mut self, delta_seconds: f32) {
+        self.seek(self.time + delta_seconds);
+    }
+
+    /// Move the playhead to the time `time`, clamped to the extent of the timeline.
+    pub fn seek(&mut self, time: f32) {
+        self.time = time.max(0.0).min(self.duration);
+    }
+
+    /// Advance the playhead by `elapsed_seconds` if the timeline is playing.
+    pub fn update(&mut self, elapsed_seconds: f32) {
+        if !self.playing {
+            return;
+        }
+
+        self.time += elapsed_seconds;
+        if self.time > self.duration {
+            self.time = if self.duration > 0.0 { self.time % self.duration } else { 0.0 };
+        }
+    }
+
+    /// The track animating `target`, if there is one.
+    pub fn track(&self, target: TrackTarget) -> Option<&Track> {
+        self.tracks.iter().find(|track| track.target == target)
+    }
+
+    /// Evaluate the track animating `target` at the playhead.
+    pub fn sample(&self, target: TrackTarget) -> Option<[f32; 4]> {
+        self.track(target).and_then(|track| track.sample(self.time))
+    }
+
+    /// Evaluate the track animating a three component target at the playhead.
+    pub fn sample_vector(&self, target: TrackTarget) -> Option<Vector3<f32>> {
+        self.sample(target).map(|value| Vector3::new(value[0], value[1], value[2]))
+    }
+
+    /// Evaluate the track animating a scalar target at the playhead.
+    pub fn sample_scalar(&self, target: TrackTarget) -> Option<f32> {
+        self.sample(target).map(|value| value[0])
+    }
+
+    /// Evaluate the camera tracks at the playhead. The camera keeps the parts of the
+    /// pose in `camera` that the timeline does not animate.
+    pub fn sample_camera(&self, camera: &CameraFrame) -> Option<CameraFrame> {
+        let position = self.sample_vector(TrackTarget::CameraPosition);
+        let orientation = self.sample(TrackTarget::CameraOrientation);
+        if position.is_none() && orientation.is_none() {
+            return None;
+        }
+
+        let position = position.unwrap_or(camera.position);
+        let orientation = orientation.map_or_else(|| orientation_from_frame(camera), |q| normalize(&q));
+
+        Some(CameraFrame {
+            position: position,
+            right: rotate(&orientation, &Vector3::unit_x()),
+            up: rotate(&orientation, &Vector3::unit_y()),
+            forward: -rotate(&orientation, &Vector3::unit_z()),
+        })
+    }
+
+    /// Find the track animating `target`, creating an empty one with the interpolation
+    /// mode `interpolation` if there is none.
+    pub fn track_mut(&mut self, target: TrackTarget, interpolation: Interpolation) -> &mut Track {
+        match self.tracks.iter().position(|track| track.target == target) {
+            Some(index) => &mut self.tracks[index],
+            None => {
+                self.tracks.push(Track::new(target, interpolation));
+                self.tracks.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// Record the pose of the camera as a key at the playhead. The timeline grows to
+    /// include the key if necessary.
+    pub fn insert_camera_key(&mut self, camera: &CameraFrame) {
+        let time = self.time;
+        let position = camera.position;
+        let orientation = orientation_from_frame(camera);
+        self.track_mut(TrackTarget::CameraPosition, Interpolation::CatmullRom)
+            .insert(TrackKey::new(time, [position[0], position[1], position[2], 0.0]));
+        self.track_mut(TrackTarget::CameraOrientation, Interpolation::Slerp)
+            .insert(TrackKey::new(time, orientation));
+        self.duration = self.duration.max(time);
+    }
+
+    /// Load a timeline from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Timeline, FormatError> {
+        let file = File::open(&path).map_err(|e| FormatError::io(FILE_KIND, &path, e))?;
+        Timeline::parse(BufReader::new(file))
+    }
+
+    /// Save the timeline to a file in the format `Timeline::parse` reads.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), FormatError> {
+        let mut file = File::create(&path).map_err(|e| FormatError::io(FILE_KIND, &path, e))?;
+        self.write(&mut file).map_err(|e| FormatError::io(FILE_KIND, &path, e))
+    }
+
+    /// Parse a timeline from the statements `line_format::read_statements` reads. The
+    /// first statement gives the length of the timeline, and each track follows as a
+    /// header naming its target and interpolation mode and a list of keys holding the
+    /// time followed by the value of the key.
+    /// ```text
+    /// duration 12.0
+    /// track light_position 2 catmull_rom
+    /// key 0.0 0.0 5.0 5.0
+    /// key 6.0 4.0 2.0 2.0
+    /// track camera_orientation slerp
+    /// key 0.0 1.0 0.0 0.0 0.0
+    /// ```
+    pub fn parse<R: BufRead>(reader: R) -> Result<Timeline, FormatError> {
+        let mut timeline: Option<Timeline> = None;
+        line_format::read_statements(reader, FILE_KIND, |line_number, words| {
+            let error = |message: &str| FormatError::new(FILE_KIND, line_number, String::from(message));
+            let parse_f32 = |word: &str| {
+                word.parse::<f32>().map_err(|_| error(&format!("Expected a number but got `{}`.", word)))
+            };
+            if words[0] == "duration" {
+                if timeline.is_some() {
+                    return Err(error("The timeline has more than one duration."));
+                }
+                if words.len() != 2 {
+                    return Err(error("Expected `duration <seconds>`."));
+                }
+                let duration = parse_f32(words[1])?;
+                if !(duration.is_finite() && duration >= 0.0) {
+                    return Err(error("The duration must be finite and non-negative."));
+                }
+                timeline = Some(Timeline::new(duration));
+                return Ok(());
+            }
+
+            let timeline = timeline.as_mut()
+                .ok_or_else(|| error("The timeline must start with its duration."))?;
+            match words[0] {
+                "track" => {
+                    let (index, interpolation) = match words.len() {
+                        3 => (None, words[2]),
+                        4 => {
+                            let index = words[2].parse::<usize>()
+                                .map_err(|_| error(&format!("Expected an index but got `{}`.", words[2])))?;
+                            (Some(index), words[3])
+                        }
+                        _ => return Err(error("Expected `track <target> [<index>] <interpolation>`.")),
+                    };
+                    let target = TrackTarget::from_name(words[1], index)
+                        .ok_or_else(|| error(&format!("Unknown track target `{}`.", words[1])))?;
+                    let interpolation = Interpolation::from_name(interpolation)
+                        .ok_or_else(|| error(&format!("Unknown interpolation mode `{}`.", interpolation)))?;
+                    let slerp = interpolation == Interpolation::Slerp;
+                    let step = interpolation == Interpolation::Step;
+                    if target.is_orientation() && !(slerp || step) {
+                        return Err(error("Orientation tracks interpolate with `step` or `slerp`."));
+                    }
+                    if !target.is_orientation() && slerp {
+                        return Err(error("Only orientation tracks interpolate with `slerp`."));
+                    }
+                    if timeline.track(target).is_some() {
+                        return Err(error(&format!("The timeline has more than one `{}` track.", words[1])));
+                    }
+                    timeline.tracks.push(Track::new(target, interpolation));
+                }
+                "key" => {
+                    let track = timeline.tracks.last_mut()
+                        .ok_or_else(|| error("Found a key before the first track."))?;
+                    let components = track.target.components();
+                    if words.len() != components + 2 {
+                        return Err(error(&format!("Expected a time and {} values.", components)));
+                    }
+                    let time = parse_f32(words[1])?;
+                    if !(time.is_finite() && time >= 0.0) {
+                        return Err(error("The time of a key must be finite and non-negative."));
+                    }
+                    let mut value = [0.0; 4];
+                    for (i, &word) in words[2..].iter().enumerate() {
+                        value[i] = parse_f32(word)?;
+                        if !value[i].is_finite() {
+                            return Err(error("The values of a key must be finite."));
+                        }
+                    }
+                    // Orientation keys are unit quaternions, and a zero quaternion has no
+                    // direction to scale to unit length.
+                    if track.target.is_orientation() {
+                        if value.iter().all(|&component| component == 0.0) {
+                            return Err(error("An orientation key must not be zero."));
+                        }
+                        value = normalize(&value);
+                    }
+                    track.insert(TrackKey::new(time, value));
+                    // Like a recorded camera key, a key past the end lengthens the timeline.
+                    timeline.duration = timeline.duration.max(time);
+                }
+                statement => {
+                    return Err(error(&format!("Unknown statement `{}`.", statement)));
+                }
+            }
+
+            Ok(())
+        })?;
+
+        timeline.ok_or_else(|| FormatError::new(FILE_KIND, 0, String::from("The timeline is empty.")))
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "duration {}", self.duration)?;
+        for track in self.tracks.iter() {
+            match track.target.index() {
+                Some(index) => {
+                    writeln!(writer, "track {} {} {}", track.target.name(), index, track.interpolation.name())?;
+                }
+                None => {
+                    writeln!(writer, "track {} {}", track.target.name(), track.interpolation.name())?;
+                }
+            }
+            for key in track.keys.iter() {
+                write!(writer, "key {}", key.time)?;
+                for component in key.value[..track.target.components()].iter() {
+                    write!(writer, " {}", component)?;
+                }
+                writeln!(writer)?;
+            }
+        }
+
+        Ok(())
+    }
+}
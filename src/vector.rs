@@ -0,0 +1,44 @@
+#[inline]
+pub(crate) fn add(v1: [f32; 3], v2: [f32; 3]) -> [f32; 3] {
+    [v1[0] + v2[0], v1[1] + v2[1], v1[2] + v2[2]]
+}
+
+#[inline]
+pub(crate) fn sub(v1: [f32; 3], v2: [f32; 3]) -> [f32; 3] {
+    [v1[0] - v2[0], v1[1] - v2[1], v1[2] - v2[2]]
+}
+
+#[inline]
+pub(crate) fn scale(v: [f32; 3], factor: f32) -> [f32; 3] {
+    [factor * v[0], factor * v[1], factor * v[2]]
+}
+
+#[inline]
+pub(crate) fn cross(v1: [f32; 3], v2: [f32; 3]) -> [f32; 3] {
+    [
+        v1[1] * v2[2] - v1[2] * v2[1],
+        v1[2] * v2[0] - v1[0] * v2[2],
+        v1[0] * v2[1] - v1[1] * v2[0],
+    ]
+}
+
+#[inline]
+pub(crate) fn dot(v1: [f32; 3], v2: [f32; 3]) -> f32 {
+    v1[0] * v2[0] + v1[1] * v2[1] + v1[2] * v2[2]
+}
+
+#[inline]
+pub(crate) fn length(v: [f32; 3]) -> f32 {
+    dot(v, v).sqrt()
+}
+
+/// Scale a vector to unit length, unless it is too short to have a direction.
+#[inline]
+pub(crate) fn normalize(v: [f32; 3]) -> Option<[f32; 3]> {
+    let length = length(v);
+    if length > f32::EPSILON {
+        Some([v[0] / length, v[1] / length, v[2] / length])
+    } else {
+        None
+    }
+}